@@ -0,0 +1,162 @@
+//! Access control for the MCP HTTP transport.
+//!
+//! Requests to `/mcp` can be required to carry a bearer token, and requests
+//! sent by a browser must come from an allowed Origin. The Origin check stops
+//! DNS-rebinding attacks, where a web page makes a visitor's browser talk to a
+//! server on their machine or network.
+//!
+//! Clients may reach the server over the network, so file paths they send
+//! are resolved inside a fixed directory with [`client_path`].
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::proxy::Secret;
+
+#[derive(Debug, Clone, Default)]
+pub struct AccessPolicy {
+    /// Token clients must send as `Authorization: Bearer <token>`.
+    pub token: Option<Secret>,
+    /// Origins allowed besides loopback ones, e.g. "https://agent.example.com".
+    /// "*" allows any origin.
+    pub allowed_origins: Vec<String>,
+}
+
+/// Why a request was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Denied {
+    MissingToken,
+    InvalidToken,
+    Origin(String),
+}
+
+impl Denied {
+    pub fn status(&self) -> u16 {
+        match self {
+            Denied::MissingToken | Denied::InvalidToken => 401,
+            Denied::Origin(_) => 403,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Denied::MissingToken => "Missing bearer token (Authorization: Bearer <token>)".into(),
+            Denied::InvalidToken => "Invalid bearer token".into(),
+            Denied::Origin(origin) => format!("Origin '{origin}' is not allowed"),
+        }
+    }
+}
+
+impl AccessPolicy {
+    /// Check a request's `Authorization` and `Origin` headers. Requests
+    /// without an Origin (non-browser clients) only need the token.
+    pub fn check(&self, authorization: Option<&str>, origin: Option<&str>) -> Result<(), Denied> {
+        if let Some(origin) = origin {
+            if !self.origin_allowed(origin) {
+                return Err(Denied::Origin(origin.to_string()));
+            }
+        }
+        if let Some(token) = &self.token {
+            let presented = authorization
+                .and_then(|value| value.trim().split_once(' '))
+                // The auth scheme is case-insensitive (RFC 9110 section 11.1).
+                .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Bearer"))
+                .map(|(_, token)| token.trim())
+                .ok_or(Denied::MissingToken)?;
+            if !constant_time_eq(presented.as_bytes(), token.expose().as_bytes()) {
+                return Err(Denied::InvalidToken);
+            }
+        }
+        Ok(())
+    }
+
+    fn origin_allowed(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/');
+        is_loopback_origin(origin)
+            || self.allowed_origins.iter().any(|allowed| {
+                allowed == "*" || allowed.trim_end_matches('/').eq_ignore_ascii_case(origin)
+            })
+    }
+}
+
+/// `http(s)://localhost`, `127.0.0.1` or `[::1]`, on any port.
+pub fn is_loopback_origin(origin: &str) -> bool {
+    let Some((scheme, rest)) = origin.split_once("://") else {
+        return false;
+    };
+    if scheme != "http" && scheme != "https" {
+        return false;
+    }
+    let host = match rest.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or_default(),
+        None => rest.split(':').next().unwrap_or_default(),
+    };
+    host.eq_ignore_ascii_case("localhost") || host == "127.0.0.1" || host == "::1"
+}
+
+/// Resolve the token from `--token` or the first line of `--token-file`.
+pub fn load_token(token: Option<String>, file: Option<&Path>) -> anyhow::Result<Option<Secret>> {
+    let token = match (token, file) {
+        (Some(token), _) => token,
+        (None, Some(path)) => std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read token file {}: {e}", path.display()))?
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        (None, None) => return Ok(None),
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        anyhow::bail!("Bearer token is empty");
+    }
+    Ok(Some(Secret::new(token)))
+}
+
+/// Resolve a file path sent by an MCP client inside `base`, creating its
+/// parent directories. Only relative paths without `..` are accepted.
+pub fn client_path(base: &Path, path: &str) -> anyhow::Result<PathBuf> {
+    let relative = Path::new(path);
+    let plain = relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if path.is_empty() || !plain || relative.file_name().is_none() {
+        anyhow::bail!(
+            "Invalid path '{path}': give a file name relative to {}, without '..'",
+            base.display()
+        );
+    }
+    let full = base.join(relative);
+    if let Some(parent) = full.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(full)
+}
+
+/// Open `path` for writing. An existing file is only replaced when
+/// `overwrite` is set.
+pub fn create_file(path: &Path, overwrite: bool) -> anyhow::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    options.open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => {
+            anyhow::anyhow!("{} already exists and was left unchanged.", path.display())
+        }
+        _ => anyhow::anyhow!("Cannot write {}: {e}", path.display()),
+    })
+}
+
+/// Compare without returning early, so timing does not reveal how much of a
+/// guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
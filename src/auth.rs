@@ -1,9 +1,14 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
+use crate::policy;
 use crate::recording::{extract_domain, now_timestamp, sanitize_filename};
+use crate::totp::TotpSecret;
+use crate::vault::{self, AuthKey, Envelope};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredCookie {
@@ -17,13 +22,89 @@ pub struct StoredCookie {
     pub same_site: Option<String>,
 }
 
+/// Current profile format. Version 1 held cookies only; version 2 adds
+/// per-origin web storage; version 3 adds the TOTP seed.
+pub const PROFILE_VERSION: u32 = 3;
+
+fn legacy_version() -> u32 {
+    1
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthProfile {
+    /// Format version; files written before versioning read as 1.
+    #[serde(default = "legacy_version")]
+    pub version: u32,
     pub profile: String,
     pub domain: String,
     pub url: String,
     pub saved_at: String,
     pub cookies: Vec<StoredCookie>,
+    /// Web storage captured alongside the cookies, one entry per origin.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub origins: Vec<OriginStorage>,
+    /// 2FA seed of the account; only ever written to encrypted profiles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp: Option<TotpSecret>,
+}
+
+/// `localStorage`, `sessionStorage` and IndexedDB contents of one origin.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OriginStorage {
+    /// e.g. "https://app.example.com"
+    pub origin: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub local_storage: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub session_storage: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indexed_db: Vec<IndexedDbDatabase>,
+}
+
+impl OriginStorage {
+    pub fn is_empty(&self) -> bool {
+        self.local_storage.is_empty()
+            && self.session_storage.is_empty()
+            && self.indexed_db.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedDbDatabase {
+    pub name: String,
+    pub version: u64,
+    pub stores: Vec<IndexedDbStore>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedDbStore {
+    pub name: String,
+    /// String or array of strings; `None` for out-of-line keys.
+    #[serde(default)]
+    pub key_path: Option<serde_json::Value>,
+    #[serde(default)]
+    pub auto_increment: bool,
+    #[serde(default)]
+    pub indexes: Vec<IndexedDbIndex>,
+    /// Records with JSON-compatible values; others are skipped at capture.
+    #[serde(default)]
+    pub records: Vec<IndexedDbRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedDbIndex {
+    pub name: String,
+    pub key_path: serde_json::Value,
+    #[serde(default)]
+    pub unique: bool,
+    #[serde(default)]
+    pub multi_entry: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedDbRecord {
+    pub key: serde_json::Value,
+    pub value: serde_json::Value,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -31,20 +112,107 @@ pub struct AuthSummary {
     pub profile: String,
     pub domain: String,
     pub cookie_count: usize,
+    /// Origins with saved web storage.
+    pub storage_origins: usize,
     pub saved_at: String,
+    /// Encrypted with a key that is not configured, so the cookies could not be counted.
+    pub locked: bool,
+    /// Holds a TOTP seed for `totp_code`.
+    pub has_totp: bool,
+    /// Earliest and latest expiry of the cookies that have one, in Unix seconds.
+    pub earliest_expiry: Option<f64>,
+    pub latest_expiry: Option<f64>,
+    /// Cookies whose expiry has passed.
+    pub expired_count: usize,
+    /// Every cookie has expired and there is no web storage that might still
+    /// hold a session, so restoring the profile cannot log anyone in.
+    pub fully_expired: bool,
 }
 
-impl From<&AuthProfile> for AuthSummary {
-    fn from(p: &AuthProfile) -> Self {
+impl AuthSummary {
+    /// Summarize `p` as of `now` (Unix seconds).
+    pub fn at(p: &AuthProfile, now: f64) -> Self {
+        let expiries: Vec<f64> = p.cookies.iter().filter_map(|c| c.expires).collect();
+        let expired_count = expiries.iter().filter(|&&e| e <= now).count();
         Self {
             profile: p.profile.clone(),
             domain: p.domain.clone(),
             cookie_count: p.cookies.len(),
+            storage_origins: p.origins.len(),
             saved_at: p.saved_at.clone(),
+            locked: false,
+            has_totp: p.totp.is_some(),
+            earliest_expiry: expiries.iter().copied().reduce(f64::min),
+            latest_expiry: expiries.iter().copied().reduce(f64::max),
+            expired_count,
+            fully_expired: !p.cookies.is_empty()
+                && expired_count == p.cookies.len()
+                && p.origins.is_empty(),
+        }
+    }
+}
+
+impl From<&AuthProfile> for AuthSummary {
+    fn from(p: &AuthProfile) -> Self {
+        Self::at(p, now_secs())
+    }
+}
+
+impl From<&vault::Metadata> for AuthSummary {
+    fn from(m: &vault::Metadata) -> Self {
+        Self {
+            profile: m.profile.clone(),
+            domain: m.domain.clone(),
+            cookie_count: 0,
+            storage_origins: 0,
+            saved_at: m.saved_at.clone(),
+            locked: true,
+            has_totp: false,
+            earliest_expiry: None,
+            latest_expiry: None,
+            expired_count: 0,
+            fully_expired: false,
         }
     }
 }
 
+/// Current time in Unix seconds, the unit of cookie expiry.
+pub fn now_secs() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// Describe when a cookie expiry falls relative to `now`, e.g. "in 3d" or
+/// "5h ago".
+pub fn describe_expiry(expires: f64, now: f64) -> String {
+    let delta = expires - now;
+    let secs = delta.abs() as u64;
+    let span = match secs {
+        0..=119 => format!("{secs}s"),
+        120..=7199 => format!("{}m", secs / 60),
+        7200..=172_799 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    };
+    if delta >= 0.0 {
+        format!("in {span}")
+    } else {
+        format!("{span} ago")
+    }
+}
+
+/// File in the auth directory holding host → profile bindings.
+const BINDINGS_FILE: &str = "bindings.json";
+
+/// Default profile for a host pattern, restored automatically on navigation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthBinding {
+    pub profile: String,
+    /// Domain directory the profile is saved under, e.g. "github-com".
+    pub domain: String,
+}
+
 fn auth_dir() -> PathBuf {
     dirs::home_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -54,6 +222,8 @@ fn auth_dir() -> PathBuf {
 
 pub struct AuthStore {
     base: PathBuf,
+    /// Encrypts profiles at rest when set.
+    key: Option<AuthKey>,
 }
 
 impl Default for AuthStore {
@@ -64,11 +234,22 @@ impl Default for AuthStore {
 
 impl AuthStore {
     pub fn new() -> Self {
-        Self { base: auth_dir() }
+        Self::with_base(auth_dir())
     }
 
     pub fn with_base(base: PathBuf) -> Self {
-        Self { base }
+        Self { base, key: None }
+    }
+
+    /// Encrypt saved profiles with `key` (see `vault`). Plaintext profiles
+    /// saved earlier can still be loaded.
+    pub fn with_key(mut self, key: Option<AuthKey>) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
     }
 
     pub fn save(
@@ -76,64 +257,141 @@ impl AuthStore {
         url: &str,
         profile_name: &str,
         cookies: Vec<StoredCookie>,
+    ) -> anyhow::Result<PathBuf> {
+        self.save_with_storage(url, profile_name, cookies, Vec::new())
+    }
+
+    /// Save cookies together with the web storage of one or more origins.
+    pub fn save_with_storage(
+        &self,
+        url: &str,
+        profile_name: &str,
+        cookies: Vec<StoredCookie>,
+        origins: Vec<OriginStorage>,
     ) -> anyhow::Result<PathBuf> {
         let domain = extract_domain(url);
         let dir = self.base.join(&domain);
         fs::create_dir_all(&dir)?;
 
+        let filename = format!("{}.json", sanitize_filename(profile_name));
+        let path = dir.join(&filename);
+        // Re-saving a login keeps the TOTP seed set up for it. A profile that
+        // cannot be read is left alone rather than replaced without its seed.
+        let totp = match self.key {
+            Some(_) if path.exists() => {
+                self.read(&path)
+                    .map_err(|e| {
+                        anyhow::anyhow!(
+                            "Cannot re-save auth profile '{profile_name}', the saved one could not be read: {e}"
+                        )
+                    })?
+                    .totp
+            }
+            _ => None,
+        };
+
         let profile = AuthProfile {
+            version: PROFILE_VERSION,
             profile: profile_name.to_string(),
             domain: domain.clone(),
             url: url.to_string(),
             saved_at: now_timestamp(),
             cookies,
+            origins: origins.into_iter().filter(|o| !o.is_empty()).collect(),
+            totp,
         };
 
-        let filename = format!("{}.json", sanitize_filename(profile_name));
-        let path = dir.join(&filename);
-        let json = serde_json::to_string_pretty(&profile)?;
-        fs::write(&path, json)?;
+        write_profile(&path, &profile, self.key.as_ref())?;
         Ok(path)
     }
 
-    pub fn load(&self, profile_name: &str, domain: Option<&str>) -> anyhow::Result<AuthProfile> {
-        let filename = format!("{}.json", sanitize_filename(profile_name));
-
-        if let Some(d) = domain {
-            let path = self.base.join(d).join(&filename);
-            let json = fs::read_to_string(&path).map_err(|_| {
-                anyhow::anyhow!(
-                    "Auth profile '{}' not found in domain '{}'",
-                    profile_name,
-                    d
-                )
-            })?;
-            let profile: AuthProfile = serde_json::from_str(&json)?;
-            return Ok(profile);
+    /// Store `totp` in a saved profile, or remove its seed with `None`.
+    /// Seeds are second factors, so they are only stored encrypted.
+    pub fn set_totp(
+        &self,
+        profile_name: &str,
+        domain: Option<&str>,
+        totp: Option<TotpSecret>,
+    ) -> anyhow::Result<AuthProfile> {
+        if totp.is_some() && self.key.is_none() {
+            anyhow::bail!(
+                "TOTP seeds are only stored in encrypted profiles; set {} or {}",
+                vault::KEY_FILE_ENV,
+                vault::PASSPHRASE_ENV
+            );
         }
-
-        if self.base.exists() {
-            for entry in fs::read_dir(&self.base)? {
-                let entry = entry?;
-                if entry.file_type()?.is_dir() {
-                    let path = entry.path().join(&filename);
-                    if path.exists() {
-                        let json = fs::read_to_string(&path)?;
-                        let profile: AuthProfile = serde_json::from_str(&json)?;
-                        return Ok(profile);
-                    }
-                }
-            }
+        if let Some(totp) = &totp {
+            totp.validate()?;
         }
+        let path = self.find(profile_name, domain)?;
+        let mut profile = self.read(&path)?;
+        profile.version = PROFILE_VERSION;
+        profile.totp = totp;
+        write_profile(&path, &profile, self.key.as_ref())?;
+        Ok(profile)
+    }
 
-        anyhow::bail!("Auth profile '{}' not found", profile_name)
+    /// The current TOTP code of a profile and the seconds it stays valid.
+    pub fn totp_code(
+        &self,
+        profile_name: &str,
+        domain: Option<&str>,
+    ) -> anyhow::Result<(String, u64)> {
+        let profile = self.load(profile_name, domain)?;
+        let totp = profile
+            .totp
+            .ok_or_else(|| anyhow::anyhow!("Auth profile '{profile_name}' has no TOTP seed"))?;
+        let now = now_secs() as u64;
+        Ok((totp.code_at(now)?, totp.remaining(now)))
     }
 
-    pub fn list(&self, domain: Option<&str>) -> anyhow::Result<Vec<AuthSummary>> {
-        let mut summaries = Vec::new();
+    /// Read a profile file, decrypting it if needed.
+    fn read(&self, path: &Path) -> anyhow::Result<AuthProfile> {
+        let contents = fs::read_to_string(path)?;
+        let Some(envelope) = Envelope::parse(&contents) else {
+            return parse_profile(contents.as_bytes());
+        };
+        let key = self.key.as_ref().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Auth profile '{}' is encrypted; set {} or {}",
+                envelope.metadata.profile,
+                vault::PASSPHRASE_ENV,
+                vault::KEY_FILE_ENV
+            )
+        })?;
+        let plaintext = vault::decrypt(key, &envelope).map_err(|e| {
+            anyhow::anyhow!(
+                "Cannot decrypt auth profile '{}': {e}",
+                envelope.metadata.profile
+            )
+        })?;
+        parse_profile(&plaintext)
+    }
 
+    /// Re-encrypt every profile with `new_key`, returning how many were
+    /// written. All profiles are read first, so a profile the current key
+    /// cannot open aborts before anything changes.
+    pub fn rekey(&self, new_key: &AuthKey) -> anyhow::Result<usize> {
+        let profiles = self
+            .profile_paths(None)?
+            .into_iter()
+            .map(|path| {
+                let profile = self
+                    .read(&path)
+                    .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+                Ok((path, profile))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for (path, profile) in &profiles {
+            write_profile(path, profile, Some(new_key))?;
+        }
+        Ok(profiles.len())
+    }
+
+    /// Profile files under `domain`, or under every domain.
+    fn profile_paths(&self, domain: Option<&str>) -> anyhow::Result<Vec<PathBuf>> {
         if !self.base.exists() {
-            return Ok(summaries);
+            return Ok(Vec::new());
         }
 
         let dirs: Vec<PathBuf> = if let Some(d) = domain {
@@ -151,38 +409,36 @@ impl AuthStore {
                 .collect()
         };
 
+        let mut paths = Vec::new();
         for dir in dirs {
             for entry in fs::read_dir(&dir)? {
-                let entry = entry?;
-                let path = entry.path();
+                let path = entry?.path();
                 if path.extension().and_then(|e| e.to_str()) == Some("json") {
-                    if let Ok(json) = fs::read_to_string(&path) {
-                        if let Ok(profile) = serde_json::from_str::<AuthProfile>(&json) {
-                            summaries.push(AuthSummary::from(&profile));
-                        }
-                    }
+                    paths.push(path);
                 }
             }
         }
+        Ok(paths)
+    }
 
-        summaries.sort_by(|a, b| a.profile.cmp(&b.profile));
-        Ok(summaries)
+    pub fn load(&self, profile_name: &str, domain: Option<&str>) -> anyhow::Result<AuthProfile> {
+        self.read(&self.find(profile_name, domain)?)
     }
 
-    pub fn delete(&self, profile_name: &str, domain: Option<&str>) -> anyhow::Result<()> {
+    /// Path of a saved profile, searching every domain when `domain` is `None`.
+    fn find(&self, profile_name: &str, domain: Option<&str>) -> anyhow::Result<PathBuf> {
         let filename = format!("{}.json", sanitize_filename(profile_name));
 
         if let Some(d) = domain {
             let path = self.base.join(d).join(&filename);
-            if path.exists() {
-                fs::remove_file(&path)?;
-                return Ok(());
+            if !path.exists() {
+                anyhow::bail!(
+                    "Auth profile '{}' not found in domain '{}'",
+                    profile_name,
+                    d
+                );
             }
-            anyhow::bail!(
-                "Auth profile '{}' not found in domain '{}'",
-                profile_name,
-                d
-            );
+            return Ok(path);
         }
 
         if self.base.exists() {
@@ -191,8 +447,7 @@ impl AuthStore {
                 if entry.file_type()?.is_dir() {
                     let path = entry.path().join(&filename);
                     if path.exists() {
-                        fs::remove_file(&path)?;
-                        return Ok(());
+                        return Ok(path);
                     }
                 }
             }
@@ -200,4 +455,167 @@ impl AuthStore {
 
         anyhow::bail!("Auth profile '{}' not found", profile_name)
     }
+
+    pub fn list(&self, domain: Option<&str>) -> anyhow::Result<Vec<AuthSummary>> {
+        let mut summaries = Vec::new();
+
+        for path in self.profile_paths(domain)? {
+            if let Ok(profile) = self.read(&path) {
+                summaries.push(AuthSummary::from(&profile));
+            } else if let Some(envelope) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|contents| Envelope::parse(&contents))
+            {
+                summaries.push(AuthSummary::from(&envelope.metadata));
+            }
+        }
+
+        summaries.sort_by(|a, b| a.profile.cmp(&b.profile));
+        Ok(summaries)
+    }
+
+    pub fn delete(&self, profile_name: &str, domain: Option<&str>) -> anyhow::Result<()> {
+        fs::remove_file(self.find(profile_name, domain)?)?;
+        Ok(())
+    }
+
+    /// Make `profile` the default for `host`, so navigating there restores it
+    /// automatically. `host` is a domain policy pattern: a host, which also
+    /// covers its subdomains, or a glob with `*`.
+    pub fn bind(
+        &self,
+        host: &str,
+        profile_name: &str,
+        domain: Option<&str>,
+    ) -> anyhow::Result<AuthBinding> {
+        let host = host.trim().to_ascii_lowercase();
+        if host.is_empty() {
+            anyhow::bail!("Host is empty");
+        }
+        let path = self.find(profile_name, domain)?;
+        let domain = path
+            .parent()
+            .and_then(|d| d.file_name())
+            .map(|d| d.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let binding = AuthBinding {
+            profile: profile_name.to_string(),
+            domain,
+        };
+        let mut bindings = self.bindings()?;
+        bindings.insert(host, binding.clone());
+        self.write_bindings(&bindings)?;
+        Ok(binding)
+    }
+
+    /// Remove the binding for `host`, returning whether there was one.
+    pub fn unbind(&self, host: &str) -> anyhow::Result<bool> {
+        let mut bindings = self.bindings()?;
+        let removed = bindings.remove(&host.trim().to_ascii_lowercase()).is_some();
+        if removed {
+            self.write_bindings(&bindings)?;
+        }
+        Ok(removed)
+    }
+
+    /// Host pattern → profile bindings.
+    pub fn bindings(&self) -> anyhow::Result<BTreeMap<String, AuthBinding>> {
+        let path = self.base.join(BINDINGS_FILE);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let contents = fs::read_to_string(&path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {e}", path.display()))
+    }
+
+    /// The binding covering `host`, preferring the most specific (longest)
+    /// pattern.
+    pub fn binding_for(&self, host: &str) -> anyhow::Result<Option<(String, AuthBinding)>> {
+        if host.is_empty() {
+            return Ok(None);
+        }
+        Ok(self
+            .bindings()?
+            .into_iter()
+            .filter(|(pattern, _)| policy::host_pattern_matches(pattern, host))
+            .max_by_key(|(pattern, _)| pattern.len()))
+    }
+
+    fn write_bindings(&self, bindings: &BTreeMap<String, AuthBinding>) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.base)?;
+        write_private(
+            &self.base.join(BINDINGS_FILE),
+            &serde_json::to_string_pretty(bindings)?,
+        )
+    }
+}
+
+/// Parse a decrypted profile, refusing formats newer than this build knows.
+fn parse_profile(json: &[u8]) -> anyhow::Result<AuthProfile> {
+    let profile: AuthProfile = serde_json::from_slice(json)?;
+    if profile.version > PROFILE_VERSION {
+        anyhow::bail!(
+            "Auth profile '{}' uses format version {}, but this build reads up to version {PROFILE_VERSION}",
+            profile.profile,
+            profile.version
+        );
+    }
+    Ok(profile)
+}
+
+/// Write a profile, encrypted when `key` is set.
+fn write_profile(path: &Path, profile: &AuthProfile, key: Option<&AuthKey>) -> anyhow::Result<()> {
+    let json = serde_json::to_string_pretty(profile)?;
+    let contents = match key {
+        Some(key) => {
+            let metadata = vault::Metadata {
+                profile: profile.profile.clone(),
+                domain: profile.domain.clone(),
+                saved_at: profile.saved_at.clone(),
+            };
+            serde_json::to_string_pretty(&vault::encrypt(key, metadata, json.as_bytes())?)?
+        }
+        None => json,
+    };
+
+    write_private(path, &contents)
+}
+
+/// Write a file readable only by its owner, replacing it atomically so an
+/// interrupted write never leaves it truncated.
+pub fn write_private(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&tmp)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Write a new file readable only by its owner, refusing to replace an
+/// existing one.
+pub fn create_private(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => {
+            anyhow::anyhow!("{} already exists and was left unchanged.", path.display())
+        }
+        _ => anyhow::anyhow!("Cannot write {}: {e}", path.display()),
+    })?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    Ok(())
 }
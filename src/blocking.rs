@@ -0,0 +1,130 @@
+//! Request blocklist for ads, trackers and heavy resources.
+//!
+//! A `BlockList` combines URL globs, host names and CDP resource types. It is
+//! evaluated for every request paused by Fetch interception (see `intercept`),
+//! so blocked requests never reach the network and never add noise refs to
+//! snapshots.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::network::{glob_match, url_host};
+
+/// Tracker/ad hosts bundled with the binary, enabled by `--block-trackers`.
+pub const BUNDLED_TRACKERS: &str = include_str!("../assets/trackers.txt");
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockList {
+    /// URL globs (`*` wildcard), e.g. `*://*/ads/*`.
+    pub url_patterns: Vec<String>,
+    /// Hosts whose requests are blocked, including their subdomains.
+    pub hosts: Vec<String>,
+    /// CDP resource types, case-insensitive (e.g. "image", "font", "media").
+    pub resource_types: Vec<String>,
+    /// Whether the bundled tracker list is applied.
+    pub trackers: bool,
+}
+
+impl BlockList {
+    pub fn is_empty(&self) -> bool {
+        self.url_patterns.is_empty()
+            && self.hosts.is_empty()
+            && self.resource_types.is_empty()
+            && !self.trackers
+    }
+
+    /// Add the entries of a list file. Blank lines and `#` comments are
+    /// skipped; lines containing `*` or `/` are URL globs, the rest are hosts.
+    pub fn add_list(&mut self, contents: &str) {
+        let (patterns, hosts) = parse_list(contents);
+        self.url_patterns.extend(patterns);
+        self.hosts.extend(hosts);
+    }
+
+    pub fn load_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read blocklist {}: {e}", path.display()))?;
+        self.add_list(&contents);
+        Ok(())
+    }
+
+    /// Return why a request should be blocked, or `None` to let it through.
+    pub fn block_reason(&self, url: &str, resource_type: &str) -> Option<String> {
+        if self
+            .resource_types
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(resource_type))
+        {
+            return Some(format!("resource type {resource_type}"));
+        }
+        if let Some(p) = self.url_patterns.iter().find(|p| glob_match(p, url)) {
+            return Some(format!("pattern {p}"));
+        }
+        let host = url_host(url);
+        if let Some(h) = self.hosts.iter().find(|h| host_matches(h, host)) {
+            return Some(format!("host {h}"));
+        }
+        if self.trackers {
+            let (patterns, hosts) = bundled_trackers();
+            if let Some(h) = hosts.iter().find(|h| host_matches(h, host)) {
+                return Some(format!("tracker {h}"));
+            }
+            if let Some(p) = patterns.iter().find(|p| glob_match(p, url)) {
+                return Some(format!("tracker {p}"));
+            }
+        }
+        None
+    }
+
+    /// One-line summary for tool output and logs.
+    pub fn describe(&self) -> String {
+        if self.is_empty() {
+            return "blocking disabled".into();
+        }
+        let mut parts = Vec::new();
+        if !self.url_patterns.is_empty() {
+            parts.push(format!("{} pattern(s)", self.url_patterns.len()));
+        }
+        if !self.hosts.is_empty() {
+            parts.push(format!("{} host(s)", self.hosts.len()));
+        }
+        if !self.resource_types.is_empty() {
+            parts.push(format!("types: {}", self.resource_types.join(", ")));
+        }
+        if self.trackers {
+            parts.push("bundled trackers".into());
+        }
+        format!("blocking {}", parts.join(", "))
+    }
+}
+
+fn parse_list(contents: &str) -> (Vec<String>, Vec<String>) {
+    let mut patterns = Vec::new();
+    let mut hosts = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.contains('*') || line.contains('/') {
+            patterns.push(line.to_string());
+        } else {
+            hosts.push(line.to_lowercase());
+        }
+    }
+    (patterns, hosts)
+}
+
+fn bundled_trackers() -> &'static (Vec<String>, Vec<String>) {
+    static LIST: OnceLock<(Vec<String>, Vec<String>)> = OnceLock::new();
+    LIST.get_or_init(|| parse_list(BUNDLED_TRACKERS))
+}
+
+/// Whether `host` is `blocked` or one of its subdomains.
+pub fn host_matches(blocked: &str, host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    host == blocked
+        || host
+            .strip_suffix(blocked)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
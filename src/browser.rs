@@ -1,50 +1,273 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
 use anyhow::{Context, Result};
-use chromiumoxide::browser::{Browser, BrowserConfig};
+use chromiumoxide::browser::{Browser, BrowserConfig, BrowserConfigBuilder};
+use chromiumoxide::cdp::browser_protocol::browser::BrowserContextId;
+use chromiumoxide::cdp::browser_protocol::target::CreateBrowserContextParams;
+use chromiumoxide::error::CdpError;
+use chromiumoxide::handler::viewport::Viewport;
+use chromiumoxide::Handler;
 use futures::StreamExt;
-use tracing::{debug, info};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::intercept::{self, InterceptRules, InterceptStats};
+use crate::proxy::ProxyOptions;
+
+/// Chrome launch settings. Read from the `[browser]` table of the config
+/// file, then overridden by CLI flags.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrowserOptions {
+    /// Chrome/Chromium binary. Auto-detected when unset.
+    pub executable: Option<PathBuf>,
+    /// Persistent profile directory. A temporary profile is used when unset.
+    pub user_data_dir: Option<PathBuf>,
+    /// Show the browser window instead of running headless.
+    pub headful: bool,
+    pub window_size: Option<Size>,
+    /// Emulated viewport. Follows `window_size` when unset, else 800x600.
+    pub viewport: Option<Size>,
+    /// Extra command-line flags passed to Chrome.
+    pub args: Vec<String>,
+    /// Seconds to wait for Chrome to start.
+    pub launch_timeout: Option<u64>,
+    /// Seconds to wait for a CDP command to respond.
+    pub request_timeout: Option<u64>,
+    /// Upstream proxy for all browser traffic.
+    pub proxy: ProxyOptions,
+}
+
+impl BrowserOptions {
+    /// Viewport pages get when emulation does not override it.
+    pub fn base_viewport(&self) -> Size {
+        self.viewport.or(self.window_size).unwrap_or(Size {
+            width: 800,
+            height: 600,
+        })
+    }
+
+    fn to_config(&self) -> Result<BrowserConfig> {
+        let mut builder = BrowserConfig::builder().no_sandbox();
+        for flag in DEFAULT_ARGS
+            .iter()
+            .copied()
+            .chain(self.args.iter().map(String::as_str))
+        {
+            builder = with_chrome_arg(builder, flag);
+        }
+        if let Some(server) = self.proxy.server() {
+            builder = builder.arg(("proxy-server", server.as_str()));
+        }
+        if let Some(bypass) = &self.proxy.bypass {
+            builder = builder.arg(("proxy-bypass-list", bypass.as_str()));
+        }
+        if let Some(path) = &self.executable {
+            builder = builder.chrome_executable(path);
+        }
+        if let Some(dir) = &self.user_data_dir {
+            builder = builder.user_data_dir(dir);
+        }
+        if self.headful {
+            builder = builder.with_head();
+        }
+        if let Some(size) = self.window_size {
+            builder = builder.window_size(size.width, size.height);
+        }
+        if let Some(size) = self.viewport.or(self.window_size) {
+            builder = builder.viewport(Viewport {
+                width: size.width,
+                height: size.height,
+                ..Viewport::default()
+            });
+        }
+        if let Some(secs) = self.launch_timeout {
+            builder = builder.launch_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.request_timeout {
+            builder = builder.request_timeout(Duration::from_secs(secs));
+        }
+        builder
+            .build()
+            .map_err(|e| anyhow::anyhow!("Failed to build browser config: {e}"))
+    }
+}
+
+/// Flags passed to every launched Chrome, before `BrowserOptions::args`.
+const DEFAULT_ARGS: &[&str] = &["--disable-gpu", "--disable-dev-shm-usage"];
+
+/// Add a command-line flag such as `--lang=de`. chromiumoxide adds the
+/// leading dashes itself, so they are stripped here.
+fn with_chrome_arg(builder: BrowserConfigBuilder, flag: &str) -> BrowserConfigBuilder {
+    let flag = flag.trim_start_matches('-');
+    match flag.split_once('=') {
+        Some((key, value)) => builder.arg((key, value)),
+        None => builder.arg(flag),
+    }
+}
+
+/// Width and height in pixels, written as `WIDTHxHEIGHT` (e.g. "1280x800").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let parse = |v: &str| v.trim().parse::<u32>().ok().filter(|&n| n > 0);
+        s.split_once(['x', 'X', ','])
+            .and_then(|(w, h)| Some((parse(w)?, parse(h)?)))
+            .map(|(width, height)| Size { width, height })
+            .ok_or_else(|| format!("invalid size '{s}', expected WIDTHxHEIGHT (e.g. 1280x800)"))
+    }
+}
+
+impl TryFrom<String> for Size {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Size> for String {
+    fn from(size: Size) -> Self {
+        size.to_string()
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// A CDP connection and the task driving it. The task ends when the
+/// connection fails, so [`BrowserHandle::is_connected`] needs no round trip.
+pub struct BrowserHandle {
+    browser: Browser,
+    handler: JoinHandle<()>,
+}
+
+impl BrowserHandle {
+    fn new(browser: Browser, mut handler: Handler) -> Self {
+        let handler = tokio::spawn(async move {
+            while let Some(event) = handler.next().await {
+                match event {
+                    Ok(()) | Err(CdpError::InvalidMessage(..)) => {}
+                    Err(e) => {
+                        warn!(error = %e, "CDP connection lost");
+                        break;
+                    }
+                }
+            }
+        });
+        Self { browser, handler }
+    }
+
+    /// False once Chrome has crashed or the connection dropped.
+    pub fn is_connected(&self) -> bool {
+        !self.handler.is_finished()
+    }
+}
+
+impl Deref for BrowserHandle {
+    type Target = Browser;
+
+    fn deref(&self) -> &Browser {
+        &self.browser
+    }
+}
+
+impl DerefMut for BrowserHandle {
+    fn deref_mut(&mut self) -> &mut Browser {
+        &mut self.browser
+    }
+}
 
 /// Connect to an already-running Chrome instance via CDP.
 ///
 /// Chrome must be started with `--remote-debugging-port=<port>`, e.g.:
 ///   google-chrome --remote-debugging-port=9222
-pub async fn connect(port: u16) -> Result<Browser> {
+pub async fn connect(port: u16) -> Result<BrowserHandle> {
     let url = format!("http://127.0.0.1:{port}");
     info!(port = port, "connecting to Chrome via CDP");
-    let (browser, mut handler) = Browser::connect(&url)
+    let (browser, handler) = Browser::connect(&url)
         .await
         .with_context(|| format!("Failed to connect to Chrome on port {port}. Is Chrome running with --remote-debugging-port={port}?"))?;
 
-    tokio::spawn(async move { while handler.next().await.is_some() {} });
-
     info!(port = port, "connected to Chrome");
-    Ok(browser)
+    Ok(BrowserHandle::new(browser, handler))
 }
 
-/// Launch a new headless Chrome instance.
-pub async fn launch() -> Result<Browser> {
-    info!("launching headless Chrome");
-    let config = BrowserConfig::builder()
-        .no_sandbox()
-        .arg("--disable-gpu")
-        .arg("--disable-dev-shm-usage")
-        .build()
-        .map_err(|e| anyhow::anyhow!("Failed to build browser config: {e}"))?;
+/// Launch a new Chrome instance (headless unless `options.headful`).
+pub async fn launch(options: &BrowserOptions) -> Result<BrowserHandle> {
+    info!(
+        headful = options.headful,
+        executable = ?options.executable,
+        user_data_dir = ?options.user_data_dir,
+        proxy = ?options.proxy.describe(),
+        "launching Chrome"
+    );
+    let config = options.to_config()?;
 
-    let (browser, mut handler) = Browser::launch(config)
+    let (browser, handler) = Browser::launch(config)
         .await
         .context("Failed to launch Chrome. Is Chrome/Chromium installed?")?;
 
-    tokio::spawn(async move { while handler.next().await.is_some() {} });
+    info!("Chrome launched");
+    Ok(BrowserHandle::new(browser, handler))
+}
 
-    info!("headless Chrome launched");
-    Ok(browser)
+/// Create an incognito browser context: its own cookies, storage and cache,
+/// disposed by Chrome if this connection drops. Pages opened in it use
+/// `proxy`, so a proxy also works when connecting to an existing Chrome.
+pub async fn create_context(browser: &Browser, proxy: &ProxyOptions) -> Result<BrowserContextId> {
+    let mut params = CreateBrowserContextParams::builder().dispose_on_detach(true);
+    if let Some(server) = proxy.server() {
+        params = params.proxy_server(server);
+    }
+    if let Some(bypass) = &proxy.bypass {
+        params = params.proxy_bypass_list(bypass.clone());
+    }
+    let id = browser
+        .create_browser_context(params.build())
+        .await
+        .context("Failed to create browser context")?;
+    debug!(context = ?id, "browser context created");
+    Ok(id)
 }
 
-/// Navigate to a URL and return the page's rendered HTML content and final URL.
-pub async fn fetch_page(browser: &Browser, url: &str) -> Result<(String, String)> {
+/// Navigate to a URL and return the page's rendered HTML content and final
+/// URL, resolving its requests against `rules` (e.g. a blocklist).
+pub async fn fetch_page(
+    browser: &Browser,
+    url: &str,
+    rules: &InterceptRules,
+) -> Result<(String, String)> {
     debug!(url = %url, "fetching page");
     let page = browser
-        .new_page(url)
+        .new_page("about:blank")
+        .await
+        .context("Failed to create page")?;
+    if rules.is_active() {
+        let stats = Arc::new(InterceptStats::default());
+        intercept::attach(&page, Arc::new(RwLock::new(rules.clone())), stats).await?;
+        intercept::sync(&page, rules)
+            .await
+            .context("Failed to enable request interception")?;
+    }
+    page.goto(url)
         .await
         .with_context(|| format!("Failed to navigate to {url}"))?;
 
@@ -0,0 +1,99 @@
+//! Config file support.
+//!
+//! Settings are read from a TOML file, `~/.cortex-browser/config.toml` by
+//! default, and CLI flags override them:
+//! ```toml
+//! [browser]
+//! executable = "/opt/chromium/chrome"
+//! user_data_dir = "/home/me/.cortex-profile"
+//! headful = true
+//! window_size = "1280x800"
+//! args = ["--lang=en-US"]
+//! launch_timeout = 30
+//!
+//! [browser.proxy]
+//! server = "http://proxy.staging.internal:3128"
+//! bypass = "localhost;*.internal"
+//! username = "ci"
+//! password = "hunter2"
+//!
+//! [headers]
+//! X-Env = "staging"
+//!
+//! [policy]
+//! allow = ["staging.example.com", "*.test.internal"]
+//! deny = ["prod.example.com"]
+//!
+//! [confirm]
+//! buttons = ["(?i)place order|delete account"]
+//! submit_hosts = ["shop.example.com"]
+//! passwords = true
+//!
+//! [emulation]
+//! device = "iphone"
+//! locale = "de-DE"
+//! timezone = "Europe/Berlin"
+//!
+//! [probes.github-com]
+//! url = "https://github.com/settings/profile"
+//! logged_out = { text = "Sign in" }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::browser::BrowserOptions;
+use crate::confirm::ConfirmRules;
+use crate::emulation::Emulation;
+use crate::policy::DomainPolicy;
+use crate::verify::AuthProbe;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub browser: BrowserOptions,
+    /// Emulation applied to every page of an MCP session.
+    pub emulation: Emulation,
+    /// Extra HTTP headers sent with every request of an MCP session.
+    pub headers: BTreeMap<String, String>,
+    /// Hosts and URL schemes an MCP session may navigate to.
+    pub policy: DomainPolicy,
+    /// Actions that need a human's approval in an MCP session.
+    pub confirm: ConfirmRules,
+    /// Login checks for `verify_auth`, keyed by profile name or domain.
+    pub probes: BTreeMap<String, AuthProbe>,
+}
+
+impl Config {
+    pub fn load_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read config file {}: {e}", path.display()))?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid config file {}: {e}", path.display()))
+    }
+
+    /// Load `path` if given, otherwise the default config file when it exists.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        match path {
+            Some(path) => Self::load_file(path),
+            None => {
+                let default = default_path();
+                if default.is_file() {
+                    Self::load_file(&default)
+                } else {
+                    Ok(Self::default())
+                }
+            }
+        }
+    }
+}
+
+/// Default config file location: `~/.cortex-browser/config.toml`
+pub fn default_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".cortex-browser")
+        .join("config.toml")
+}
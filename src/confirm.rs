@@ -0,0 +1,260 @@
+//! Human approval for sensitive actions.
+//!
+//! Rules pick out `click`, `type_text` and `press_key` calls that must not run
+//! without a human: clicking an element whose name matches a regex ("Place
+//! order", "Delete account"), submitting a form on a matching host (by click
+//! or by pressing Enter in it), or typing into a password field. The server asks through MCP elicitation when the client
+//! supports it; otherwise the action is queued until the agent relays the
+//! user's answer with the `approve` or `reject` tool.
+
+use anyhow::Context as _;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::dom::ElementLocator;
+use crate::policy;
+use crate::proxy::Secret;
+use crate::secrets::SecretKind;
+
+/// Read from the `[confirm]` table of the config file, then extended by CLI flags.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfirmRules {
+    /// Regexes matched against the name of a clicked element, e.g. "(?i)place order".
+    pub buttons: Vec<String>,
+    /// Hosts on which submitting a form needs approval, as domain policy
+    /// patterns ("shop.example.com" also covers its subdomains).
+    pub submit_hosts: Vec<String>,
+    /// Typing into password fields needs approval.
+    pub passwords: bool,
+}
+
+impl ConfirmRules {
+    pub fn is_active(&self) -> bool {
+        !self.buttons.is_empty() || !self.submit_hosts.is_empty() || self.passwords
+    }
+
+    /// Append another set of rules to this one.
+    pub fn merge(&mut self, other: ConfirmRules) {
+        self.buttons.extend(other.buttons);
+        self.submit_hosts.extend(other.submit_hosts);
+        self.passwords |= other.passwords;
+    }
+
+    /// Check that every button pattern is a valid regex.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for pattern in &self.buttons {
+            Regex::new(pattern).with_context(|| format!("Invalid confirm pattern '{pattern}'"))?;
+        }
+        Ok(())
+    }
+
+    /// Whether form submissions on `host` need approval, so the caller only
+    /// probes the page for one when it matters.
+    pub fn watches_submits(&self, host: &str) -> bool {
+        self.submit_hosts
+            .iter()
+            .any(|pattern| policy::host_pattern_matches(pattern, host))
+    }
+
+    /// Why an action on `locator` needs approval, or `None` if it can run.
+    /// `submits_form` says whether a click or key press would submit a form.
+    pub fn reason(
+        &self,
+        kind: ActionKind,
+        locator: &ElementLocator,
+        host: &str,
+        submits_form: bool,
+    ) -> Option<String> {
+        match kind {
+            ActionKind::Click => {
+                let name = locator.text.trim();
+                if let Some(pattern) = self.buttons.iter().find(|pattern| {
+                    Regex::new(pattern).is_ok_and(|re| !name.is_empty() && re.is_match(name))
+                }) {
+                    return Some(format!("name matches '{pattern}'"));
+                }
+                self.submit_reason(host, submits_form)
+            }
+            ActionKind::TypeText => {
+                let is_password = locator.secret_kind() == Some(SecretKind::Password);
+                (self.passwords && is_password).then(|| "password field".to_string())
+            }
+            ActionKind::PressKey => self.submit_reason(host, submits_form),
+        }
+    }
+
+    /// Why submitting a form on `host` needs approval, if it does. Used
+    /// directly for key presses in the focused element, which has no locator.
+    pub fn submit_reason(&self, host: &str, submits_form: bool) -> Option<String> {
+        (submits_form && self.watches_submits(host)).then(|| format!("submits a form on {host}"))
+    }
+
+    /// Loggable summary, e.g. "buttons (?i)delete; submits on shop.example.com".
+    pub fn describe(&self) -> String {
+        if !self.is_active() {
+            return "none".into();
+        }
+        let mut parts = Vec::new();
+        if !self.buttons.is_empty() {
+            parts.push(format!("buttons {}", self.buttons.join(", ")));
+        }
+        if !self.submit_hosts.is_empty() {
+            parts.push(format!("submits on {}", self.submit_hosts.join(", ")));
+        }
+        if self.passwords {
+            parts.push("password fields".into());
+        }
+        parts.join("; ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionKind {
+    Click,
+    TypeText,
+    /// A key press that submits a form, i.e. Enter in a form field.
+    PressKey,
+}
+
+/// Whether pressing `key` in a form field submits the form.
+pub fn submits_on(key: &str) -> bool {
+    key.eq_ignore_ascii_case("enter")
+}
+
+/// A tool call held back until a human decides.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingKind {
+    Click {
+        ref_id: u32,
+        return_diff: bool,
+    },
+    TypeText {
+        ref_id: u32,
+        text: Secret,
+        /// Variable name to record the text as.
+        variable: Option<String>,
+        return_diff: bool,
+    },
+    /// Pressed in the focused element unless `ref_id` is given.
+    PressKey {
+        key: String,
+        ref_id: Option<u32>,
+    },
+}
+
+impl PendingKind {
+    pub fn kind(&self) -> ActionKind {
+        match self {
+            PendingKind::Click { .. } => ActionKind::Click,
+            PendingKind::TypeText { .. } => ActionKind::TypeText,
+            PendingKind::PressKey { .. } => ActionKind::PressKey,
+        }
+    }
+
+    pub fn ref_id(&self) -> Option<u32> {
+        match self {
+            PendingKind::Click { ref_id, .. } | PendingKind::TypeText { ref_id, .. } => {
+                Some(*ref_id)
+            }
+            PendingKind::PressKey { ref_id, .. } => *ref_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingAction {
+    pub id: u32,
+    /// Tab the action's ref belongs to.
+    pub tab_id: u32,
+    pub action: PendingKind,
+    /// The element the ref pointed to when the action was requested.
+    /// Approving refuses to run it on anything else. `None` for a key
+    /// pressed in the focused element.
+    pub locator: Option<ElementLocator>,
+    /// Human-readable description, e.g. `click @e5 "Place order"`.
+    pub summary: String,
+    pub reason: String,
+}
+
+/// Actions waiting for `approve` or `reject`, oldest first.
+#[derive(Debug, Default)]
+pub struct PendingQueue {
+    next_id: u32,
+    actions: Vec<PendingAction>,
+}
+
+impl PendingQueue {
+    /// Queue an action and return its ID.
+    pub fn push(
+        &mut self,
+        tab_id: u32,
+        action: PendingKind,
+        locator: Option<ElementLocator>,
+        summary: String,
+        reason: String,
+    ) -> u32 {
+        self.next_id += 1;
+        self.actions.push(PendingAction {
+            id: self.next_id,
+            tab_id,
+            action,
+            locator,
+            summary,
+            reason,
+        });
+        self.next_id
+    }
+
+    pub fn get(&self, id: u32) -> Option<&PendingAction> {
+        self.actions.iter().find(|a| a.id == id)
+    }
+
+    pub fn take(&mut self, id: u32) -> Option<PendingAction> {
+        let index = self.actions.iter().position(|a| a.id == id)?;
+        Some(self.actions.remove(index))
+    }
+
+    pub fn list(&self) -> &[PendingAction] {
+        &self.actions
+    }
+}
+
+/// Describe an action for the human deciding on it. Typed text is left out,
+/// since it may be a password. `locator` is the element behind the action's
+/// ref, if it has one.
+pub fn summarize(action: &PendingKind, locator: Option<&ElementLocator>) -> String {
+    let target = match (action.ref_id(), locator) {
+        (Some(ref_id), Some(locator)) => format!("@e{ref_id} {}", target_name(locator)),
+        (Some(ref_id), None) => format!("@e{ref_id}"),
+        (None, _) => "the focused element".to_string(),
+    };
+    match action {
+        PendingKind::PressKey { key, .. } => format!("press {key} in {target}"),
+        _ => describe(action.kind(), &target),
+    }
+}
+
+/// Describe a step of a replayed recording, e.g. `click "Place order" in
+/// replay step 3`.
+pub fn summarize_replay(kind: ActionKind, locator: Option<&ElementLocator>, step: usize) -> String {
+    let target = locator.map_or("the focused element".to_string(), target_name);
+    format!("{} in replay step {step}", describe(kind, &target))
+}
+
+fn target_name(locator: &ElementLocator) -> String {
+    let name = locator.text.trim();
+    if name.is_empty() {
+        format!("<{}>", locator.tag)
+    } else {
+        format!("\"{name}\"")
+    }
+}
+
+fn describe(kind: ActionKind, target: &str) -> String {
+    match kind {
+        ActionKind::Click => format!("click {target}"),
+        ActionKind::TypeText => format!("type into {target}"),
+        ActionKind::PressKey => format!("press Enter in {target}"),
+    }
+}
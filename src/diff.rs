@@ -245,6 +245,8 @@ mod tests {
             url: "https://test.com".into(),
             nodes,
             viewport: None,
+            emulation: None,
+            auth: None,
         }
     }
 
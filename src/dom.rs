@@ -3,6 +3,8 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
+use crate::secrets::{self, SecretKind};
+
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum AriaRole {
     // Landmarks
@@ -69,6 +71,23 @@ impl AriaRole {
     }
 }
 
+impl AriaRole {
+    /// Whether this role marks a page region, e.g. navigation or main.
+    pub fn is_landmark(&self) -> bool {
+        matches!(
+            self,
+            AriaRole::Banner
+                | AriaRole::Navigation
+                | AriaRole::Main
+                | AriaRole::Complementary
+                | AriaRole::ContentInfo
+                | AriaRole::Search
+                | AriaRole::Region
+                | AriaRole::Form
+        )
+    }
+}
+
 impl fmt::Display for AriaRole {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -133,23 +152,43 @@ pub struct PageSnapshot {
     pub nodes: Vec<SemanticNode>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub viewport: Option<ViewportInfo>,
+    /// Summary of active device/locale emulation, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emulation: Option<String>,
+    /// Auth profile restored automatically for this page's host, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<String>,
 }
 
 /// Maps ref_id → element locator for finding elements in the live DOM.
 pub type RefIndex = HashMap<u32, ElementLocator>;
 
 /// Stores enough info about a ref'd element to locate it in the live browser DOM.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ElementLocator {
     pub tag: String,
     pub id: Option<String>,
     pub name: Option<String>,
     pub input_type: Option<String>,
+    /// `autocomplete` attribute, used to recognise OTP and card fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autocomplete: Option<String>,
     pub href: Option<String>,
     pub text: String,
+    /// ARIA role and enclosing landmark, used to re-find the element when
+    /// a replay's locator no longer matches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub landmark: Option<String>,
 }
 
 impl ElementLocator {
+    /// Whether this is a password, one-time code or credit card field.
+    pub fn secret_kind(&self) -> Option<SecretKind> {
+        secrets::classify(self.input_type.as_deref(), self.autocomplete.as_deref())
+    }
+
     /// Generate a JavaScript expression that finds this element in the live DOM.
     pub fn to_js_expression(&self) -> String {
         if let Some(id) = &self.id {
@@ -196,6 +235,18 @@ impl ElementLocator {
         )
     }
 
+    /// JS that returns 'true' if clicking the element would submit a form.
+    pub fn submits_form_js(&self) -> String {
+        format!(
+            "(function() {{ \
+                var el = {find}; \
+                if (!el) return 'NOT_FOUND'; \
+                return String(!!(el.form && (el.type === 'submit' || el.type === 'image'))); \
+            }})()",
+            find = self.to_js_expression()
+        )
+    }
+
     /// JS that finds the element, focuses it, sets its value, and fires input/change events.
     pub fn type_js(&self, text: &str) -> String {
         let escaped = js_escape(text);
@@ -214,6 +265,47 @@ impl ElementLocator {
         )
     }
 
+    /// JS that finds the element and scrolls it to the middle of the viewport.
+    pub fn scroll_into_view_js(&self) -> String {
+        format!(
+            "(function() {{ \
+                var el = {find}; \
+                if (!el) return 'NOT_FOUND'; \
+                el.scrollIntoView({{behavior: 'instant', block: 'center'}}); \
+                return 'OK'; \
+            }})()",
+            find = self.to_js_expression()
+        )
+    }
+
+    /// JS that finds the element and focuses it, so key presses go to it.
+    pub fn focus_js(&self) -> String {
+        format!(
+            "(function() {{ \
+                var el = {find}; \
+                if (!el) return 'NOT_FOUND'; \
+                el.focus(); \
+                return 'OK'; \
+            }})()",
+            find = self.to_js_expression()
+        )
+    }
+
+    /// JS that returns the element's [`ElementState`] as JSON.
+    pub fn inspect_js(&self) -> String {
+        format!(
+            "(function() {{ \
+                var el = {find}; \
+                if (!el) return 'NOT_FOUND'; \
+                return JSON.stringify({{ \
+                    value: el.value === undefined ? null : String(el.value), \
+                    checked: !!el.checked \
+                }}); \
+            }})()",
+            find = self.to_js_expression()
+        )
+    }
+
     /// JS that finds the element, sets its value, and fires a change event.
     pub fn select_js(&self, value: &str) -> String {
         let escaped = js_escape(value);
@@ -231,6 +323,31 @@ impl ElementLocator {
     }
 }
 
+/// Live state of a located element, read by `AssertRef` during replay.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ElementState {
+    pub value: Option<String>,
+    #[serde(default)]
+    pub checked: bool,
+}
+
+/// JS that returns 'true' if pressing Enter in the element (the focused
+/// element when `None`) would submit a form: it sits in a form and is not a
+/// multi-line field.
+pub fn enter_submits_js(locator: Option<&ElementLocator>) -> String {
+    let find = locator.map_or("document.activeElement".to_string(), |l| {
+        l.to_js_expression()
+    });
+    format!(
+        "(function() {{ \
+            var el = {find}; \
+            if (!el) return 'NOT_FOUND'; \
+            if (el.tagName === 'TEXTAREA' || el.isContentEditable) return 'false'; \
+            return String(!!(el.form || (el.closest && el.closest('form')))); \
+        }})()"
+    )
+}
+
 fn js_escape(s: &str) -> String {
     s.replace('\\', "\\\\")
         .replace('\'', "\\'")
@@ -0,0 +1,461 @@
+//! Device and locale emulation.
+//!
+//! An `Emulation` describes how every page of a session should present
+//! itself: viewport and scale, mobile/touch, user agent, locale, timezone,
+//! geolocation and preferred color scheme / motion. It is applied through
+//! the CDP Emulation domain when a page is created and whenever the `emulate`
+//! tool changes it. Named presets cover common phones and tablets.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chromiumoxide::cdp::browser_protocol::browser::{
+    PermissionDescriptor, PermissionSetting, SetPermissionParams,
+};
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    ClearGeolocationOverrideParams, MediaFeature, SetDeviceMetricsOverrideParams,
+    SetEmulatedMediaParams, SetGeolocationOverrideParams, SetLocaleOverrideParams,
+    SetTimezoneOverrideParams, SetTouchEmulationEnabledParams, SetUserAgentOverrideParams,
+};
+use chromiumoxide::Page;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::browser::Size;
+
+/// A named device preset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Device {
+    pub name: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub scale: f64,
+    pub mobile: bool,
+    pub touch: bool,
+    pub user_agent: &'static str,
+}
+
+const IPHONE_UA: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1";
+const IPAD_UA: &str = "Mozilla/5.0 (iPad; CPU OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1";
+const PIXEL_UA: &str = "Mozilla/5.0 (Linux; Android 14; Pixel 7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+const GALAXY_UA: &str = "Mozilla/5.0 (Linux; Android 13; SM-S911B) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+const DESKTOP_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+pub const DEVICES: &[Device] = &[
+    Device {
+        name: "iPhone 15",
+        width: 393,
+        height: 852,
+        scale: 3.0,
+        mobile: true,
+        touch: true,
+        user_agent: IPHONE_UA,
+    },
+    Device {
+        name: "iPhone SE",
+        width: 375,
+        height: 667,
+        scale: 2.0,
+        mobile: true,
+        touch: true,
+        user_agent: IPHONE_UA,
+    },
+    Device {
+        name: "Pixel 7",
+        width: 412,
+        height: 915,
+        scale: 2.625,
+        mobile: true,
+        touch: true,
+        user_agent: PIXEL_UA,
+    },
+    Device {
+        name: "Galaxy S23",
+        width: 360,
+        height: 780,
+        scale: 3.0,
+        mobile: true,
+        touch: true,
+        user_agent: GALAXY_UA,
+    },
+    Device {
+        name: "iPad Air",
+        width: 820,
+        height: 1180,
+        scale: 2.0,
+        mobile: true,
+        touch: true,
+        user_agent: IPAD_UA,
+    },
+    Device {
+        name: "iPad Pro",
+        width: 1024,
+        height: 1366,
+        scale: 2.0,
+        mobile: true,
+        touch: true,
+        user_agent: IPAD_UA,
+    },
+    Device {
+        name: "Desktop",
+        width: 1920,
+        height: 1080,
+        scale: 1.0,
+        mobile: false,
+        touch: false,
+        user_agent: DESKTOP_UA,
+    },
+];
+
+/// Short aliases for the most common presets.
+const ALIASES: &[(&str, &str)] = &[
+    ("iphone", "iPhone 15"),
+    ("pixel", "Pixel 7"),
+    ("android", "Pixel 7"),
+    ("galaxy", "Galaxy S23"),
+    ("ipad", "iPad Air"),
+];
+
+/// Look up a preset by name, ignoring case, spaces, dashes and underscores
+/// (e.g. "iphone-se", "Pixel 7", "ipad").
+pub fn find_device(name: &str) -> Option<&'static Device> {
+    let key = normalize(name);
+    let target = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == key)
+        .map(|(_, target)| normalize(target))
+        .unwrap_or(key);
+    DEVICES.iter().find(|d| normalize(d.name) == target)
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Comma-separated preset names, for help and error messages.
+pub fn device_names() -> String {
+    DEVICES
+        .iter()
+        .map(|d| d.name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Emulated GPS position, written as `LAT,LON[,ACCURACY]` on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Geolocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Accuracy in meters (default: 100).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accuracy: Option<f64>,
+}
+
+impl FromStr for Geolocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<f64> = s
+            .split(',')
+            .map(|p| p.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid geolocation '{s}', expected LAT,LON[,ACCURACY]"))?;
+        let geo = match parts.as_slice() {
+            [latitude, longitude] => Geolocation {
+                latitude: *latitude,
+                longitude: *longitude,
+                accuracy: None,
+            },
+            [latitude, longitude, accuracy] => Geolocation {
+                latitude: *latitude,
+                longitude: *longitude,
+                accuracy: Some(*accuracy),
+            },
+            _ => {
+                return Err(format!(
+                    "invalid geolocation '{s}', expected LAT,LON[,ACCURACY]"
+                ))
+            }
+        };
+        geo.validate()?;
+        Ok(geo)
+    }
+}
+
+impl Geolocation {
+    fn validate(&self) -> Result<(), String> {
+        if !(-90.0..=90.0).contains(&self.latitude) || !(-180.0..=180.0).contains(&self.longitude) {
+            return Err(format!(
+                "geolocation {},{} is out of range",
+                self.latitude, self.longitude
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Geolocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.4},{:.4}", self.latitude, self.longitude)
+    }
+}
+
+/// Session-wide emulation settings. Unset fields keep the browser default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Emulation {
+    /// Device preset the other fields start from (see `DEVICES`).
+    pub device: Option<String>,
+    pub viewport: Option<Size>,
+    pub device_scale_factor: Option<f64>,
+    pub mobile: Option<bool>,
+    pub touch: Option<bool>,
+    pub user_agent: Option<String>,
+    /// BCP 47 locale, also sent as Accept-Language (e.g. "de-DE").
+    pub locale: Option<String>,
+    /// IANA timezone (e.g. "Europe/Berlin").
+    pub timezone: Option<String>,
+    pub geolocation: Option<Geolocation>,
+    /// `prefers-color-scheme`: "light", "dark" or "no-preference".
+    pub color_scheme: Option<String>,
+    /// `prefers-reduced-motion: reduce`.
+    pub reduced_motion: bool,
+}
+
+impl Emulation {
+    pub fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Overlay the fields set in `other` onto `self`. Selecting a device
+    /// replaces the metrics and user agent of a previously selected one.
+    pub fn merge(&mut self, other: Emulation) {
+        if other.device.is_some() {
+            self.device = other.device;
+            self.viewport = None;
+            self.device_scale_factor = None;
+            self.mobile = None;
+            self.touch = None;
+            self.user_agent = None;
+        }
+        overlay(&mut self.viewport, other.viewport);
+        overlay(&mut self.device_scale_factor, other.device_scale_factor);
+        overlay(&mut self.mobile, other.mobile);
+        overlay(&mut self.touch, other.touch);
+        overlay(&mut self.user_agent, other.user_agent);
+        overlay(&mut self.locale, other.locale);
+        overlay(&mut self.timezone, other.timezone);
+        overlay(&mut self.geolocation, other.geolocation);
+        overlay(&mut self.color_scheme, other.color_scheme);
+        self.reduced_motion |= other.reduced_motion;
+    }
+
+    /// Check the device name, color scheme and geolocation.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.preset()?;
+        if let Some(scheme) = &self.color_scheme {
+            if !matches!(scheme.as_str(), "light" | "dark" | "no-preference") {
+                anyhow::bail!("Invalid color scheme '{scheme}'. Use light, dark or no-preference.");
+            }
+        }
+        if let Some(geo) = &self.geolocation {
+            geo.validate().map_err(anyhow::Error::msg)?;
+        }
+        if let Some(scale) = self.device_scale_factor {
+            if scale <= 0.0 {
+                anyhow::bail!("device_scale_factor must be positive");
+            }
+        }
+        Ok(())
+    }
+
+    fn preset(&self) -> anyhow::Result<Option<&'static Device>> {
+        match &self.device {
+            Some(name) => find_device(name).map(Some).ok_or_else(|| {
+                anyhow::anyhow!("Unknown device '{name}'. Available: {}", device_names())
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Effective viewport, scale, mobile and touch settings after applying
+    /// the device preset, or `None` when metrics are not overridden.
+    pub fn metrics(&self) -> Option<Metrics> {
+        let preset = self.preset().ok().flatten();
+        if preset.is_none()
+            && self.viewport.is_none()
+            && self.device_scale_factor.is_none()
+            && self.mobile.is_none()
+            && self.touch.is_none()
+        {
+            return None;
+        }
+        Some(Metrics {
+            viewport: self.viewport.or(preset.map(|d| Size {
+                width: d.width,
+                height: d.height,
+            })),
+            scale: self
+                .device_scale_factor
+                .or(preset.map(|d| d.scale))
+                .unwrap_or(1.0),
+            mobile: self.mobile.or(preset.map(|d| d.mobile)).unwrap_or(false),
+            touch: self.touch.or(preset.map(|d| d.touch)).unwrap_or(false),
+        })
+    }
+
+    /// User agent override: explicit, else the device preset's.
+    pub fn effective_user_agent(&self) -> Option<&str> {
+        self.user_agent
+            .as_deref()
+            .or_else(|| self.preset().ok().flatten().map(|d| d.user_agent))
+    }
+
+    /// One-line summary for the snapshot header, or `None` when inactive.
+    /// e.g. "iPhone 15 393x852@3x mobile touch, de-DE, Europe/Berlin, dark"
+    pub fn describe(&self) -> Option<String> {
+        if !self.is_active() {
+            return None;
+        }
+        let mut parts = Vec::new();
+        if let Some(m) = self.metrics() {
+            let mut device = match self.preset().ok().flatten() {
+                Some(d) => format!("{} ", d.name),
+                None => String::new(),
+            };
+            if let Some(size) = m.viewport {
+                device.push_str(&size.to_string());
+            }
+            if m.scale != 1.0 {
+                device.push_str(&format!("@{}x", m.scale));
+            }
+            if m.mobile {
+                device.push_str(" mobile");
+            }
+            if m.touch {
+                device.push_str(" touch");
+            }
+            parts.push(device.trim().to_string());
+        }
+        if self.user_agent.is_some() {
+            parts.push("custom UA".into());
+        }
+        if let Some(locale) = &self.locale {
+            parts.push(locale.clone());
+        }
+        if let Some(tz) = &self.timezone {
+            parts.push(tz.clone());
+        }
+        if let Some(geo) = &self.geolocation {
+            parts.push(format!("geo {geo}"));
+        }
+        if let Some(scheme) = &self.color_scheme {
+            parts.push(scheme.clone());
+        }
+        if self.reduced_motion {
+            parts.push("reduced-motion".into());
+        }
+        Some(parts.join(", "))
+    }
+}
+
+fn overlay<T>(target: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *target = value;
+    }
+}
+
+/// Resolved device metrics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    /// `None` keeps the session's default viewport size.
+    pub viewport: Option<Size>,
+    pub scale: f64,
+    pub mobile: bool,
+    pub touch: bool,
+}
+
+/// Make `page` match `emulation`. Settings that are unset are reset to the
+/// defaults, with `base_viewport` as the default viewport size.
+pub async fn apply(page: &Page, emulation: &Emulation, base_viewport: Size) -> anyhow::Result<()> {
+    let metrics = emulation.metrics().unwrap_or(Metrics {
+        viewport: None,
+        scale: 1.0,
+        mobile: false,
+        touch: false,
+    });
+    let size = metrics.viewport.unwrap_or(base_viewport);
+    page.execute(SetDeviceMetricsOverrideParams::new(
+        size.width,
+        size.height,
+        metrics.scale,
+        metrics.mobile,
+    ))
+    .await?;
+
+    let mut touch = SetTouchEmulationEnabledParams::new(metrics.touch);
+    if metrics.touch {
+        touch.max_touch_points = Some(5);
+    }
+    page.execute(touch).await?;
+
+    // The override always carries a user agent, so fall back to the real one.
+    let user_agent = match emulation.effective_user_agent() {
+        Some(ua) => ua.to_string(),
+        None => page.user_agent().await?,
+    };
+    let mut ua = SetUserAgentOverrideParams::new(user_agent);
+    ua.accept_language = emulation.locale.clone();
+    page.execute(ua).await?;
+
+    let mut locale = SetLocaleOverrideParams::default();
+    locale.locale = emulation.locale.clone();
+    page.execute(locale).await?;
+
+    // An empty timezone ID restores the host timezone.
+    page.execute(SetTimezoneOverrideParams::new(
+        emulation.timezone.clone().unwrap_or_default(),
+    ))
+    .await?;
+
+    match &emulation.geolocation {
+        Some(geo) => {
+            if let Err(e) = page
+                .execute(SetPermissionParams::new(
+                    PermissionDescriptor::new("geolocation"),
+                    PermissionSetting::Granted,
+                ))
+                .await
+            {
+                warn!(error = %e, "failed to grant geolocation permission");
+            }
+            page.execute(
+                SetGeolocationOverrideParams::builder()
+                    .latitude(geo.latitude)
+                    .longitude(geo.longitude)
+                    .accuracy(geo.accuracy.unwrap_or(100.0))
+                    .build(),
+            )
+            .await?;
+        }
+        None => {
+            page.execute(ClearGeolocationOverrideParams::default())
+                .await?;
+        }
+    }
+
+    let mut features = Vec::new();
+    if let Some(scheme) = &emulation.color_scheme {
+        features.push(MediaFeature::new("prefers-color-scheme", scheme.clone()));
+    }
+    if emulation.reduced_motion {
+        features.push(MediaFeature::new("prefers-reduced-motion", "reduce"));
+    }
+    page.execute(SetEmulatedMediaParams::builder().features(features).build())
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,612 @@
+//! HAR 1.2 capture and replay.
+//!
+//! While a capture is active, every tab feeds the requests its page makes
+//! (headers, post data, response body, timing) into a session-wide
+//! `HarRecorder`, which is written out as a standard `.har` file. A
+//! `HarReplay` does the reverse: it answers requests from a HAR file through
+//! Fetch interception (see `intercept`), so a session recorded once can be
+//! rerun offline together with its action recording.
+
+use std::collections::HashMap;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use base64::Engine as _;
+use chromiumoxide::cdp::browser_protocol::network::{
+    EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, EventResponseReceived,
+    GetResponseBodyParams, Request, Response,
+};
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::access;
+use crate::routes::MockResponse;
+
+// ── HAR types ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HarLog {
+    pub version: String,
+    pub creator: HarCreator,
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HarEntry {
+    pub started_date_time: String,
+    /// Total elapsed time of the request in milliseconds.
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    pub cache: serde_json::Value,
+    pub timings: HarTimings,
+    /// CDP resource type, as written by Chrome DevTools.
+    #[serde(rename = "_resourceType", skip_serializing_if = "Option::is_none")]
+    pub resource_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub cookies: Vec<serde_json::Value>,
+    pub headers: Vec<HarHeader>,
+    pub query_string: Vec<HarHeader>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HarResponse {
+    pub status: i64,
+    pub status_text: String,
+    pub http_version: String,
+    pub cookies: Vec<serde_json::Value>,
+    pub headers: Vec<HarHeader>,
+    pub content: HarContent,
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+/// Name/value pair, used for headers and query string parameters.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HarHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HarPostData {
+    pub mime_type: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HarContent {
+    pub size: i64,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// `"base64"` for binary bodies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HarTimings {
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+impl Har {
+    pub fn new(entries: Vec<HarEntry>) -> Self {
+        Self {
+            log: HarLog {
+                version: "1.2".into(),
+                creator: HarCreator {
+                    name: env!("CARGO_PKG_NAME").into(),
+                    version: env!("CARGO_PKG_VERSION").into(),
+                },
+                entries,
+            },
+        }
+    }
+
+    pub fn load_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read HAR file {}: {e}", path.display()))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid HAR file {}: {e}", path.display()))
+    }
+
+    /// Write the HAR to `path`. An existing file is only replaced when
+    /// `overwrite` is set.
+    pub fn save(&self, path: &Path, overwrite: bool) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        access::create_file(path, overwrite)?
+            .write_all(json.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Failed to write HAR file {}: {e}", path.display()))
+    }
+}
+
+impl HarContent {
+    /// Decoded body bytes.
+    pub fn body(&self) -> Vec<u8> {
+        let text = self.text.as_deref().unwrap_or_default();
+        if self.encoding.as_deref() == Some("base64") {
+            base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .unwrap_or_default()
+        } else {
+            text.as_bytes().to_vec()
+        }
+    }
+}
+
+// ── Capture ─────────────────────────────────────────────────────────────────
+
+/// Completed entries of an active HAR capture.
+#[derive(Debug)]
+pub struct HarRecorder {
+    pub path: PathBuf,
+    pub entries: Vec<HarEntry>,
+    /// Replace the file at `path` if it exists.
+    pub overwrite: bool,
+}
+
+/// Session-wide capture slot. `None` while no capture is running.
+pub type SharedHarRecorder = Arc<Mutex<Option<HarRecorder>>>;
+
+impl HarRecorder {
+    pub fn new(path: PathBuf, overwrite: bool) -> Self {
+        Self {
+            path,
+            entries: Vec::new(),
+            overwrite,
+        }
+    }
+
+    /// A shared slot with a capture already running, for `--har`. The path
+    /// comes from the command line, so an existing file is replaced.
+    pub fn shared(path: PathBuf) -> SharedHarRecorder {
+        Arc::new(Mutex::new(Some(Self::new(path, true))))
+    }
+
+    /// Write the capture to its path. Returns the number of entries written.
+    pub fn save(self) -> anyhow::Result<usize> {
+        let count = self.entries.len();
+        Har::new(self.entries).save(&self.path, self.overwrite)?;
+        Ok(count)
+    }
+}
+
+/// Base directory for HAR captures without an explicit path: `~/.cortex-browser/har`
+pub fn har_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".cortex-browser")
+        .join("har")
+}
+
+struct PendingEntry {
+    entry: HarEntry,
+    /// Monotonic timestamp (seconds) at which the request was sent.
+    started_at: f64,
+}
+
+/// In-flight requests of one page, assembled into HAR entries as their
+/// events arrive.
+#[derive(Default)]
+pub struct HarCapture {
+    pending: HashMap<String, PendingEntry>,
+}
+
+impl HarCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_pending(&self, request_id: &str) -> bool {
+        self.pending.contains_key(request_id)
+    }
+
+    /// Start an entry. `wall_time` is seconds since the epoch, `timestamp`
+    /// the monotonic time used for durations.
+    pub fn on_request(
+        &mut self,
+        request_id: &str,
+        request: HarRequest,
+        resource_type: &str,
+        wall_time: f64,
+        timestamp: f64,
+    ) {
+        let entry = HarEntry {
+            started_date_time: iso8601(wall_time),
+            request,
+            cache: serde_json::json!({}),
+            resource_type: Some(resource_type.to_ascii_lowercase()),
+            ..Default::default()
+        };
+        self.pending.insert(
+            request_id.to_string(),
+            PendingEntry {
+                entry,
+                started_at: timestamp,
+            },
+        );
+    }
+
+    /// Complete a redirect hop. The follow-up request reuses the request ID
+    /// and is started with `on_request` afterwards.
+    pub fn on_redirect(
+        &mut self,
+        request_id: &str,
+        mut response: HarResponse,
+        location: &str,
+        timestamp: f64,
+    ) -> Option<HarEntry> {
+        response.redirect_url = location.to_string();
+        self.on_response(request_id, response);
+        self.finish(request_id, timestamp)
+    }
+
+    pub fn on_response(&mut self, request_id: &str, response: HarResponse) {
+        if let Some(p) = self.pending.get_mut(request_id) {
+            p.entry.response = response;
+        }
+    }
+
+    /// Complete an entry with its body (`(body, base64_encoded)`) if one
+    /// could be read.
+    pub fn on_finished(
+        &mut self,
+        request_id: &str,
+        body: Option<(String, bool)>,
+        timestamp: f64,
+    ) -> Option<HarEntry> {
+        if let (Some(p), Some((text, base64_encoded))) = (self.pending.get_mut(request_id), body) {
+            let content = &mut p.entry.response.content;
+            content.encoding = base64_encoded.then(|| "base64".to_string());
+            content.text = Some(text);
+            content.size = content.body().len() as i64;
+        }
+        self.finish(request_id, timestamp)
+    }
+
+    /// Complete an entry that never got a response. HAR has no error field,
+    /// so it is recorded with status 0 and the error as status text.
+    pub fn on_failed(&mut self, request_id: &str, error: &str, timestamp: f64) -> Option<HarEntry> {
+        if let Some(p) = self.pending.get_mut(request_id) {
+            if p.entry.response.status == 0 {
+                p.entry.response.status_text = error.to_string();
+            }
+        }
+        self.finish(request_id, timestamp)
+    }
+
+    fn finish(&mut self, request_id: &str, timestamp: f64) -> Option<HarEntry> {
+        let PendingEntry {
+            mut entry,
+            started_at,
+        } = self.pending.remove(request_id)?;
+        let time = ((timestamp - started_at) * 1000.0).max(0.0);
+        entry.time = time;
+        entry.timings = HarTimings {
+            send: 0.0,
+            wait: time,
+            receive: 0.0,
+        };
+        if entry.response.http_version.is_empty() {
+            entry.response.http_version = entry.request.http_version.clone();
+        }
+        entry.response.headers_size = -1;
+        entry.request.headers_size = -1;
+        Some(entry)
+    }
+}
+
+/// Subscribe to the page's Network events and append completed requests to
+/// `recorder` while a capture is running. The listener task ends when the
+/// page's event streams close.
+pub async fn attach(page: &Page, recorder: SharedHarRecorder) -> anyhow::Result<()> {
+    let mut sent = page.event_listener::<EventRequestWillBeSent>().await?;
+    let mut received = page.event_listener::<EventResponseReceived>().await?;
+    let mut finished = page.event_listener::<EventLoadingFinished>().await?;
+    let mut failed = page.event_listener::<EventLoadingFailed>().await?;
+    let page = page.clone();
+
+    tokio::spawn(async move {
+        let mut capture = HarCapture::new();
+        let is_recording = || recorder.lock().map(|r| r.is_some()).unwrap_or(false);
+        let push = |entry: Option<HarEntry>| {
+            if let (Some(entry), Ok(mut rec)) = (entry, recorder.lock()) {
+                if let Some(rec) = rec.as_mut() {
+                    rec.entries.push(entry);
+                }
+            }
+        };
+
+        loop {
+            tokio::select! {
+                Some(ev) = sent.next() => {
+                    let id = ev.request_id.as_ref();
+                    let ts = *ev.timestamp.inner();
+                    if let Some(redirect) = &ev.redirect_response {
+                        push(capture.on_redirect(id, har_response(redirect), &ev.request.url, ts));
+                    }
+                    if is_recording() {
+                        let resource_type = ev.r#type.as_ref().map(|t| t.as_ref()).unwrap_or("Other");
+                        capture.on_request(id, har_request(&ev.request), resource_type, *ev.wall_time.inner(), ts);
+                    }
+                }
+                Some(ev) = received.next() => {
+                    capture.on_response(ev.request_id.as_ref(), har_response(&ev.response));
+                }
+                Some(ev) = finished.next() => {
+                    let id = ev.request_id.as_ref();
+                    if !capture.is_pending(id) {
+                        continue;
+                    }
+                    let body = match page.execute(GetResponseBodyParams::new(id.to_string())).await {
+                        Ok(resp) => Some((resp.result.body, resp.result.base64_encoded)),
+                        Err(e) => {
+                            debug!(request_id = %id, error = %e, "HAR: response body unavailable");
+                            None
+                        }
+                    };
+                    push(capture.on_finished(id, body, *ev.timestamp.inner()));
+                }
+                Some(ev) = failed.next() => {
+                    push(capture.on_failed(ev.request_id.as_ref(), &ev.error_text, *ev.timestamp.inner()));
+                }
+                else => break,
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn har_request(request: &Request) -> HarRequest {
+    let headers = headers_from_json(request.headers.inner());
+    let post_data = request.post_data_entries.as_ref().map(|entries| {
+        let bytes: Vec<u8> = entries
+            .iter()
+            .filter_map(|e| e.bytes.as_ref())
+            .flat_map(|b| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(AsRef::<str>::as_ref(b))
+                    .unwrap_or_default()
+            })
+            .collect();
+        HarPostData {
+            mime_type: header_value(&headers, "content-type")
+                .unwrap_or_default()
+                .to_string(),
+            text: String::from_utf8_lossy(&bytes).into_owned(),
+        }
+    });
+    HarRequest {
+        method: request.method.clone(),
+        url: request.url.clone(),
+        http_version: "HTTP/1.1".into(),
+        query_string: query_string(&request.url),
+        body_size: post_data.as_ref().map_or(0, |p| p.text.len() as i64),
+        post_data,
+        headers,
+        ..Default::default()
+    }
+}
+
+fn har_response(response: &Response) -> HarResponse {
+    HarResponse {
+        status: response.status,
+        status_text: response.status_text.clone(),
+        http_version: response.protocol.clone().unwrap_or_default(),
+        headers: headers_from_json(response.headers.inner()),
+        content: HarContent {
+            mime_type: response.mime_type.clone(),
+            ..Default::default()
+        },
+        body_size: response.encoded_data_length as i64,
+        ..Default::default()
+    }
+}
+
+/// Convert a CDP headers object into HAR name/value pairs. Multi-value
+/// headers arrive newline-joined and are split into separate entries.
+pub fn headers_from_json(headers: &serde_json::Value) -> Vec<HarHeader> {
+    let Some(map) = headers.as_object() else {
+        return Vec::new();
+    };
+    map.iter()
+        .flat_map(|(name, value)| {
+            let value = value
+                .as_str()
+                .map(String::from)
+                .unwrap_or_else(|| value.to_string());
+            value
+                .split('\n')
+                .map(|v| HarHeader {
+                    name: name.clone(),
+                    value: v.to_string(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn header_value<'a>(headers: &'a [HarHeader], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+/// Query string parameters of a URL, undecoded.
+pub fn query_string(url: &str) -> Vec<HarHeader> {
+    let Some((_, query)) = url.split('#').next().unwrap_or(url).split_once('?') else {
+        return Vec::new();
+    };
+    query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            HarHeader {
+                name: name.to_string(),
+                value: value.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Format seconds since the Unix epoch as ISO 8601 UTC with milliseconds,
+/// e.g. `2024-05-01T12:30:00.250Z`.
+pub fn iso8601(epoch_secs: f64) -> String {
+    let millis = (epoch_secs.max(0.0) * 1000.0).round() as i64;
+    let (days, ms_of_day) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        ms_of_day / 3_600_000,
+        ms_of_day / 60_000 % 60,
+        ms_of_day / 1000 % 60,
+        ms_of_day % 1000
+    )
+}
+
+// ── Replay ──────────────────────────────────────────────────────────────────
+
+/// Hop-by-hop and encoding headers that no longer describe a decoded body.
+const SKIPPED_REPLAY_HEADERS: &[&str] =
+    &["content-encoding", "content-length", "transfer-encoding"];
+
+/// Responses from a HAR file, served by method and URL.
+///
+/// When a request was captured several times (e.g. polling), the recorded
+/// responses are served in order and the last one repeats.
+#[derive(Debug, Default)]
+pub struct HarReplay {
+    pub source: Option<PathBuf>,
+    responses: HashMap<(String, String), Vec<MockResponse>>,
+    served: Mutex<HashMap<(String, String), usize>>,
+}
+
+impl HarReplay {
+    pub fn from_har(har: &Har) -> Self {
+        let mut responses: HashMap<(String, String), Vec<MockResponse>> = HashMap::new();
+        for entry in &har.log.entries {
+            // Failed requests have nothing to replay.
+            if entry.response.status <= 0 {
+                continue;
+            }
+            let response = MockResponse {
+                status: entry.response.status as u16,
+                headers: entry
+                    .response
+                    .headers
+                    .iter()
+                    .filter(|h| {
+                        !SKIPPED_REPLAY_HEADERS
+                            .iter()
+                            .any(|s| h.name.eq_ignore_ascii_case(s))
+                    })
+                    .map(|h| (h.name.clone(), h.value.clone()))
+                    .collect(),
+                body: entry.response.content.body(),
+            };
+            responses
+                .entry(replay_key(&entry.request.method, &entry.request.url))
+                .or_default()
+                .push(response);
+        }
+        Self {
+            source: None,
+            responses,
+            served: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn load_file(path: &Path) -> anyhow::Result<Self> {
+        let mut replay = Self::from_har(&Har::load_file(path)?);
+        replay.source = Some(path.to_path_buf());
+        Ok(replay)
+    }
+
+    /// Number of distinct requests that can be replayed.
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    /// Next recorded response for this request, if it was captured.
+    pub fn next(&self, method: &str, url: &str) -> Option<MockResponse> {
+        let key = replay_key(method, url);
+        let responses = self.responses.get(&key)?;
+        let mut served = self.served.lock().unwrap_or_else(|e| e.into_inner());
+        let count = served.entry(key).or_insert(0);
+        let response = responses[(*count).min(responses.len() - 1)].clone();
+        *count += 1;
+        Some(response)
+    }
+
+    /// One-line summary for tool output and logs.
+    pub fn describe(&self) -> String {
+        match &self.source {
+            Some(path) => format!("{} request(s) from {}", self.len(), path.display()),
+            None => format!("{} request(s)", self.len()),
+        }
+    }
+}
+
+fn replay_key(method: &str, url: &str) -> (String, String) {
+    let url = url.split('#').next().unwrap_or(url);
+    (method.to_ascii_uppercase(), url.to_string())
+}
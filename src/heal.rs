@@ -0,0 +1,151 @@
+//! Self-healing replay: re-finding a recorded element whose locator no
+//! longer matches the live DOM.
+//!
+//! Candidates come from a fresh snapshot's ref index and are scored against
+//! the recorded locator by role, accessible name, enclosing landmark and
+//! whether they kept the recorded ref. Replay retargets the best candidate
+//! when its confidence reaches the threshold and no other candidate comes
+//! close.
+
+use std::collections::HashSet;
+
+use crate::dom::{ElementLocator, RefIndex};
+
+/// Minimum confidence for replay to act on a healed element.
+pub const DEFAULT_THRESHOLD: f64 = 0.7;
+
+/// How much better the best candidate must score than the runner-up.
+const MIN_MARGIN: f64 = 0.05;
+
+const ROLE_WEIGHT: f64 = 0.3;
+const NAME_WEIGHT: f64 = 0.4;
+const LANDMARK_WEIGHT: f64 = 0.15;
+const REF_WEIGHT: f64 = 0.15;
+
+/// The element a healed step was retargeted to.
+#[derive(Debug, Clone)]
+pub struct HealedTarget {
+    pub ref_id: u32,
+    pub locator: ElementLocator,
+    /// Score between 0 and 1.
+    pub confidence: f64,
+}
+
+/// Confidence that `candidate` (now `@e{candidate_ref}`) is the element
+/// recorded as `target` with `recorded_ref`. Candidates of another role, or
+/// another tag for recordings made before roles were stored, score 0.
+pub fn score(
+    target: &ElementLocator,
+    recorded_ref: u32,
+    candidate: &ElementLocator,
+    candidate_ref: u32,
+) -> f64 {
+    let same_kind = match (&target.role, &candidate.role) {
+        (Some(a), Some(b)) => a == b,
+        _ => target.tag.eq_ignore_ascii_case(&candidate.tag),
+    };
+    if !same_kind {
+        return 0.0;
+    }
+    let landmark = if target.landmark == candidate.landmark {
+        1.0
+    } else {
+        0.0
+    };
+    let same_ref = if recorded_ref == candidate_ref {
+        1.0
+    } else {
+        0.0
+    };
+    ROLE_WEIGHT
+        + NAME_WEIGHT * name_similarity(&target.text, &candidate.text)
+        + LANDMARK_WEIGHT * landmark
+        + REF_WEIGHT * same_ref
+}
+
+/// Similarity of two accessible names between 0 and 1: 1 when equal ignoring
+/// case and spacing, otherwise the Dice coefficient of their character pairs.
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let normalize = |s: &str| {
+        s.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    let (a, b) = (normalize(a), normalize(b));
+    if a == b {
+        return 1.0;
+    }
+    let pairs = |s: &str| -> HashSet<(char, char)> {
+        let chars: Vec<char> = s.chars().collect();
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    };
+    let (pa, pb) = (pairs(&a), pairs(&b));
+    if pa.is_empty() || pb.is_empty() {
+        return 0.0;
+    }
+    2.0 * pa.intersection(&pb).count() as f64 / (pa.len() + pb.len()) as f64
+}
+
+/// The candidate in `index` that best matches the recorded element, or why
+/// none is good enough.
+pub fn best_match(
+    target: &ElementLocator,
+    recorded_ref: u32,
+    index: &RefIndex,
+    threshold: f64,
+) -> Result<HealedTarget, String> {
+    let mut scored: Vec<(f64, u32, &ElementLocator)> = index
+        .iter()
+        .map(|(&ref_id, locator)| {
+            (
+                score(target, recorded_ref, locator, ref_id),
+                ref_id,
+                locator,
+            )
+        })
+        .filter(|(score, ..)| *score > 0.0)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    let Some(&(confidence, ref_id, locator)) = scored.first() else {
+        return Err("no element with the same role on the page".into());
+    };
+    if confidence < threshold {
+        return Err(format!(
+            "closest match @e{ref_id} \"{}\" scored {:.0}%, below {:.0}%",
+            locator.text,
+            confidence * 100.0,
+            threshold * 100.0
+        ));
+    }
+    if let Some(&(runner_up, other, _)) = scored.get(1) {
+        if confidence - runner_up < MIN_MARGIN {
+            return Err(format!(
+                "@e{ref_id} and @e{other} match equally well ({:.0}%)",
+                confidence * 100.0
+            ));
+        }
+    }
+    Ok(HealedTarget {
+        ref_id,
+        locator: locator.clone(),
+        confidence,
+    })
+}
+
+/// Whether a replay keeps the locators it healed. Only a run that passed
+/// every step has shown they work; one that failed an assertion, or stopped
+/// on an error, saves nothing.
+pub fn keep_healed(healed_steps: &[usize], failed_step: Option<usize>) -> bool {
+    !healed_steps.is_empty() && failed_step.is_none()
+}
+
+/// Comma-separated step numbers for a replay report.
+pub fn step_list(steps: &[usize]) -> String {
+    steps
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -70,6 +70,8 @@ impl TaskContext {
             url: snapshot.url.clone(),
             nodes: self.filter_nodes(&snapshot.nodes, threshold),
             viewport: snapshot.viewport.clone(),
+            emulation: snapshot.emulation.clone(),
+            auth: snapshot.auth.clone(),
         }
     }
 
@@ -244,6 +246,8 @@ mod tests {
                 make_node(AriaRole::StaticText, "more noise"),
             ],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let ctx = make_ctx(vec!["login"], vec![], false);
         let filtered = ctx.filter_snapshot(&snapshot);
@@ -265,6 +269,8 @@ mod tests {
                 offscreen: None,
             }],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let ctx = make_ctx(vec!["submit"], vec![], false);
         let filtered = ctx.filter_snapshot(&snapshot);
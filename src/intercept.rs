@@ -0,0 +1,285 @@
+//! CDP Fetch interception for request blocking, mocking, HAR replay, the
+//! domain policy and proxy authentication.
+//!
+//! Each tab gets `Fetch.requestPaused` and `Fetch.authRequired` listeners when
+//! its page is created. Interception itself is only enabled on the page while
+//! there are rules to apply, so pages without a blocklist, routes, HAR replay,
+//! a domain policy or proxy credentials load at full speed.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use base64::Engine as _;
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    AuthChallengeResponse, AuthChallengeResponseResponse, AuthChallengeSource,
+    ContinueRequestParams, ContinueWithAuthParams, DisableParams, EnableParams, EventAuthRequired,
+    EventRequestPaused, FailRequestParams, FulfillRequestParams, HeaderEntry, RequestPattern,
+};
+use chromiumoxide::cdp::browser_protocol::network::ErrorReason;
+use chromiumoxide::Page;
+use futures::StreamExt;
+use tracing::{debug, warn};
+
+use crate::blocking::BlockList;
+use crate::har::HarReplay;
+use crate::policy::DomainPolicy;
+use crate::proxy::Credentials;
+use crate::routes::{MockResponse, RouteTable};
+
+/// Session-wide interception rules, shared by all tabs.
+#[derive(Debug, Clone, Default)]
+pub struct InterceptRules {
+    pub blocklist: BlockList,
+    pub routes: RouteTable,
+    /// Responses replayed from a HAR file. Requests missing from the HAR fail.
+    pub har: Option<Arc<HarReplay>>,
+    /// Credentials answered to proxy auth challenges.
+    pub proxy_auth: Option<Credentials>,
+    /// Hosts and schemes documents may load from.
+    pub policy: DomainPolicy,
+}
+
+/// What to do with a paused request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Continue,
+    Block(String),
+    /// A document request that violates the domain policy.
+    Denied(String),
+    Fulfill(MockResponse),
+    /// Unmatched request while offline (route table or HAR replay).
+    Offline,
+    /// A matching route could not be served (e.g. missing fixture file).
+    Error(String),
+}
+
+impl InterceptRules {
+    /// Whether any rule needs Fetch interception enabled.
+    pub fn is_active(&self) -> bool {
+        !self.blocklist.is_empty()
+            || !self.routes.is_empty()
+            || self.har.is_some()
+            || self.proxy_auth.is_some()
+            || self.policy.is_active()
+    }
+
+    /// Decide how to handle a request. The domain policy is checked first,
+    /// for documents only. Blocking wins over routes, so a blocklist still
+    /// applies to mocked sites, and routes win over HAR replay so individual
+    /// responses can be overridden.
+    pub fn decide(&self, method: &str, url: &str, resource_type: &str) -> Decision {
+        if resource_type == "Document" {
+            if let Err(reason) = self.policy.check(url) {
+                return Decision::Denied(reason);
+            }
+        }
+        if let Some(reason) = self.blocklist.block_reason(url, resource_type) {
+            return Decision::Block(reason);
+        }
+        if let Some(route) = self.routes.find(url) {
+            return match self.routes.resolve(route) {
+                Ok(response) => Decision::Fulfill(response),
+                Err(e) => Decision::Error(e.to_string()),
+            };
+        }
+        if let Some(response) = self.har.as_ref().and_then(|h| h.next(method, url)) {
+            return Decision::Fulfill(response);
+        }
+        if (self.routes.offline || self.har.is_some()) && !url.starts_with("data:") {
+            return Decision::Offline;
+        }
+        Decision::Continue
+    }
+
+    /// Answer an auth challenge. Proxy challenges get the configured
+    /// credentials once per request; if the proxy rejects them the request is
+    /// cancelled instead of retrying forever. Server challenges keep Chrome's
+    /// default behaviour.
+    pub fn auth_response(
+        &self,
+        source: Option<&AuthChallengeSource>,
+        first_attempt: bool,
+    ) -> AuthChallengeResponse {
+        match (source, &self.proxy_auth) {
+            (Some(AuthChallengeSource::Proxy), Some(credentials)) if first_attempt => {
+                AuthChallengeResponse::builder()
+                    .response(AuthChallengeResponseResponse::ProvideCredentials)
+                    .username(credentials.username.clone())
+                    .password(credentials.password.expose())
+                    .build()
+                    .expect("response is set")
+            }
+            (Some(AuthChallengeSource::Proxy), Some(_)) => {
+                AuthChallengeResponse::new(AuthChallengeResponseResponse::CancelAuth)
+            }
+            _ => AuthChallengeResponse::new(AuthChallengeResponseResponse::Default),
+        }
+    }
+}
+
+pub type SharedInterceptRules = Arc<RwLock<InterceptRules>>;
+
+/// Per-tab interception counters.
+#[derive(Debug, Default)]
+pub struct InterceptStats {
+    pub blocked: AtomicUsize,
+    pub fulfilled: AtomicUsize,
+    /// Last document request refused by the domain policy, not yet reported.
+    pub violation: Mutex<Option<String>>,
+}
+
+impl InterceptStats {
+    pub fn blocked(&self) -> usize {
+        self.blocked.load(Ordering::Relaxed)
+    }
+
+    pub fn fulfilled(&self) -> usize {
+        self.fulfilled.load(Ordering::Relaxed)
+    }
+
+    /// Take the last policy violation, so each one is reported once.
+    pub fn take_violation(&self) -> Option<String> {
+        self.violation
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+}
+
+/// Subscribe to paused requests on `page` and resolve them against `rules`.
+/// The listener task ends when the page's event stream closes.
+pub async fn attach(
+    page: &Page,
+    rules: SharedInterceptRules,
+    stats: Arc<InterceptStats>,
+) -> anyhow::Result<()> {
+    let mut paused = page.event_listener::<EventRequestPaused>().await?;
+    let mut auth = page.event_listener::<EventAuthRequired>().await?;
+
+    let auth_page = page.clone();
+    let auth_rules = rules.clone();
+    tokio::spawn(async move {
+        let mut answered = HashSet::new();
+        while let Some(ev) = auth.next().await {
+            let first_attempt = answered.insert(ev.request_id.clone());
+            let response = auth_rules
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .auth_response(ev.auth_challenge.source.as_ref(), first_attempt);
+            match response.response {
+                AuthChallengeResponseResponse::ProvideCredentials => {
+                    debug!(origin = %ev.auth_challenge.origin, "answering proxy auth challenge")
+                }
+                AuthChallengeResponseResponse::CancelAuth => {
+                    warn!(origin = %ev.auth_challenge.origin, "proxy rejected credentials")
+                }
+                AuthChallengeResponseResponse::Default => {}
+            }
+            let params = ContinueWithAuthParams::new(ev.request_id.clone(), response);
+            if let Err(e) = auth_page.execute(params).await {
+                warn!(url = %ev.request.url, error = %e, "failed to answer auth challenge");
+            }
+        }
+    });
+
+    let page = page.clone();
+
+    tokio::spawn(async move {
+        while let Some(ev) = paused.next().await {
+            let decision = {
+                let rules = rules.read().unwrap_or_else(|e| e.into_inner());
+                rules.decide(
+                    &ev.request.method,
+                    &ev.request.url,
+                    ev.resource_type.as_ref(),
+                )
+            };
+
+            let id = ev.request_id.clone();
+            let result = match decision {
+                Decision::Continue => page
+                    .execute(ContinueRequestParams::new(id))
+                    .await
+                    .map(|_| ()),
+                Decision::Block(reason) => {
+                    stats.blocked.fetch_add(1, Ordering::Relaxed);
+                    debug!(url = %ev.request.url, reason = %reason, "request blocked");
+                    page.execute(FailRequestParams::new(id, ErrorReason::BlockedByClient))
+                        .await
+                        .map(|_| ())
+                }
+                Decision::Denied(reason) => {
+                    stats.blocked.fetch_add(1, Ordering::Relaxed);
+                    warn!(url = %ev.request.url, reason = %reason, "navigation denied by domain policy");
+                    *stats.violation.lock().unwrap_or_else(|e| e.into_inner()) =
+                        Some(format!("{} ({reason})", ev.request.url));
+                    page.execute(FailRequestParams::new(id, ErrorReason::BlockedByClient))
+                        .await
+                        .map(|_| ())
+                }
+                Decision::Fulfill(response) => {
+                    stats.fulfilled.fetch_add(1, Ordering::Relaxed);
+                    debug!(url = %ev.request.url, status = response.status, "request fulfilled");
+                    page.execute(fulfill_params(id, response)).await.map(|_| ())
+                }
+                Decision::Offline => {
+                    debug!(url = %ev.request.url, "unmatched request failed (offline)");
+                    page.execute(FailRequestParams::new(
+                        id,
+                        ErrorReason::InternetDisconnected,
+                    ))
+                    .await
+                    .map(|_| ())
+                }
+                Decision::Error(e) => {
+                    warn!(url = %ev.request.url, error = %e, "route could not be served");
+                    page.execute(FailRequestParams::new(id, ErrorReason::Failed))
+                        .await
+                        .map(|_| ())
+                }
+            };
+            if let Err(e) = result {
+                warn!(url = %ev.request.url, error = %e, "failed to resolve paused request");
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn fulfill_params(
+    id: chromiumoxide::cdp::browser_protocol::fetch::RequestId,
+    response: MockResponse,
+) -> FulfillRequestParams {
+    let mut params = FulfillRequestParams::new(id, response.status as i64);
+    params.response_headers = Some(
+        response
+            .headers
+            .into_iter()
+            .map(|(name, value)| HeaderEntry::new(name, value))
+            .collect(),
+    );
+    params.body = Some(
+        base64::engine::general_purpose::STANDARD
+            .encode(&response.body)
+            .into(),
+    );
+    params
+}
+
+/// Enable or disable Fetch interception on `page` to match `rules`.
+pub async fn sync(page: &Page, rules: &InterceptRules) -> anyhow::Result<()> {
+    if rules.is_active() {
+        page.execute(
+            EnableParams::builder()
+                .pattern(RequestPattern::builder().url_pattern("*").build())
+                .handle_auth_requests(rules.proxy_auth.is_some())
+                .build(),
+        )
+        .await?;
+    } else {
+        page.execute(DisableParams::default()).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,527 @@
+//! Auth profile import and export in other tools' formats.
+//!
+//! - Netscape `cookies.txt`, as written by curl, wget, yt-dlp and browser
+//!   extensions: one tab-separated line per cookie, with httpOnly cookies
+//!   prefixed `#HttpOnly_`. The format has no SameSite column, so SameSite is
+//!   lost on export and unset on import.
+//! - Playwright `storageState.json`: cookies plus `localStorage` (and, since
+//!   Playwright 1.51, IndexedDB) per origin. It has no `sessionStorage`, so
+//!   that is dropped on export.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{
+    create_private, write_private, AuthProfile, AuthStore, IndexedDbDatabase, IndexedDbIndex,
+    IndexedDbRecord, IndexedDbStore, OriginStorage, StoredCookie,
+};
+use crate::recording::extract_domain;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    CookiesTxt,
+    StorageState,
+}
+
+impl Format {
+    /// Guess the format of a file from its contents: storageState is a JSON
+    /// object, cookies.txt is not.
+    pub fn detect(contents: &str) -> Format {
+        if contents.trim_start().starts_with('{') {
+            Format::StorageState
+        } else {
+            Format::CookiesTxt
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cookies-txt" | "cookies.txt" | "netscape" => Ok(Format::CookiesTxt),
+            "storage-state" | "storagestate" | "playwright" => Ok(Format::StorageState),
+            _ => Err(format!(
+                "unknown format '{s}', expected cookies-txt or storage-state"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::CookiesTxt => f.write_str("cookies-txt"),
+            Format::StorageState => f.write_str("storage-state"),
+        }
+    }
+}
+
+/// Cookies and storage read from a file, ready to save as a profile.
+#[derive(Debug, Clone, Default)]
+pub struct Imported {
+    pub cookies: Vec<StoredCookie>,
+    pub origins: Vec<OriginStorage>,
+}
+
+impl Imported {
+    /// URL to file the profile under: the first origin with storage, else
+    /// the first cookie's domain.
+    pub fn default_url(&self) -> Option<String> {
+        if let Some(origin) = self.origins.first() {
+            return Some(origin.origin.clone());
+        }
+        let cookie = self.cookies.first()?;
+        let scheme = if cookie.secure { "https" } else { "http" };
+        Some(format!(
+            "{scheme}://{}",
+            cookie.domain.trim_start_matches('.')
+        ))
+    }
+}
+
+pub fn import(contents: &str, format: Format) -> anyhow::Result<Imported> {
+    match format {
+        Format::CookiesTxt => Ok(Imported {
+            cookies: parse_cookies_txt(contents)?,
+            origins: Vec::new(),
+        }),
+        Format::StorageState => parse_storage_state(contents),
+    }
+}
+
+pub fn export(profile: &AuthProfile, format: Format) -> anyhow::Result<String> {
+    match format {
+        Format::CookiesTxt => Ok(to_cookies_txt(&profile.cookies)),
+        Format::StorageState => to_storage_state(profile),
+    }
+}
+
+/// Read `path` and save it as `profile`. The format is detected from the
+/// contents unless given; the profile is filed under `url`, defaulting to
+/// [`Imported::default_url`].
+pub fn import_file(
+    store: &AuthStore,
+    path: &Path,
+    profile: &str,
+    format: Option<Format>,
+    url: Option<&str>,
+) -> anyhow::Result<AuthProfile> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {e}", path.display()))?;
+    let format = format.unwrap_or_else(|| Format::detect(&contents));
+    let imported = import(&contents, format)?;
+    if imported.cookies.is_empty() && imported.origins.is_empty() {
+        anyhow::bail!("No cookies or storage found in {}", path.display());
+    }
+    let url = match url {
+        Some(url) => url.to_string(),
+        None => imported
+            .default_url()
+            .ok_or_else(|| anyhow::anyhow!("Cannot tell the site from the file, pass a URL"))?,
+    };
+    store.save_with_storage(&url, profile, imported.cookies, imported.origins)?;
+    store.load(profile, Some(&extract_domain(&url)))
+}
+
+/// Write a saved profile to `path`. The file holds live credentials, so it is
+/// created readable by the owner only. An existing file is only replaced
+/// when `overwrite` is set.
+pub fn export_file(
+    store: &AuthStore,
+    profile: &str,
+    domain: Option<&str>,
+    path: &Path,
+    format: Format,
+    overwrite: bool,
+) -> anyhow::Result<AuthProfile> {
+    let profile = store.load(profile, domain)?;
+    let contents = export(&profile, format)?;
+    if overwrite {
+        write_private(path, &contents)?;
+    } else {
+        create_private(path, &contents)?;
+    }
+    Ok(profile)
+}
+
+/// Directory the `import_auth` and `export_auth` tools read and write:
+/// `~/.cortex-browser/auth-exports`. It is kept apart from the profiles so
+/// an exported file is never mistaken for one.
+pub fn exports_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".cortex-browser")
+        .join("auth-exports")
+}
+
+// ── cookies.txt ─────────────────────────────────────────────────────────────
+
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+pub fn parse_cookies_txt(contents: &str) -> anyhow::Result<Vec<StoredCookie>> {
+    let mut cookies = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+            anyhow::bail!(
+                "cookies.txt line {}: expected 7 tab-separated fields, found {}",
+                number + 1,
+                fields.len()
+            );
+        };
+        // Errors name the line only, since fields can hold cookie values.
+        let expires: f64 = expires
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("cookies.txt line {}: invalid expiry", number + 1))?;
+        // A leading dot marks a cookie sent to subdomains too.
+        let domain = if is_true(subdomains) && !domain.starts_with('.') {
+            format!(".{domain}")
+        } else {
+            domain.to_string()
+        };
+        cookies.push(StoredCookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain,
+            path: path.to_string(),
+            expires: (expires > 0.0).then_some(expires),
+            http_only,
+            secure: is_true(secure),
+            same_site: None,
+        });
+    }
+    Ok(cookies)
+}
+
+fn is_true(field: &str) -> bool {
+    field.trim().eq_ignore_ascii_case("TRUE")
+}
+
+pub fn to_cookies_txt(cookies: &[StoredCookie]) -> String {
+    let flag = |b: bool| if b { "TRUE" } else { "FALSE" };
+    let mut out = String::from("# Netscape HTTP Cookie File\n");
+    for c in cookies {
+        out.push_str(&format!(
+            "{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            if c.http_only { HTTP_ONLY_PREFIX } else { "" },
+            c.domain,
+            flag(c.domain.starts_with('.')),
+            c.path,
+            flag(c.secure),
+            c.expires.map(|e| e as i64).unwrap_or(0),
+            c.name,
+            c.value
+        ));
+    }
+    out
+}
+
+// ── Playwright storageState ─────────────────────────────────────────────────
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StorageState {
+    #[serde(default)]
+    cookies: Vec<StateCookie>,
+    #[serde(default)]
+    origins: Vec<StateOrigin>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StateCookie {
+    name: String,
+    value: String,
+    domain: String,
+    #[serde(default = "root_path")]
+    path: String,
+    /// Unix seconds, -1 for a session cookie.
+    #[serde(default = "session_expiry")]
+    expires: f64,
+    #[serde(default)]
+    http_only: bool,
+    #[serde(default)]
+    secure: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    same_site: Option<String>,
+}
+
+fn root_path() -> String {
+    "/".into()
+}
+
+fn session_expiry() -> f64 {
+    -1.0
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StateOrigin {
+    origin: String,
+    #[serde(default)]
+    local_storage: Vec<StateItem>,
+    #[serde(default, rename = "indexedDB", skip_serializing_if = "Vec::is_empty")]
+    indexed_db: Vec<StateDatabase>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StateItem {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StateDatabase {
+    name: String,
+    version: u64,
+    #[serde(default)]
+    stores: Vec<StateStore>,
+}
+
+/// Playwright splits key paths into `keyPath` (string) and `keyPathArray`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StateStore {
+    name: String,
+    #[serde(default)]
+    auto_increment: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_path_array: Option<Vec<String>>,
+    #[serde(default)]
+    records: Vec<StateRecord>,
+    #[serde(default)]
+    indexes: Vec<StateIndex>,
+}
+
+/// Records with `keyEncoded`/`valueEncoded` (Dates, typed arrays) are not
+/// plain JSON and are skipped on import.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StateRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<serde_json::Value>,
+    #[serde(default, skip_serializing)]
+    key_encoded: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StateIndex {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_path_array: Option<Vec<String>>,
+    #[serde(default)]
+    multi_entry: bool,
+    #[serde(default)]
+    unique: bool,
+}
+
+fn join_key_path(
+    key_path: Option<String>,
+    array: Option<Vec<String>>,
+) -> Option<serde_json::Value> {
+    match (key_path, array) {
+        (_, Some(array)) => Some(serde_json::json!(array)),
+        (Some(path), None) => Some(serde_json::Value::String(path)),
+        (None, None) => None,
+    }
+}
+
+fn split_key_path(key_path: &Option<serde_json::Value>) -> (Option<String>, Option<Vec<String>>) {
+    match key_path {
+        Some(serde_json::Value::String(path)) => (Some(path.clone()), None),
+        Some(serde_json::Value::Array(parts)) => (
+            None,
+            Some(
+                parts
+                    .iter()
+                    .filter_map(|p| p.as_str().map(str::to_string))
+                    .collect(),
+            ),
+        ),
+        _ => (None, None),
+    }
+}
+
+fn parse_storage_state(contents: &str) -> anyhow::Result<Imported> {
+    let state: StorageState = serde_json::from_str(contents).map_err(|e| {
+        // serde's message can quote a cookie value, so only say where.
+        anyhow::anyhow!(
+            "Invalid storageState JSON at line {}, column {}",
+            e.line(),
+            e.column()
+        )
+    })?;
+    let cookies = state
+        .cookies
+        .into_iter()
+        .map(|c| StoredCookie {
+            name: c.name,
+            value: c.value,
+            domain: c.domain,
+            path: c.path,
+            expires: (c.expires > 0.0).then_some(c.expires),
+            http_only: c.http_only,
+            secure: c.secure,
+            same_site: c.same_site,
+        })
+        .collect();
+    let origins = state
+        .origins
+        .into_iter()
+        .map(|o| OriginStorage {
+            origin: o.origin,
+            local_storage: o
+                .local_storage
+                .into_iter()
+                .map(|item| (item.name, item.value))
+                .collect(),
+            session_storage: BTreeMap::new(),
+            indexed_db: o
+                .indexed_db
+                .into_iter()
+                .map(|db| IndexedDbDatabase {
+                    name: db.name,
+                    version: db.version,
+                    stores: db
+                        .stores
+                        .into_iter()
+                        .map(|s| IndexedDbStore {
+                            name: s.name,
+                            key_path: join_key_path(s.key_path, s.key_path_array),
+                            auto_increment: s.auto_increment,
+                            indexes: s
+                                .indexes
+                                .into_iter()
+                                .map(|i| IndexedDbIndex {
+                                    name: i.name,
+                                    key_path: join_key_path(i.key_path, i.key_path_array)
+                                        .unwrap_or_default(),
+                                    unique: i.unique,
+                                    multi_entry: i.multi_entry,
+                                })
+                                .collect(),
+                            records: s
+                                .records
+                                .into_iter()
+                                .filter(|r| r.key_encoded.is_none())
+                                .filter_map(|r| {
+                                    Some(IndexedDbRecord {
+                                        // Inline keys live in the value.
+                                        key: r.key.unwrap_or_default(),
+                                        value: r.value?,
+                                    })
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        })
+        .collect();
+    Ok(Imported { cookies, origins })
+}
+
+fn to_storage_state(profile: &AuthProfile) -> anyhow::Result<String> {
+    let state = StorageState {
+        cookies: profile
+            .cookies
+            .iter()
+            .map(|c| StateCookie {
+                name: c.name.clone(),
+                value: c.value.clone(),
+                domain: c.domain.clone(),
+                path: c.path.clone(),
+                expires: c.expires.unwrap_or(-1.0),
+                http_only: c.http_only,
+                secure: c.secure,
+                // Playwright requires sameSite; Chrome treats unset as Lax.
+                same_site: Some(c.same_site.clone().unwrap_or_else(|| "Lax".into())),
+            })
+            .collect(),
+        origins: profile
+            .origins
+            .iter()
+            .filter(|o| !o.local_storage.is_empty() || !o.indexed_db.is_empty())
+            .map(|o| StateOrigin {
+                origin: o.origin.clone(),
+                local_storage: o
+                    .local_storage
+                    .iter()
+                    .map(|(name, value)| StateItem {
+                        name: name.clone(),
+                        value: value.clone(),
+                    })
+                    .collect(),
+                indexed_db: o
+                    .indexed_db
+                    .iter()
+                    .map(|db| StateDatabase {
+                        name: db.name.clone(),
+                        version: db.version,
+                        stores: db
+                            .stores
+                            .iter()
+                            .map(|s| {
+                                let (key_path, key_path_array) = split_key_path(&s.key_path);
+                                StateStore {
+                                    name: s.name.clone(),
+                                    auto_increment: s.auto_increment,
+                                    key_path,
+                                    records: s
+                                        .records
+                                        .iter()
+                                        .map(|r| StateRecord {
+                                            key: (s.key_path.is_none()).then(|| r.key.clone()),
+                                            value: Some(r.value.clone()),
+                                            key_encoded: None,
+                                        })
+                                        .collect(),
+                                    key_path_array,
+                                    indexes: s
+                                        .indexes
+                                        .iter()
+                                        .map(|i| {
+                                            let (key_path, key_path_array) =
+                                                split_key_path(&Some(i.key_path.clone()));
+                                            StateIndex {
+                                                name: i.name.clone(),
+                                                key_path,
+                                                key_path_array,
+                                                multi_entry: i.multi_entry,
+                                                unique: i.unique,
+                                            }
+                                        })
+                                        .collect(),
+                                }
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_string_pretty(&state)?)
+}
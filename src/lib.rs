@@ -1,11 +1,31 @@
+pub mod access;
 pub mod auth;
+pub mod blocking;
 pub mod browser;
+pub mod config;
+pub mod confirm;
 pub mod diff;
 pub mod dom;
+pub mod emulation;
 pub mod extract;
+pub mod har;
+pub mod heal;
 pub mod hints;
+pub mod intercept;
+pub mod interchange;
 pub mod mcp;
 pub mod mutation;
+pub mod network;
 pub mod pipeline;
+pub mod playwright;
+pub mod policy;
+pub mod pool;
+pub mod proxy;
 pub mod recording;
+pub mod routes;
+pub mod secrets;
 pub mod serialize;
+pub mod storage;
+pub mod totp;
+pub mod vault;
+pub mod verify;
@@ -1,9 +1,15 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, info};
 
-use cortex_browser::{browser, dom, mcp, pipeline, serialize};
+use cortex_browser::{
+    access, auth, blocking, browser, config, confirm, dom, emulation, har, intercept, interchange,
+    mcp, pipeline, playwright, policy, proxy, recording, routes, serialize, totp, vault,
+};
 
 #[derive(Parser)]
 #[command(name = "cortex-browser")]
@@ -31,6 +37,12 @@ enum Commands {
         /// Launch a new headless Chrome instead of connecting
         #[arg(short, long)]
         launch: bool,
+
+        #[command(flatten)]
+        browser: BrowserArgs,
+
+        #[command(flatten)]
+        blocking: BlockArgs,
     },
 
     /// Start as an MCP (Model Context Protocol) server over stdio
@@ -42,6 +54,24 @@ enum Commands {
         /// Launch a new headless Chrome instead of connecting
         #[arg(short, long)]
         launch: bool,
+
+        /// Open tabs in a fresh incognito browser context instead of the
+        /// browser's default one, so cookies and storage are not shared
+        #[arg(long)]
+        isolate: bool,
+
+        /// After a browser crash, re-apply the auth profile last loaded with restore_auth
+        #[arg(long)]
+        recover_auth: bool,
+
+        #[command(flatten)]
+        browser: BrowserArgs,
+
+        #[command(flatten)]
+        emulation: EmulationArgs,
+
+        #[command(flatten)]
+        intercept: InterceptArgs,
     },
 
     /// Start as an MCP server over HTTP (Streamable HTTP + SSE transport)
@@ -61,9 +91,464 @@ enum Commands {
         /// Port to serve the MCP HTTP endpoint on
         #[arg(long, default_value_t = 8080)]
         http_port: u16,
+
+        /// Refuse new sessions while this many are active (each session has
+        /// its own incognito browser context)
+        #[arg(long, value_name = "N")]
+        max_sessions: Option<usize>,
+
+        /// Close sessions idle for this many seconds (0 keeps them forever)
+        #[arg(long, value_name = "SECS", default_value_t = 1800)]
+        idle_timeout: u64,
+
+        /// Chrome processes shared by all sessions (with --launch)
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        pool_size: usize,
+
+        /// Sessions hosted by each pooled Chrome before another is launched
+        #[arg(long, value_name = "N", default_value_t = 8)]
+        sessions_per_browser: usize,
+
+        /// Require clients to send "Authorization: Bearer <TOKEN>"
+        #[arg(long, value_name = "TOKEN", conflicts_with = "token_file")]
+        token: Option<String>,
+
+        /// Read the bearer token from the first line of a file
+        #[arg(long, value_name = "FILE")]
+        token_file: Option<PathBuf>,
+
+        /// Browser origin allowed to call the server besides localhost (repeatable, "*" for any)
+        #[arg(long = "allowed-origin", value_name = "ORIGIN")]
+        allowed_origins: Vec<String>,
+
+        /// Serve HTTPS with this PEM certificate (requires --tls-key)
+        #[arg(long, value_name = "FILE", requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+
+        /// PEM private key for --tls-cert
+        #[arg(long, value_name = "FILE", requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+
+        /// After a browser crash, re-apply the auth profile last loaded with restore_auth
+        #[arg(long)]
+        recover_auth: bool,
+
+        #[command(flatten)]
+        browser: BrowserArgs,
+
+        #[command(flatten)]
+        emulation: EmulationArgs,
+
+        #[command(flatten)]
+        intercept: InterceptArgs,
+    },
+
+    /// Re-encrypt saved auth profiles with a new key. The current key is read
+    /// from CORTEX_AUTH_KEY_FILE or CORTEX_AUTH_PASSPHRASE (leave both unset
+    /// to encrypt plaintext profiles for the first time).
+    RotateKey {
+        /// File holding the new base64-encoded 32-byte key; without it the new
+        /// passphrase is read from CORTEX_AUTH_NEW_PASSPHRASE
+        #[arg(long, value_name = "FILE")]
+        new_key_file: Option<PathBuf>,
+
+        /// Directory holding the profiles (default: ~/.cortex-browser/auth)
+        #[arg(long, value_name = "DIR")]
+        auth_dir: Option<PathBuf>,
+    },
+
+    /// Convert auth profiles to and from Netscape cookies.txt and Playwright
+    /// storageState files, and store TOTP seeds. Encrypted profiles use the
+    /// key from CORTEX_AUTH_KEY_FILE or CORTEX_AUTH_PASSPHRASE.
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+
+    /// Export a saved recording as a Playwright test in TypeScript or Python
+    ExportRecording {
+        /// Recording to export
+        name: String,
+
+        /// Domain the recording was saved under, if the name is ambiguous
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// typescript or python
+        #[arg(short, long, default_value = "typescript")]
+        language: playwright::Language,
+
+        /// File to write (default: stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Replace the output file if it already exists
+        #[arg(long)]
+        force: bool,
+
+        /// Directory holding the recordings (default: ~/.cortex-browser/recordings)
+        #[arg(long, value_name = "DIR")]
+        recordings_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthAction {
+    /// Save a cookies.txt or storageState file as an auth profile
+    Import {
+        /// File to read
+        file: PathBuf,
+
+        /// Name of the profile to create or replace
+        #[arg(long)]
+        profile: String,
+
+        /// cookies-txt or storage-state (detected from the contents by default)
+        #[arg(long)]
+        format: Option<interchange::Format>,
+
+        /// Site the profile belongs to (default: the first cookie's domain)
+        #[arg(long)]
+        url: Option<String>,
+
+        /// Directory holding the profiles (default: ~/.cortex-browser/auth)
+        #[arg(long, value_name = "DIR")]
+        auth_dir: Option<PathBuf>,
+    },
+
+    /// Store the TOTP seed of a profile's account for 2FA logins. The base32
+    /// seed or otpauth:// URI is read from stdin, so it stays out of shell
+    /// history. Requires an encryption key.
+    SetTotp {
+        /// Profile to add the seed to
+        profile: String,
+
+        /// Domain the profile was saved under, if the name is ambiguous
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Remove the profile's seed instead
+        #[arg(long)]
+        remove: bool,
+
+        /// Directory holding the profiles (default: ~/.cortex-browser/auth)
+        #[arg(long, value_name = "DIR")]
+        auth_dir: Option<PathBuf>,
+    },
+
+    /// Write an auth profile as a cookies.txt or storageState file
+    Export {
+        /// Profile to export
+        profile: String,
+
+        /// Domain the profile was saved under, if the name is ambiguous
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// cookies-txt or storage-state
+        #[arg(long, default_value = "storage-state")]
+        format: interchange::Format,
+
+        /// File to write
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+
+        /// Directory holding the profiles (default: ~/.cortex-browser/auth)
+        #[arg(long, value_name = "DIR")]
+        auth_dir: Option<PathBuf>,
     },
 }
 
+/// Chrome launch settings (used with --launch). Flags override the config file.
+#[derive(Args)]
+struct BrowserArgs {
+    /// Config file (default: ~/.cortex-browser/config.toml if it exists)
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Chrome/Chromium binary to launch instead of the auto-detected one
+    #[arg(long, value_name = "PATH")]
+    chrome: Option<PathBuf>,
+
+    /// Persistent profile directory to reuse across launches
+    #[arg(long, value_name = "DIR")]
+    user_data_dir: Option<PathBuf>,
+
+    /// Show the browser window instead of running headless
+    #[arg(long)]
+    headful: bool,
+
+    /// Browser window size, e.g. 1280x800
+    #[arg(long, value_name = "WxH")]
+    window_size: Option<browser::Size>,
+
+    /// Emulated viewport size (defaults to the window size, else 800x600)
+    #[arg(long, value_name = "WxH")]
+    viewport: Option<browser::Size>,
+
+    /// Extra flag passed to Chrome (repeatable, e.g. --chrome-arg=--lang=de)
+    #[arg(long = "chrome-arg", value_name = "ARG", allow_hyphen_values = true)]
+    chrome_args: Vec<String>,
+
+    /// Seconds to wait for Chrome to start
+    #[arg(long, value_name = "SECS")]
+    launch_timeout: Option<u64>,
+
+    /// Seconds to wait for a CDP command to respond
+    #[arg(long, value_name = "SECS")]
+    request_timeout: Option<u64>,
+
+    /// Route browser traffic through a proxy, e.g. http://proxy.internal:3128
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Hosts that skip the proxy, separated by ';' (e.g. "localhost;*.internal")
+    #[arg(long, value_name = "LIST")]
+    proxy_bypass: Option<String>,
+
+    /// Credentials answered to proxy auth challenges (MCP servers only)
+    #[arg(long, value_name = "USER:PASS")]
+    proxy_auth: Option<proxy::Credentials>,
+}
+
+impl BrowserArgs {
+    fn load_config(&self) -> Result<config::Config> {
+        config::Config::load(self.config.as_deref())
+    }
+
+    /// Apply the flags on top of the config file's `[browser]` settings.
+    fn into_browser_options(self, mut options: browser::BrowserOptions) -> browser::BrowserOptions {
+        if let Some(path) = self.chrome {
+            options.executable = Some(path);
+        }
+        if let Some(dir) = self.user_data_dir {
+            options.user_data_dir = Some(dir);
+        }
+        options.headful |= self.headful;
+        if let Some(size) = self.window_size {
+            options.window_size = Some(size);
+        }
+        if let Some(size) = self.viewport {
+            options.viewport = Some(size);
+        }
+        options.args.extend(self.chrome_args);
+        if let Some(secs) = self.launch_timeout {
+            options.launch_timeout = Some(secs);
+        }
+        if let Some(secs) = self.request_timeout {
+            options.request_timeout = Some(secs);
+        }
+        if let Some(server) = self.proxy {
+            options.proxy.server = Some(server);
+        }
+        if let Some(bypass) = self.proxy_bypass {
+            options.proxy.bypass = Some(bypass);
+        }
+        if let Some(credentials) = self.proxy_auth {
+            options.proxy.username = Some(credentials.username);
+            options.proxy.password = Some(credentials.password);
+        }
+        options
+    }
+}
+
+/// Device and locale emulation applied to every page of the session.
+/// Flags override the config file's `[emulation]` settings.
+#[derive(Args)]
+struct EmulationArgs {
+    /// Device preset: iphone, iphone-se, pixel, galaxy, ipad, ipad-pro, desktop
+    #[arg(long, value_name = "NAME")]
+    device: Option<String>,
+
+    /// User agent string to send and report
+    #[arg(long, value_name = "UA")]
+    user_agent: Option<String>,
+
+    /// Locale and Accept-Language, e.g. de-DE
+    #[arg(long, value_name = "LOCALE")]
+    locale: Option<String>,
+
+    /// IANA timezone, e.g. Europe/Berlin
+    #[arg(long, value_name = "TZ")]
+    timezone: Option<String>,
+
+    /// Emulated position, e.g. 52.52,13.405
+    #[arg(long, value_name = "LAT,LON")]
+    geolocation: Option<emulation::Geolocation>,
+
+    /// prefers-color-scheme: light, dark or no-preference
+    #[arg(long, value_name = "SCHEME")]
+    color_scheme: Option<String>,
+
+    /// Emulate prefers-reduced-motion: reduce
+    #[arg(long)]
+    reduced_motion: bool,
+}
+
+impl EmulationArgs {
+    fn into_emulation(self, mut emulation: emulation::Emulation) -> Result<emulation::Emulation> {
+        emulation.merge(emulation::Emulation {
+            device: self.device,
+            user_agent: self.user_agent,
+            locale: self.locale,
+            timezone: self.timezone,
+            geolocation: self.geolocation,
+            color_scheme: self.color_scheme,
+            reduced_motion: self.reduced_motion,
+            ..Default::default()
+        });
+        emulation.validate()?;
+        Ok(emulation)
+    }
+}
+
+/// Request headers, domain policy, approval rules, blocking, mocking and HAR capture applied to every page the server opens.
+#[derive(Args)]
+struct InterceptArgs {
+    /// Send an extra header with every request (repeatable, e.g. "X-Env: staging")
+    #[arg(long = "header", value_name = "NAME: VALUE", value_parser = proxy::parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// Only allow navigation to this host or its subdomains (repeatable, globs like "*.example.com")
+    #[arg(long = "allow-host", value_name = "HOST")]
+    allow_hosts: Vec<String>,
+
+    /// Never navigate to this host or its subdomains (repeatable, wins over --allow-host)
+    #[arg(long = "deny-host", value_name = "HOST")]
+    deny_hosts: Vec<String>,
+
+    /// Allow navigation to a URL scheme (repeatable; http and https by default once a policy is set)
+    #[arg(long = "allow-scheme", value_name = "SCHEME")]
+    allow_schemes: Vec<String>,
+
+    /// Ask a human before clicking an element whose name matches this regex (repeatable, e.g. "(?i)place order")
+    #[arg(long = "confirm-click", value_name = "REGEX")]
+    confirm_clicks: Vec<String>,
+
+    /// Ask a human before submitting a form on this host or its subdomains (repeatable)
+    #[arg(long = "confirm-submit", value_name = "HOST")]
+    confirm_submits: Vec<String>,
+
+    /// Ask a human before typing into password fields
+    #[arg(long)]
+    confirm_passwords: bool,
+
+    #[command(flatten)]
+    blocking: BlockArgs,
+
+    /// Serve matching requests from fixtures defined in a JSON or TOML routes file
+    #[arg(long, value_name = "FILE")]
+    routes: Option<PathBuf>,
+
+    /// Capture every request of the session into a HAR file, written on shutdown
+    #[arg(long, value_name = "FILE")]
+    har: Option<PathBuf>,
+
+    /// Serve responses from a HAR file instead of the network; unmatched requests fail
+    #[arg(long, value_name = "FILE")]
+    har_replay: Option<PathBuf>,
+}
+
+/// Requests to block on every page, shared by the servers and `snapshot`.
+#[derive(Args)]
+struct BlockArgs {
+    /// Block requests whose URL matches this glob (repeatable, e.g. "*://*/ads/*")
+    #[arg(long = "block", value_name = "GLOB")]
+    patterns: Vec<String>,
+
+    /// Block a resource type such as image, font, media or stylesheet (repeatable)
+    #[arg(long = "block-type", value_name = "TYPE")]
+    resource_types: Vec<String>,
+
+    /// Block requests to the bundled list of ad and tracker hosts
+    #[arg(long)]
+    block_trackers: bool,
+
+    /// Load additional hosts and URL globs to block from a file (one per line)
+    #[arg(long, value_name = "FILE")]
+    blocklist: Option<PathBuf>,
+}
+
+impl BlockArgs {
+    fn into_blocklist(self) -> Result<blocking::BlockList> {
+        let mut blocklist = blocking::BlockList {
+            url_patterns: self.patterns,
+            hosts: Vec::new(),
+            resource_types: self.resource_types,
+            trackers: self.block_trackers,
+        };
+        if let Some(path) = &self.blocklist {
+            blocklist.load_file(path)?;
+        }
+        Ok(blocklist)
+    }
+}
+
+impl InterceptArgs {
+    fn into_server_options(self) -> Result<mcp::ServerOptions> {
+        let blocklist = self.blocking.into_blocklist()?;
+        let routes = match &self.routes {
+            Some(path) => routes::RouteTable::load_file(path)?,
+            None => routes::RouteTable::default(),
+        };
+        let har_replay = match &self.har_replay {
+            Some(path) => Some(Arc::new(har::HarReplay::load_file(path)?)),
+            None => None,
+        };
+        Ok(mcp::ServerOptions {
+            extra_headers: self.headers.into_iter().collect(),
+            policy: policy::DomainPolicy {
+                allow: self.allow_hosts,
+                deny: self.deny_hosts,
+                schemes: self.allow_schemes,
+            },
+            confirm: confirm::ConfirmRules {
+                buttons: self.confirm_clicks,
+                submit_hosts: self.confirm_submits,
+                passwords: self.confirm_passwords,
+            },
+            blocklist,
+            routes,
+            har_replay,
+            har_capture: self.har.map(har::HarRecorder::shared),
+            ..Default::default()
+        })
+    }
+}
+
+/// Combine the config file and flags into the MCP server's startup options.
+fn server_options(
+    browser: BrowserArgs,
+    emulation: EmulationArgs,
+    intercept: InterceptArgs,
+) -> Result<mcp::ServerOptions> {
+    let config = browser.load_config()?;
+    let mut options = intercept.into_server_options()?;
+    for (name, value) in &config.headers {
+        proxy::validate_header(name, value).map_err(anyhow::Error::msg)?;
+    }
+    let mut extra_headers = config.headers;
+    proxy::merge_headers(
+        &mut extra_headers,
+        std::mem::take(&mut options.extra_headers),
+    );
+    let mut policy = config.policy;
+    policy.merge(std::mem::take(&mut options.policy));
+    let mut confirm = config.confirm;
+    confirm.merge(std::mem::take(&mut options.confirm));
+    confirm.validate()?;
+    Ok(mcp::ServerOptions {
+        browser: browser.into_browser_options(config.browser),
+        emulation: emulation.into_emulation(config.emulation)?,
+        extra_headers,
+        policy,
+        confirm,
+        auth_key: vault::AuthKey::from_env()?,
+        probes: config.probes,
+        ..options
+    })
+}
+
 fn main() -> Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -81,45 +566,244 @@ fn main() -> Result<()> {
             format,
             port,
             launch,
+            browser,
+            blocking,
         } => {
             info!(input = %input, format = %format, "snapshot command");
             if is_url(&input) {
+                let config = browser.load_config()?;
+                let options = browser.into_browser_options(config.browser);
+                let rules = intercept::InterceptRules {
+                    blocklist: blocking.into_blocklist()?,
+                    ..Default::default()
+                };
                 let rt = tokio::runtime::Runtime::new()?;
-                rt.block_on(run_browser_snapshot(&input, &format, port, launch))
+                rt.block_on(run_browser_snapshot(
+                    &input, &format, port, launch, &options, &rules,
+                ))
             } else {
                 run_file_snapshot(&input, &format)
             }
         }
-        Commands::Mcp { port, launch } => {
+        Commands::Mcp {
+            port,
+            launch,
+            isolate,
+            recover_auth,
+            browser,
+            emulation,
+            intercept,
+        } => {
             info!(port = port, launch = launch, "starting MCP server");
+            let options = mcp::ServerOptions {
+                isolate,
+                recover_auth,
+                ..server_options(browser, emulation, intercept)?
+            };
             let rt = tokio::runtime::Runtime::new()?;
-            rt.block_on(mcp::run_mcp_server(launch, port))
+            rt.block_on(mcp::run_mcp_server(launch, port, options))
         }
         Commands::McpHttp {
             port,
             launch,
             host,
             http_port,
+            max_sessions,
+            idle_timeout,
+            pool_size,
+            sessions_per_browser,
+            token,
+            token_file,
+            allowed_origins,
+            tls_cert,
+            tls_key,
+            recover_auth,
+            browser,
+            emulation,
+            intercept,
         } => {
             info!(port = port, launch = launch, host = %host, http_port = http_port, "starting MCP HTTP server");
+            let options = mcp::ServerOptions {
+                recover_auth,
+                ..server_options(browser, emulation, intercept)?
+            };
             let rt = tokio::runtime::Runtime::new()?;
-            rt.block_on(mcp::run_mcp_http_server(launch, port, &host, http_port))
+            let http = mcp::HttpServerOptions {
+                host,
+                port: http_port,
+                max_sessions,
+                idle_timeout: (idle_timeout > 0).then(|| Duration::from_secs(idle_timeout)),
+                pool_size,
+                sessions_per_browser,
+                access: access::AccessPolicy {
+                    token: access::load_token(token, token_file.as_deref())?,
+                    allowed_origins,
+                },
+                tls: tls_cert.zip(tls_key),
+            };
+            rt.block_on(mcp::run_mcp_http_server(launch, port, http, options))
+        }
+        Commands::RotateKey {
+            new_key_file,
+            auth_dir,
+        } => {
+            let new_key = match new_key_file {
+                Some(path) => vault::AuthKey::from_key_file(&path)?,
+                None => {
+                    let passphrase = std::env::var(vault::NEW_PASSPHRASE_ENV).map_err(|_| {
+                        anyhow::anyhow!("Pass --new-key-file or set {}", vault::NEW_PASSPHRASE_ENV)
+                    })?;
+                    vault::AuthKey::passphrase(&passphrase)?
+                }
+            };
+            let store = auth_dir
+                .map(auth::AuthStore::with_base)
+                .unwrap_or_default()
+                .with_key(vault::AuthKey::from_env()?);
+            let count = store.rekey(&new_key)?;
+            info!(profiles = count, "auth profiles re-encrypted");
+            println!("Re-encrypted {count} auth profile(s).");
+            Ok(())
+        }
+        Commands::Auth { action } => run_auth(action),
+        Commands::ExportRecording {
+            name,
+            domain,
+            language,
+            output,
+            force,
+            recordings_dir,
+        } => {
+            let store = recordings_dir
+                .map(recording::RecordingStore::with_base)
+                .unwrap_or_default();
+            match output {
+                Some(output) => {
+                    let rec = playwright::export_file(
+                        &store,
+                        &name,
+                        domain.as_deref(),
+                        &output,
+                        language,
+                        force,
+                    )?;
+                    println!(
+                        "Exported '{}' ({} action(s)) to {} as a {language} Playwright test.",
+                        rec.name,
+                        rec.actions.len(),
+                        output.display()
+                    );
+                }
+                None => {
+                    let rec = store.load(&name, domain.as_deref())?;
+                    print!("{}", playwright::export(&rec, language));
+                }
+            }
+            Ok(())
         }
     }
 }
 
+fn run_auth(action: AuthAction) -> Result<()> {
+    let store = |auth_dir: Option<PathBuf>| -> Result<auth::AuthStore> {
+        Ok(auth_dir
+            .map(auth::AuthStore::with_base)
+            .unwrap_or_default()
+            .with_key(vault::AuthKey::from_env()?))
+    };
+    match action {
+        AuthAction::Import {
+            file,
+            profile,
+            format,
+            url,
+            auth_dir,
+        } => {
+            let saved = interchange::import_file(
+                &store(auth_dir)?,
+                &file,
+                &profile,
+                format,
+                url.as_deref(),
+            )?;
+            println!(
+                "Imported {} cookie(s) and storage for {} origin(s) as '{}' ({}).",
+                saved.cookies.len(),
+                saved.origins.len(),
+                saved.profile,
+                saved.domain
+            );
+        }
+        AuthAction::SetTotp {
+            profile,
+            domain,
+            remove,
+            auth_dir,
+        } => {
+            let totp = if remove {
+                None
+            } else {
+                let mut input = String::new();
+                std::io::stdin().read_to_string(&mut input)?;
+                Some(totp::TotpSecret::parse(&input)?)
+            };
+            let saved = store(auth_dir)?.set_totp(&profile, domain.as_deref(), totp)?;
+            match saved.totp {
+                Some(totp) => println!(
+                    "Stored a {}-digit TOTP seed for '{}' ({}).",
+                    totp.digits, saved.profile, saved.domain
+                ),
+                None => println!(
+                    "Removed the TOTP seed of '{}' ({}).",
+                    saved.profile, saved.domain
+                ),
+            }
+        }
+        AuthAction::Export {
+            profile,
+            domain,
+            format,
+            output,
+            auth_dir,
+        } => {
+            let saved = interchange::export_file(
+                &store(auth_dir)?,
+                &profile,
+                domain.as_deref(),
+                &output,
+                format,
+                true,
+            )?;
+            println!(
+                "Exported '{}' ({} cookie(s)) to {} as {format}.",
+                saved.profile,
+                saved.cookies.len(),
+                output.display()
+            );
+        }
+    }
+    Ok(())
+}
+
 fn is_url(input: &str) -> bool {
     input.starts_with("http://") || input.starts_with("https://")
 }
 
-async fn run_browser_snapshot(url: &str, format: &str, port: u16, launch: bool) -> Result<()> {
+async fn run_browser_snapshot(
+    url: &str,
+    format: &str,
+    port: u16,
+    launch: bool,
+    options: &browser::BrowserOptions,
+    rules: &intercept::InterceptRules,
+) -> Result<()> {
     let browser = if launch {
-        browser::launch().await?
+        browser::launch(options).await?
     } else {
         browser::connect(port).await?
     };
 
-    let (html, final_url) = browser::fetch_page(&browser, url).await?;
+    let (html, final_url) = browser::fetch_page(&browser, url, rules).await?;
     debug!(html_len = html.len(), final_url = %final_url, "fetched page");
     let snapshot = pipeline::process(&html, &final_url);
     info!(nodes = snapshot.nodes.len(), "snapshot complete");
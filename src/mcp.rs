@@ -1,13 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use anyhow::Context as _;
 use base64::Engine as _;
+use chromiumoxide::cdp::browser_protocol::browser::BrowserContextId;
+use chromiumoxide::cdp::browser_protocol::page::ScriptIdentifier;
+use chromiumoxide::cdp::browser_protocol::target::CreateTargetParams;
 use chromiumoxide::Browser;
 use rmcp::handler::server::router::tool::ToolRouter;
+use rmcp::handler::server::tool::ToolCallContext;
 use rmcp::handler::server::wrapper::Parameters;
-use rmcp::model::{CallToolResult, Content, ServerCapabilities, ServerInfo};
-use rmcp::{tool, tool_handler, tool_router, ServerHandler, ServiceExt};
+use rmcp::model::{
+    CallToolRequestParams, CallToolResult, Content, ListToolsResult, PaginatedRequestParams,
+    ServerCapabilities, ServerInfo, Tool,
+};
+use rmcp::service::{ElicitationError, ElicitationMode, RequestContext};
+use rmcp::{tool, tool_router, Peer, RoleServer, ServerHandler, ServiceExt};
 use schemars::JsonSchema;
 use serde::Deserialize;
 use tokio::sync::RwLock;
@@ -15,7 +23,11 @@ use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
 use crate::dom::RefIndex;
-use crate::{auth, browser, diff, extract, hints, mutation, pipeline, recording, serialize};
+use crate::{
+    access, auth, blocking, browser, confirm, diff, emulation, extract, har, heal, hints,
+    intercept, interchange, mutation, network, pipeline, playwright, policy, pool, proxy,
+    recording, routes, secrets, serialize, storage, totp, vault, verify,
+};
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct NavigateParams {
@@ -36,8 +48,13 @@ pub struct ClickParams {
 pub struct TypeTextParams {
     /// The ref ID of the input element (the number N from @eN)
     pub r#ref: u32,
-    /// The text to type into the element
+    /// The text to type into the element, or "{{totp:PROFILE}}" to type the
+    /// current 2FA code of a saved auth profile
     pub text: String,
+    /// While recording, store the text as this variable (e.g. "query") so
+    /// replays can type a different value
+    #[serde(default)]
+    pub variable: Option<String>,
     /// If true, return a compact diff instead of a full snapshot
     #[serde(default)]
     pub return_diff: Option<bool>,
@@ -49,6 +66,10 @@ pub struct SelectOptionParams {
     pub r#ref: u32,
     /// The value or visible text of the option to select
     pub value: String,
+    /// While recording, store the value as this variable so replays can
+    /// select a different option
+    #[serde(default)]
+    pub variable: Option<String>,
     /// If true, return a compact diff instead of a full snapshot
     #[serde(default)]
     pub return_diff: Option<bool>,
@@ -113,6 +134,15 @@ pub struct ScrollToRefParams {
     pub r#ref: u32,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PressKeyParams {
+    /// Key name as in KeyboardEvent.key, e.g. "Enter", "Escape", "Tab", "ArrowDown", "a"
+    pub key: String,
+    /// Optional ref ID of the element to focus first (the number N from @eN)
+    #[serde(default)]
+    pub r#ref: Option<u32>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ExtractParams {
     /// A JSON Schema object describing the desired output shape. Supports objects with
@@ -132,6 +162,9 @@ pub struct StartRecordingParams {
     /// Optional description of what this recording does
     #[serde(default)]
     pub description: Option<String>,
+    /// Also record the time between actions, so replays keep the same pace
+    #[serde(default)]
+    pub timing: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -141,6 +174,46 @@ pub struct ReplayRecordingParams {
     /// Optional domain to narrow the search (e.g., "github-com")
     #[serde(default)]
     pub domain: Option<String>,
+    /// Values for {{secret:NAME}} placeholders, keyed by NAME. Missing ones
+    /// are read from CORTEX_SECRET_<NAME> environment variables.
+    #[serde(default)]
+    pub secrets: Option<std::collections::BTreeMap<String, String>>,
+    /// Values for the recording's {{NAME}} variables, keyed by NAME
+    /// (list_recordings shows which a recording needs)
+    #[serde(default)]
+    pub variables: Option<std::collections::BTreeMap<String, String>>,
+    /// When a recorded element is not found, re-find it by role, name and
+    /// landmark and save the fixed recording as a new version (default: true)
+    #[serde(default)]
+    pub heal: Option<bool>,
+    /// Minimum match confidence (0-1) for healing an element (default: 0.7)
+    #[serde(default)]
+    pub heal_threshold: Option<f64>,
+    /// Multiplier for recorded delays, each capped at 5 s: 0 skips them,
+    /// 0.5 halves them (default: 1)
+    #[serde(default)]
+    pub delay_scale: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AddAssertionParams {
+    /// What to check: "text" (visible page text contains `text`), "url"
+    /// (current URL matches `text`, `*` wildcards allowed; defaults to the
+    /// current URL), "ref" (element `ref` exists, or has `value` / `checked`)
+    /// or "snapshot" (compact snapshot contains `text`)
+    pub kind: String,
+    /// Expected text or URL pattern
+    #[serde(default)]
+    pub text: Option<String>,
+    /// The ref ID of the element for "ref" assertions
+    #[serde(default)]
+    pub r#ref: Option<u32>,
+    /// Expected value of the element for "ref" assertions
+    #[serde(default)]
+    pub value: Option<String>,
+    /// Expected checked state of the element for "ref" assertions
+    #[serde(default)]
+    pub checked: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -159,6 +232,25 @@ pub struct DeleteRecordingParams {
     pub domain: Option<String>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExportRecordingParams {
+    /// The name of the recording to export
+    pub name: String,
+    /// Optional domain to narrow the search
+    #[serde(default)]
+    pub domain: Option<String>,
+    /// "typescript" or "python" (default: "typescript")
+    #[serde(default)]
+    pub language: Option<String>,
+    /// File to write the test to, relative to the exports directory
+    /// (~/.cortex-browser/recordings/exports). Without it the test is returned.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Replace the file if it already exists (default: false)
+    #[serde(default)]
+    pub overwrite: Option<bool>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ScreenshotParams {
     /// If true, capture the entire scrollable page instead of just the viewport (default: false)
@@ -173,6 +265,12 @@ pub struct ScreenshotParams {
 pub struct SaveAuthParams {
     /// A short name for this auth profile (e.g., "github-login")
     pub profile: String,
+    /// Also save the page origin's localStorage and sessionStorage (default: true)
+    #[serde(default)]
+    pub storage: Option<bool>,
+    /// Also save the page origin's IndexedDB databases (default: false)
+    #[serde(default)]
+    pub indexed_db: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -184,6 +282,41 @@ pub struct RestoreAuthParams {
     pub domain: Option<String>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct VerifyAuthParams {
+    /// The name of the auth profile to check
+    pub profile: String,
+    /// Optional domain to narrow the search (e.g., "github-com")
+    #[serde(default)]
+    pub domain: Option<String>,
+    /// Page to load (default: the configured probe URL, else the URL the profile was saved from)
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Text and/or role that only appears when logged in (e.g., {"text": "Sign out"})
+    #[serde(default)]
+    pub logged_in: Option<verify::SnapshotMatch>,
+    /// Text and/or role that only appears when logged out (e.g., {"role": "button", "text": "Log in"})
+    #[serde(default)]
+    pub logged_out: Option<verify::SnapshotMatch>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BindAuthParams {
+    /// Host to bind (e.g., "github.com", which also covers its subdomains, or "*.staging.example.com")
+    pub host: String,
+    /// The auth profile to restore automatically when navigating there
+    pub profile: String,
+    /// Optional domain to narrow the profile search (e.g., "github-com")
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UnbindAuthParams {
+    /// Host pattern to unbind, as given to bind_auth
+    pub host: String,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ListAuthParams {
     /// Optional domain to filter by (e.g., "github-com")
@@ -191,6 +324,47 @@ pub struct ListAuthParams {
     pub domain: Option<String>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TotpCodeParams {
+    /// The auth profile holding the TOTP seed
+    pub profile: String,
+    /// Optional domain to narrow the search (e.g., "github-com")
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImportAuthParams {
+    /// Path of a Netscape cookies.txt or Playwright storageState.json file,
+    /// relative to ~/.cortex-browser/auth-exports
+    pub path: String,
+    /// Name of the auth profile to create or replace
+    pub profile: String,
+    /// "cookies-txt" or "storage-state" (default: detected from the contents)
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Site the profile belongs to (default: the first cookie's domain)
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExportAuthParams {
+    /// The name of the auth profile to export
+    pub profile: String,
+    /// Optional domain to narrow the search (e.g., "github-com")
+    #[serde(default)]
+    pub domain: Option<String>,
+    /// Path of the file to write, relative to ~/.cortex-browser/auth-exports
+    pub path: String,
+    /// "cookies-txt" or "storage-state" (default: "storage-state")
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Replace the file if it already exists (default: false)
+    #[serde(default)]
+    pub overwrite: Option<bool>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct DeleteAuthParams {
     /// The name of the auth profile to delete
@@ -200,6 +374,183 @@ pub struct DeleteAuthParams {
     pub domain: Option<String>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NetworkLogParams {
+    /// Only include requests whose URL matches this pattern. Use * as a wildcard
+    /// (e.g., "*/api/*"); without * it is a substring match.
+    #[serde(default)]
+    pub url_pattern: Option<String>,
+    /// Only include requests of this resource type (e.g., "xhr", "fetch", "document", "script")
+    #[serde(default)]
+    pub resource_type: Option<String>,
+    /// Maximum number of entries to return, most recent last (default: 50)
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// If true, clear the tab's network log after reading it
+    #[serde(default)]
+    pub clear: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ResponseBodyParams {
+    /// The request ID from network_log (the value after #)
+    pub request_id: String,
+    /// Maximum number of characters of the body to return (default: 20000)
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetBlockingParams {
+    /// URL globs to block (e.g., ["*://*/ads/*", "*.mp4"]). Replaces the current patterns.
+    #[serde(default)]
+    pub patterns: Option<Vec<String>>,
+    /// Hosts to block, including their subdomains (e.g., ["doubleclick.net"]). Replaces the current hosts.
+    #[serde(default)]
+    pub hosts: Option<Vec<String>>,
+    /// Resource types to block (e.g., ["image", "font", "media", "stylesheet"]). Replaces the current types.
+    #[serde(default)]
+    pub resource_types: Option<Vec<String>>,
+    /// Whether to apply the bundled ad/tracker host list
+    #[serde(default)]
+    pub trackers: Option<bool>,
+    /// If true, remove all blocking rules before applying the other fields
+    #[serde(default)]
+    pub clear: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RouteParams {
+    /// URL glob the route applies to (e.g., "https://shop.test/*", "*/api/search*")
+    pub url_pattern: String,
+    /// Path of a fixture file to serve as the response body
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Inline response body, used when file is not set
+    #[serde(default)]
+    pub body: Option<String>,
+    /// HTTP status code (default: 200)
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// Extra response headers (e.g., {"Cache-Control": "no-store"})
+    #[serde(default)]
+    pub headers: std::collections::BTreeMap<String, String>,
+    /// Content-Type header; guessed from the file extension when omitted
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// If true, remove the route with this url_pattern instead of adding it
+    #[serde(default)]
+    pub remove: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EmulateParams {
+    /// Device preset: "iPhone 15", "iPhone SE", "Pixel 7", "Galaxy S23", "iPad Air", "iPad Pro", "Desktop" (aliases: iphone, pixel, galaxy, ipad)
+    #[serde(default)]
+    pub device: Option<String>,
+    /// Viewport width in CSS pixels (requires height)
+    #[serde(default)]
+    pub width: Option<u32>,
+    /// Viewport height in CSS pixels (requires width)
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// Device pixel ratio (e.g., 2 or 3 for retina phones)
+    #[serde(default)]
+    pub device_scale_factor: Option<f64>,
+    /// Emulate a mobile device (meta viewport, overlay scrollbars)
+    #[serde(default)]
+    pub mobile: Option<bool>,
+    /// Emulate a touch screen
+    #[serde(default)]
+    pub touch: Option<bool>,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Locale, also sent as Accept-Language (e.g., "de-DE")
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// IANA timezone (e.g., "Europe/Berlin")
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Geolocation latitude (requires longitude)
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    /// Geolocation longitude (requires latitude)
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    /// Geolocation accuracy in meters (default: 100)
+    #[serde(default)]
+    pub accuracy: Option<f64>,
+    /// prefers-color-scheme: "light", "dark" or "no-preference"
+    #[serde(default)]
+    pub color_scheme: Option<String>,
+    /// prefers-reduced-motion: reduce
+    #[serde(default)]
+    pub reduced_motion: Option<bool>,
+    /// If true, clear all emulation before applying the other fields
+    #[serde(default)]
+    pub reset: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetHeadersParams {
+    /// Headers to send with every request, e.g. {"X-Env": "staging"}. An empty value removes that header.
+    #[serde(default)]
+    pub headers: std::collections::BTreeMap<String, String>,
+    /// If true, remove all extra headers before applying `headers`
+    #[serde(default)]
+    pub clear: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetProxyAuthParams {
+    /// Proxy username
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Proxy password
+    #[serde(default)]
+    pub password: Option<String>,
+    /// If true, stop answering proxy auth challenges
+    #[serde(default)]
+    pub clear: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PendingActionParams {
+    /// ID of the pending action, from the PENDING message
+    pub id: u32,
+}
+
+/// The user's answer to an approval request sent through MCP elicitation.
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Approval {
+    /// Allow the action to run
+    approve: bool,
+}
+
+rmcp::elicit_safe!(Approval);
+
+/// How long to wait for the user to answer an approval request.
+const APPROVAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StartHarParams {
+    /// File to write the HAR to, relative to ~/.cortex-browser/har
+    /// (default: <timestamp>.har)
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Replace the file if it already exists (default: false)
+    #[serde(default)]
+    pub overwrite: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReplayHarParams {
+    /// HAR file to serve responses from, relative to ~/.cortex-browser/har.
+    /// Omit to stop replaying.
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
 struct TabState {
     page: chromiumoxide::Page,
     ref_index: RefIndex,
@@ -209,26 +560,92 @@ struct TabState {
     task_context: Option<hints::TaskContext>,
     /// Previous snapshot tree for page diff computation.
     previous_snapshot: Option<crate::dom::PageSnapshot>,
+    network: network::SharedNetworkLog,
+    intercept: Arc<intercept::InterceptStats>,
+    /// Web storage restore scripts registered on the page.
+    auth_scripts: Vec<ScriptIdentifier>,
+}
+
+/// A freshly created page with its per-tab listeners attached.
+struct OpenedPage {
+    page: chromiumoxide::Page,
+    network: network::SharedNetworkLog,
+    intercept: Arc<intercept::InterceptStats>,
+    auth_scripts: Vec<ScriptIdentifier>,
+}
+
+/// Startup settings shared by every server instance (one per MCP session).
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    /// Chrome launch settings, used when the server launches its own browser.
+    pub browser: browser::BrowserOptions,
+    /// Device and locale emulation applied to every page.
+    pub emulation: emulation::Emulation,
+    /// Headers sent with every request.
+    pub extra_headers: proxy::ExtraHeaders,
+    pub blocklist: blocking::BlockList,
+    pub routes: routes::RouteTable,
+    /// Hosts and schemes the session may navigate to.
+    pub policy: policy::DomainPolicy,
+    /// Actions that need a human's approval before they run.
+    pub confirm: confirm::ConfirmRules,
+    /// Encrypts saved auth profiles (`CORTEX_AUTH_KEY_FILE` / `CORTEX_AUTH_PASSPHRASE`).
+    pub auth_key: Option<vault::AuthKey>,
+    /// Login checks for `verify_auth`, keyed by profile name or domain.
+    pub probes: BTreeMap<String, verify::AuthProbe>,
+    /// HAR file to serve responses from instead of the network.
+    pub har_replay: Option<Arc<har::HarReplay>>,
+    /// Capture started at launch (`--har`). When set, it is shared by every
+    /// session and written out when the server shuts down.
+    pub har_capture: Option<har::SharedHarRecorder>,
+    /// Open the session's tabs in their own incognito browser context, so
+    /// cookies and storage are not shared with other sessions on the same Chrome.
+    pub isolate: bool,
+    /// Shared browsers to lease a context from instead of launching one per
+    /// session (`mcp-http --launch`).
+    pub pool: Option<Arc<pool::BrowserPool>>,
+    /// After reconnecting to a crashed browser, restore the auth profile last
+    /// loaded with `restore_auth`.
+    pub recover_auth: bool,
 }
 
 struct BrowserState {
-    browser: Option<Browser>,
+    browser: Option<Arc<browser::BrowserHandle>>,
+    /// Incognito context the session's pages live in (`ServerOptions::isolate`).
+    context: Option<BrowserContextId>,
+    /// The session's slot in the browser pool, released when the session ends.
+    lease: Option<pool::Lease>,
     tabs: HashMap<u32, TabState>,
     active_tab: u32,
     next_tab_id: u32,
     active_recording: Option<recording::Recording>,
+    /// Time of the last recorded action, when recording with `timing`.
+    last_recorded_at: Option<std::time::Instant>,
     replaying: bool,
+    /// Profile name and domain of the last `restore_auth`, for crash recovery.
+    last_auth: Option<(String, Option<String>)>,
+    /// Web storage scripts of restored profiles, keyed by "domain/profile" and
+    /// registered on every tab.
+    auth_scripts: BTreeMap<String, String>,
+    /// Bindings restored automatically by navigation, keyed by host pattern.
+    auto_auth: BTreeMap<String, auth::AuthBinding>,
 }
 
 impl BrowserState {
     fn new() -> Self {
         Self {
             browser: None,
+            context: None,
+            lease: None,
             tabs: HashMap::new(),
             active_tab: 0,
             next_tab_id: 1,
             active_recording: None,
+            last_recorded_at: None,
             replaying: false,
+            last_auth: None,
+            auth_scripts: BTreeMap::new(),
+            auto_auth: BTreeMap::new(),
         }
     }
 
@@ -245,23 +662,75 @@ impl BrowserState {
             .with_context(|| "No active tab. Use navigate or open_tab first.")
     }
 
+    /// Header line naming the profile restored automatically for `url`'s host.
+    fn auth_label(&self, url: &str) -> Option<String> {
+        let host = network::url_host(url).to_ascii_lowercase();
+        self.auto_auth
+            .iter()
+            .filter(|(pattern, _)| policy::host_pattern_matches(pattern, &host))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(pattern, binding)| format!("{} (auto, bound to {pattern})", binding.profile))
+    }
+
     /// Push an action to the active recording (if any and not replaying).
     fn record(&mut self, action: recording::RecordedAction) {
         if self.replaying {
             return;
         }
         if let Some(ref mut rec) = self.active_recording {
-            rec.actions.push(action);
+            if let Some(last) = self.last_recorded_at.replace(std::time::Instant::now()) {
+                let ms = last.elapsed().as_millis() as u64;
+                if ms >= recording::MIN_DELAY_MS {
+                    rec.actions.push(recording::RecordedAction::Delay {
+                        ms: ms.min(recording::MAX_DELAY_MS),
+                    });
+                }
+            }
+            let active_tab = self
+                .tabs
+                .contains_key(&self.active_tab)
+                .then_some(self.active_tab);
+            rec.push(action, active_tab);
         }
     }
 }
 
+impl Drop for BrowserState {
+    /// Dispose the session's browser context, closing its tabs, once the last
+    /// handle to the session goes away.
+    fn drop(&mut self) {
+        let (Some(browser), Some(context)) = (self.browser.take(), self.context.take()) else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        runtime.spawn(async move {
+            match browser.dispose_browser_context(context).await {
+                Ok(()) => info!("browser context disposed"),
+                Err(e) => warn!(error = %e, "failed to dispose browser context"),
+            }
+        });
+    }
+}
+
 #[derive(Clone)]
 pub struct CortexBrowserServer {
     tool_router: ToolRouter<Self>,
     state: Arc<RwLock<BrowserState>>,
     store: Arc<recording::RecordingStore>,
     auth_store: Arc<auth::AuthStore>,
+    probes: Arc<BTreeMap<String, verify::AuthProbe>>,
+    intercept_rules: intercept::SharedInterceptRules,
+    har: har::SharedHarRecorder,
+    browser_options: browser::BrowserOptions,
+    isolate: bool,
+    pool: Option<Arc<pool::BrowserPool>>,
+    recover_auth: bool,
+    emulation: Arc<std::sync::RwLock<emulation::Emulation>>,
+    extra_headers: Arc<std::sync::RwLock<proxy::ExtraHeaders>>,
+    confirm: confirm::ConfirmRules,
+    pending: Arc<std::sync::Mutex<confirm::PendingQueue>>,
     launch_browser: bool,
     port: u16,
 }
@@ -269,11 +738,33 @@ pub struct CortexBrowserServer {
 #[tool_router]
 impl CortexBrowserServer {
     pub fn new(launch_browser: bool, port: u16) -> Self {
+        Self::with_options(launch_browser, port, ServerOptions::default())
+    }
+
+    pub fn with_options(launch_browser: bool, port: u16, options: ServerOptions) -> Self {
+        let rules = intercept::InterceptRules {
+            blocklist: options.blocklist,
+            routes: options.routes,
+            har: options.har_replay,
+            proxy_auth: options.browser.proxy.credentials(),
+            policy: options.policy,
+        };
         Self {
             tool_router: Self::tool_router(),
             state: Arc::new(RwLock::new(BrowserState::new())),
             store: Arc::new(recording::RecordingStore::new()),
-            auth_store: Arc::new(auth::AuthStore::new()),
+            auth_store: Arc::new(auth::AuthStore::new().with_key(options.auth_key)),
+            probes: Arc::new(options.probes),
+            intercept_rules: Arc::new(std::sync::RwLock::new(rules)),
+            har: options.har_capture.unwrap_or_default(),
+            browser_options: options.browser,
+            isolate: options.isolate,
+            pool: options.pool,
+            recover_auth: options.recover_auth,
+            emulation: Arc::new(std::sync::RwLock::new(options.emulation)),
+            extra_headers: Arc::new(std::sync::RwLock::new(options.extra_headers)),
+            confirm: options.confirm,
+            pending: Arc::default(),
             launch_browser,
             port,
         }
@@ -302,41 +793,50 @@ impl CortexBrowserServer {
     #[tool(
         description = "Click an element by ref ID (the N from @eN in the snapshot). Returns updated page snapshot, or a compact diff if return_diff is true."
     )]
-    async fn click(&self, Parameters(params): Parameters<ClickParams>) -> String {
-        match self
-            .do_click(params.r#ref, params.return_diff.unwrap_or(false))
-            .await
-        {
+    async fn click(
+        &self,
+        Parameters(params): Parameters<ClickParams>,
+        peer: Peer<RoleServer>,
+    ) -> String {
+        let action = confirm::PendingKind::Click {
+            ref_id: params.r#ref,
+            return_diff: params.return_diff.unwrap_or(false),
+        };
+        match self.run_with_approval(&peer, action).await {
             Ok(text) => text,
             Err(e) => format!("ERROR: Click @e{} failed: {e}", params.r#ref),
         }
     }
 
     #[tool(
-        description = "Type text into an input field by ref ID (the N from @eN). Returns updated page snapshot, or a compact diff if return_diff is true."
+        description = "Type text into an input field by ref ID (the N from @eN). Pass \"{{totp:PROFILE}}\" as text to type the current TOTP code of a saved auth profile. While recording, set `variable` to make the text a replay parameter. Returns updated page snapshot, or a compact diff if return_diff is true."
     )]
-    async fn type_text(&self, Parameters(params): Parameters<TypeTextParams>) -> String {
-        match self
-            .do_type_text(
-                params.r#ref,
-                &params.text,
-                params.return_diff.unwrap_or(false),
-            )
-            .await
-        {
+    async fn type_text(
+        &self,
+        Parameters(params): Parameters<TypeTextParams>,
+        peer: Peer<RoleServer>,
+    ) -> String {
+        let action = confirm::PendingKind::TypeText {
+            ref_id: params.r#ref,
+            text: proxy::Secret::new(params.text),
+            variable: params.variable,
+            return_diff: params.return_diff.unwrap_or(false),
+        };
+        match self.run_with_approval(&peer, action).await {
             Ok(text) => text,
             Err(e) => format!("ERROR: Type into @e{} failed: {e}", params.r#ref),
         }
     }
 
     #[tool(
-        description = "Select an option in a dropdown by ref ID (the N from @eN). Returns updated page snapshot, or a compact diff if return_diff is true."
+        description = "Select an option in a dropdown by ref ID (the N from @eN). While recording, set `variable` to make the value a replay parameter. Returns updated page snapshot, or a compact diff if return_diff is true."
     )]
     async fn select_option(&self, Parameters(params): Parameters<SelectOptionParams>) -> String {
         match self
             .do_select(
                 params.r#ref,
                 &params.value,
+                params.variable.as_deref(),
                 params.return_diff.unwrap_or(false),
             )
             .await
@@ -442,7 +942,13 @@ impl CortexBrowserServer {
         description = "Scroll down by roughly one viewport height. Returns an updated snapshot with viewport position."
     )]
     async fn scroll_down(&self) -> String {
-        match self.do_scroll(mutation::SCROLL_DOWN_JS).await {
+        match self
+            .do_scroll(
+                mutation::SCROLL_DOWN_JS,
+                recording::RecordedAction::ScrollDown,
+            )
+            .await
+        {
             Ok(text) => text,
             Err(e) => format!("ERROR: Scroll down failed: {e}"),
         }
@@ -452,7 +958,10 @@ impl CortexBrowserServer {
         description = "Scroll up by roughly one viewport height. Returns an updated snapshot with viewport position."
     )]
     async fn scroll_up(&self) -> String {
-        match self.do_scroll(mutation::SCROLL_UP_JS).await {
+        match self
+            .do_scroll(mutation::SCROLL_UP_JS, recording::RecordedAction::ScrollUp)
+            .await
+        {
             Ok(text) => text,
             Err(e) => format!("ERROR: Scroll up failed: {e}"),
         }
@@ -468,6 +977,44 @@ impl CortexBrowserServer {
         }
     }
 
+    #[tool(
+        description = "Press a key such as Enter, Escape, Tab or ArrowDown, optionally focusing an element by ref ID first (the N from @eN). Enter in a form field counts as a form submission for approval rules. Returns an updated snapshot."
+    )]
+    async fn press_key(
+        &self,
+        Parameters(params): Parameters<PressKeyParams>,
+        peer: Peer<RoleServer>,
+    ) -> String {
+        let action = confirm::PendingKind::PressKey {
+            key: params.key.clone(),
+            ref_id: params.r#ref,
+        };
+        match self.run_with_approval(&peer, action).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Press {} failed: {e}", params.key),
+        }
+    }
+
+    #[tool(
+        description = "Go back one page in the active tab's history. Returns an updated snapshot."
+    )]
+    async fn go_back(&self) -> String {
+        match self.do_history(true).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Go back failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Go forward one page in the active tab's history. Returns an updated snapshot."
+    )]
+    async fn go_forward(&self) -> String {
+        match self.do_history(false).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Go forward failed: {e}"),
+        }
+    }
+
     #[tool(
         description = "Compare the current page to its previous snapshot and return a compact diff showing what changed. Useful after actions to see only what's different without a full snapshot."
     )]
@@ -489,7 +1036,7 @@ impl CortexBrowserServer {
     }
 
     #[tool(
-        description = "Start recording browser actions for the current domain. Actions (navigate, click, type, select) will be captured until stop_recording is called. Only one recording can be active at a time."
+        description = "Start recording browser actions for the current domain. Every page-changing action (navigate, click, type, select, scroll, wait_for_changes, press_key, go_back/go_forward, tab changes) is captured until stop_recording is called. Set timing to also capture the pauses between actions. Only one recording can be active at a time."
     )]
     async fn start_recording(
         &self,
@@ -512,18 +1059,29 @@ impl CortexBrowserServer {
     }
 
     #[tool(
-        description = "Replay a saved recording deterministically. Each action is re-executed using stored element locators - no LLM needed. Aborts on first element not found."
+        description = "Replay a saved recording deterministically. Each action is re-executed using stored element locators - no LLM needed. Pass values for the recording's {{NAME}} variables in `variables`. Assertions are reported as PASS/FAIL per step. An element the stored locator no longer finds is re-matched by role, name and landmark (heal), and the fixed recording is saved as a new version. Aborts on first element that cannot be found or healed, or failed assertion."
     )]
     async fn replay_recording(
         &self,
         Parameters(params): Parameters<ReplayRecordingParams>,
+        peer: Peer<RoleServer>,
     ) -> String {
-        match self.do_replay_recording(params).await {
+        match self.do_replay_recording(params, &peer).await {
             Ok(text) => text,
             Err(e) => format!("ERROR: Replay failed: {e}"),
         }
     }
 
+    #[tool(
+        description = "Add a checkpoint to the active recording: page text, URL, element state or snapshot content that must hold at this point. It is checked against the current page first, and replay_recording reports it as PASS or FAIL and stops on failure."
+    )]
+    async fn add_assertion(&self, Parameters(params): Parameters<AddAssertionParams>) -> String {
+        match self.do_add_assertion(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Add assertion failed: {e}"),
+        }
+    }
+
     #[tool(description = "List saved recordings, optionally filtered by domain.")]
     async fn list_recordings(
         &self,
@@ -546,6 +1104,19 @@ impl CortexBrowserServer {
         }
     }
 
+    #[tool(
+        description = "Export a saved recording as a Playwright test in TypeScript or Python, for running it as a regular regression test. Elements are located by role and name, label, #id or [name=...]; assertions become expect() calls; variables and secrets are read from environment variables. Returns the test, or writes it to path inside the exports directory (set overwrite to replace an existing file)."
+    )]
+    async fn export_recording(
+        &self,
+        Parameters(params): Parameters<ExportRecordingParams>,
+    ) -> String {
+        match self.do_export_recording(params) {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Export recording failed: {e}"),
+        }
+    }
+
     #[tool(
         description = "Take a PNG screenshot of the current page. Returns a base64-encoded image. Use full_page to capture the entire scrollable page. Use annotate to overlay interactive elements with red borders and @eN labels for visual debugging."
     )]
@@ -572,7 +1143,7 @@ impl CortexBrowserServer {
     }
 
     #[tool(
-        description = "Save the current page's cookies to disk as a named auth profile. Use restore_auth to reload them later, even across browser restarts."
+        description = "Save the current page's cookies, plus its origin's localStorage and sessionStorage (and optionally IndexedDB), to disk as a named auth profile. Use restore_auth to reload them later, even across browser restarts."
     )]
     async fn save_auth(&self, Parameters(params): Parameters<SaveAuthParams>) -> String {
         match self.do_save_auth(params).await {
@@ -582,7 +1153,7 @@ impl CortexBrowserServer {
     }
 
     #[tool(
-        description = "Restore a saved auth profile by injecting its cookies into the browser. Navigate to the target domain first, then call this to restore login state."
+        description = "Restore a saved auth profile by injecting its cookies into the browser. Saved web storage is written before the app's scripts run on the next load of its origin. Navigate to the target domain first, then call this to restore login state."
     )]
     async fn restore_auth(&self, Parameters(params): Parameters<RestoreAuthParams>) -> String {
         match self.do_restore_auth(params).await {
@@ -591,7 +1162,19 @@ impl CortexBrowserServer {
         }
     }
 
-    #[tool(description = "List saved auth profiles, optionally filtered by domain.")]
+    #[tool(
+        description = "Check whether a saved auth profile still logs in. Restores it into a throwaway browser context, loads a probe URL and looks for logged_in / logged_out text or roles in the snapshot. Also reports cookie expiry. Does not touch your tabs."
+    )]
+    async fn verify_auth(&self, Parameters(params): Parameters<VerifyAuthParams>) -> String {
+        match self.do_verify_auth(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Verify auth failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "List saved auth profiles, optionally filtered by domain, with cookie expiry (expired counts, earliest and latest expiry)."
+    )]
     async fn list_auth(&self, Parameters(params): Parameters<ListAuthParams>) -> String {
         match self.do_list_auth(params).await {
             Ok(text) => text,
@@ -599,37 +1182,248 @@ impl CortexBrowserServer {
         }
     }
 
-    #[tool(description = "Delete a saved auth profile by name.")]
-    async fn delete_auth(&self, Parameters(params): Parameters<DeleteAuthParams>) -> String {
-        match self.do_delete_auth(params).await {
+    #[tool(
+        description = "Make an auth profile the default for a host: navigate and open_tab restore its cookies and web storage automatically before the first request there, once per session. Bindings persist across restarts."
+    )]
+    async fn bind_auth(&self, Parameters(params): Parameters<BindAuthParams>) -> String {
+        match self.do_bind_auth(params) {
             Ok(text) => text,
-            Err(e) => format!("ERROR: Delete auth failed: {e}"),
+            Err(e) => format!("ERROR: Bind auth failed: {e}"),
         }
     }
-}
 
-#[tool_handler]
-impl ServerHandler for CortexBrowserServer {
-    fn get_info(&self) -> ServerInfo {
-        ServerInfo {
-            instructions: Some(
-                "cortex-browser: compact browser perception layer for AI agents. \
-                 Use 'navigate' to open a URL and get a page snapshot. Interactive elements \
-                 are labeled @eN. Use 'click', 'type_text', 'select_option' with the ref \
-                 number N to interact. Use 'snapshot' to refresh the current view. \
-                 Use 'set_task_context' to focus snapshots on what matters for your current task. \
-                 Use 'wait_for_changes' after actions that trigger async page updates. \
-                 Use 'focused_snapshot' for one-time filtered views. \
-                 Use 'open_tab', 'list_tabs', 'switch_tab', 'close_tab' for multi-tab workflows. \
-                 Use 'scroll_down', 'scroll_up', 'scroll_to_ref' to navigate within long pages. \
-                 Elements marked [offscreen] are outside the current viewport. \
-                 Use 'page_diff' to see what changed since the last snapshot, or pass return_diff:true to click/type_text/select_option. \
-                 Use 'extract' with a JSON Schema to pull structured data (tables, lists, objects) from the page as JSON. \
+    #[tool(description = "Remove a host's default auth profile set with bind_auth.")]
+    async fn unbind_auth(&self, Parameters(params): Parameters<UnbindAuthParams>) -> String {
+        match self.do_unbind_auth(params) {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Unbind auth failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Get the current TOTP (RFC 6238) 2FA code of a saved auth profile whose seed was set with `cortex-browser auth set-totp`. Prefer typing \"{{totp:PROFILE}}\" with type_text, which never shows the code."
+    )]
+    async fn totp_code(&self, Parameters(params): Parameters<TotpCodeParams>) -> String {
+        match self.do_totp_code(params) {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: TOTP code failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Save a Netscape cookies.txt (curl, yt-dlp, browser extensions) or Playwright storageState.json file as an auth profile. Keeps httpOnly, secure, SameSite and expiry; storageState localStorage and IndexedDB are kept too."
+    )]
+    async fn import_auth(&self, Parameters(params): Parameters<ImportAuthParams>) -> String {
+        match self.do_import_auth(params) {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Import auth failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Write a saved auth profile to a file as Playwright storageState.json (default) or Netscape cookies.txt, for use with other tools. The file holds live credentials and is created readable by the owner only."
+    )]
+    async fn export_auth(&self, Parameters(params): Parameters<ExportAuthParams>) -> String {
+        match self.do_export_auth(params) {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Export auth failed: {e}"),
+        }
+    }
+
+    #[tool(description = "Delete a saved auth profile by name.")]
+    async fn delete_auth(&self, Parameters(params): Parameters<DeleteAuthParams>) -> String {
+        match self.do_delete_auth(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Delete auth failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "List network requests made by the active tab (method, status, resource type, URL, timing). Filter by url_pattern (* wildcard) and resource_type (e.g., \"xhr\", \"fetch\"). Use get_response_body with a request ID to read the payload."
+    )]
+    async fn network_log(&self, Parameters(params): Parameters<NetworkLogParams>) -> String {
+        match self.do_network_log(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Network log failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Return the response body of a network request by ID (from network_log). Useful for reading JSON API payloads directly instead of scraping the rendered page."
+    )]
+    async fn get_response_body(
+        &self,
+        Parameters(params): Parameters<ResponseBodyParams>,
+    ) -> String {
+        match self.do_get_response_body(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Get response body failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Configure request blocking for all tabs: URL globs, hosts, resource types (image, font, media, ...) and the bundled ad/tracker list. Blocked requests never hit the network. Call with no arguments to see the current rules and per-tab blocked counts."
+    )]
+    async fn set_blocking(&self, Parameters(params): Parameters<SetBlockingParams>) -> String {
+        match self.do_set_blocking(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Set blocking failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Mock requests matching a URL glob with a fixture file or inline body (status, headers, content type), fulfilled without touching the network. Applies to all tabs; later routes override earlier ones. Pass remove:true to delete a route."
+    )]
+    async fn route(&self, Parameters(params): Parameters<RouteParams>) -> String {
+        match self.do_route(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Route failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Emulate a device and locale on every tab: viewport, device scale factor, mobile/touch, user agent, locale/Accept-Language, timezone, geolocation, prefers-color-scheme and reduced motion. Use a device preset (iphone, pixel, ipad, ...) to save typing. Settings accumulate across calls; pass reset:true to start over."
+    )]
+    async fn emulate(&self, Parameters(params): Parameters<EmulateParams>) -> String {
+        match self.do_emulate(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Emulate failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Send extra HTTP headers (e.g. X-Env, Authorization: Bearer ...) with every request from every tab. Headers accumulate across calls; an empty value removes one, clear:true removes all. Values are never echoed back."
+    )]
+    async fn set_headers(&self, Parameters(params): Parameters<SetHeadersParams>) -> String {
+        match self.do_set_headers(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Set headers failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Set the username and password answered to proxy auth challenges, for browsers launched behind an authenticating proxy. The proxy server itself is fixed at launch (--proxy). Pass clear:true to stop answering challenges."
+    )]
+    async fn set_proxy_auth(&self, Parameters(params): Parameters<SetProxyAuthParams>) -> String {
+        match self.do_set_proxy_auth(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Set proxy auth failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Run a sensitive action that returned PENDING, after the user explicitly approved it. Never approve on your own judgement."
+    )]
+    async fn approve(&self, Parameters(params): Parameters<PendingActionParams>) -> String {
+        match self.do_approve(params.id).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Approve failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Drop a sensitive action that returned PENDING, when the user declined it."
+    )]
+    async fn reject(&self, Parameters(params): Parameters<PendingActionParams>) -> String {
+        match self.do_reject(params.id) {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Reject failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Start capturing every request the session loads (headers, bodies, timing) into a HAR 1.2 file. Covers all tabs until stop_har is called."
+    )]
+    async fn start_har(&self, Parameters(params): Parameters<StartHarParams>) -> String {
+        match self.do_start_har(params) {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Start HAR failed: {e}"),
+        }
+    }
+
+    #[tool(description = "Stop the active HAR capture and write it to disk.")]
+    async fn stop_har(&self) -> String {
+        match self.do_stop_har() {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Stop HAR failed: {e}"),
+        }
+    }
+
+    #[tool(
+        description = "Serve responses from a HAR file instead of the network, for offline reruns. Requests missing from the HAR fail; routes still take priority. Omit path to stop replaying."
+    )]
+    async fn replay_har(&self, Parameters(params): Parameters<ReplayHarParams>) -> String {
+        match self.do_replay_har(params).await {
+            Ok(text) => text,
+            Err(e) => format!("ERROR: Replay HAR failed: {e}"),
+        }
+    }
+}
+
+impl ServerHandler for CortexBrowserServer {
+    /// Route tool calls, first reconnecting if the browser went away. When it
+    /// did, the agent is told that tabs were reloaded and refs are stale.
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let notice = self.recover_browser().await;
+        let tcc = ToolCallContext::new(self, request, context);
+        let mut result = self.tool_router.call(tcc).await?;
+        if let Some(notice) = notice {
+            result.content.insert(0, Content::text(notice));
+        }
+        Ok(result)
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, rmcp::ErrorData> {
+        Ok(ListToolsResult {
+            tools: self.tool_router.list_all(),
+            meta: None,
+            next_cursor: None,
+        })
+    }
+
+    fn get_tool(&self, name: &str) -> Option<Tool> {
+        self.tool_router.get(name).cloned()
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            instructions: Some(
+                "cortex-browser: compact browser perception layer for AI agents. \
+                 Use 'navigate' to open a URL and get a page snapshot. Interactive elements \
+                 are labeled @eN. Use 'click', 'type_text', 'select_option' with the ref \
+                 number N to interact. Use 'snapshot' to refresh the current view. \
+                 Use 'set_task_context' to focus snapshots on what matters for your current task. \
+                 Use 'wait_for_changes' after actions that trigger async page updates. \
+                 Use 'focused_snapshot' for one-time filtered views. \
+                 Use 'open_tab', 'list_tabs', 'switch_tab', 'close_tab' for multi-tab workflows. \
+                 Use 'scroll_down', 'scroll_up', 'scroll_to_ref' to navigate within long pages. \
+                 Elements marked [offscreen] are outside the current viewport. \
+                 Use 'page_diff' to see what changed since the last snapshot, or pass return_diff:true to click/type_text/select_option. \
+                 Use 'extract' with a JSON Schema to pull structured data (tables, lists, objects) from the page as JSON. \
                  Use 'start_recording' / 'stop_recording' to capture action sequences, then 'replay_recording' to replay them deterministically without LLM decisions. \
+                 Use 'press_key' for Enter, Escape or arrow keys, and 'go_back' / 'go_forward' to move through the tab's history. \
+                 Use 'add_assertion' while recording to add checkpoints (page text, URL, element state) that replay verifies. \
+                 Pass `variable` to type_text or select_option while recording to turn a value into a {{NAME}} variable that replay_recording fills from `variables`. \
                  Use 'list_recordings' and 'delete_recording' to manage saved recordings. \
                  Use 'screenshot' to capture a PNG of the current page, with optional full_page and annotate flags for visual debugging. \
                  Use 'get_cookies' to inspect auth state, 'save_auth' / 'restore_auth' to persist and reload login sessions across browser restarts, \
-                 'list_auth' and 'delete_auth' to manage saved auth profiles."
+                 'list_auth' and 'delete_auth' to manage saved auth profiles, 'verify_auth' to check a profile still logs in, 'bind_auth' to restore a profile automatically whenever you navigate to its host, 'import_auth' / 'export_auth' to convert profiles from and to cookies.txt or Playwright storageState files. \
+                 At a 2FA prompt, type_text \"{{totp:PROFILE}}\" types the current one-time code of a profile with a TOTP seed; 'totp_code' returns it. \
+                 Use 'network_log' to list the tab's network requests and 'get_response_body' to read an API response directly. \
+                 Use 'set_blocking' to block ads, trackers and heavy resources like images and fonts. \
+                 Use 'route' to serve matching requests from fixture files or inline bodies instead of the network. \
+                 Use 'emulate' to test mobile layouts and localized sites (device presets, locale, timezone, geolocation, color scheme). \
+                 Use 'set_headers' to send extra HTTP headers with every request and 'set_proxy_auth' to change proxy credentials. \
+                 Sensitive clicks and typing may need a human's approval: a result starting with PENDING means ask the user, then call 'approve' or 'reject' with its id. \
+                 Use 'start_har' / 'stop_har' to capture the session's traffic as a HAR file, and 'replay_har' to serve responses from one offline."
                     .into(),
             ),
             capabilities: ServerCapabilities::builder().enable_tools().build(),
@@ -670,6 +1464,21 @@ fn parse_viewport_json(json: &str) -> Option<crate::dom::ViewportInfo> {
         })
 }
 
+/// Report a healed replay step and point its action at the new element.
+fn note_healed(
+    step_results: &mut Vec<String>,
+    action: &mut recording::RecordedAction,
+    target: heal::HealedTarget,
+) {
+    step_results.push(format!(
+        "  healed → @e{} \"{}\" ({:.0}% confidence)",
+        target.ref_id,
+        target.locator.text,
+        target.confidence * 100.0
+    ));
+    action.retarget(target.locator, target.ref_id);
+}
+
 /// Apply task context filtering to a snapshot if a context is set.
 fn apply_task_context(
     task_context: &Option<hints::TaskContext>,
@@ -751,54 +1560,313 @@ fn build_annotation_js(ref_index: &RefIndex, full_page: bool) -> String {
 const REMOVE_ANNOTATIONS_JS: &str =
     "(function() { var el = document.getElementById('__cortex_annotations'); if (el) el.remove(); })()";
 
+/// Visible text of the page, checked by `AssertText`.
+const PAGE_TEXT_JS: &str = "document.body ? document.body.innerText : ''";
+
 impl CortexBrowserServer {
-    async fn ensure_browser(&self) -> anyhow::Result<()> {
+    /// Connect to (or launch) the browser on first use. If the browser crashed
+    /// or the connection dropped, reconnect and reopen the tabs at their last
+    /// URLs; the returned notice tells the agent what was reset.
+    async fn ensure_browser(&self) -> anyhow::Result<Option<String>> {
         let mut state = self.state.write().await;
-        if state.browser.is_some() {
-            return Ok(());
+        if state.browser.as_ref().is_some_and(|b| b.is_connected()) {
+            return Ok(None);
+        }
+        // Tabs without a browser are left over from a failed recovery.
+        let recovering = state.browser.is_some() || !state.tabs.is_empty();
+
+        if recovering {
+            warn!("browser connection lost, reconnecting");
+            // The old context died with the connection.
+            state.browser = None;
+            state.context = None;
+            state.lease = None;
+        } else {
+            info!(
+                launch = self.launch_browser,
+                port = self.port,
+                "initializing browser connection"
+            );
+        }
+        self.connect_browser(&mut state).await?;
+        if !recovering {
+            return Ok(None);
+        }
+
+        let mut notes = Vec::new();
+        if self.recover_auth {
+            if let Some((profile, domain)) = state.last_auth.clone() {
+                match self
+                    .restore_cookies(&state, &profile, domain.as_deref())
+                    .await
+                {
+                    Ok(restored) => notes.push(format!(
+                        "auth profile '{profile}' was re-applied ({} cookie(s))",
+                        restored.cookies.len()
+                    )),
+                    Err(e) => {
+                        warn!(profile = %profile, error = %e, "failed to re-apply auth profile");
+                        notes.push(format!(
+                            "auth profile '{profile}' could not be re-applied: {e}"
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (pattern, binding) in state.auto_auth.clone() {
+            match self
+                .restore_cookies(&state, &binding.profile, Some(&binding.domain))
+                .await
+            {
+                Ok(_) => notes.push(format!(
+                    "auth profile '{}' bound to {pattern} was re-applied",
+                    binding.profile
+                )),
+                Err(e) => {
+                    warn!(profile = %binding.profile, error = %e, "failed to re-apply bound auth profile");
+                    notes.push(format!(
+                        "auth profile '{}' bound to {pattern} could not be re-applied: {e}",
+                        binding.profile
+                    ));
+                }
+            }
+        }
+
+        let mut tabs: Vec<(u32, TabState)> = state.tabs.drain().collect();
+        tabs.sort_by_key(|(id, _)| *id);
+        let mut reopened = 0;
+        for (id, old) in tabs {
+            match self.open_page(&state, &old.current_url).await {
+                Ok(opened) => {
+                    opened.page.wait_for_navigation().await.ok();
+                    state.tabs.insert(
+                        id,
+                        TabState {
+                            page: opened.page,
+                            ref_index: RefIndex::new(),
+                            current_url: old.current_url,
+                            cached_snapshot: None,
+                            observer_installed: false,
+                            task_context: old.task_context,
+                            previous_snapshot: None,
+                            network: opened.network,
+                            intercept: opened.intercept,
+                            auth_scripts: opened.auth_scripts,
+                        },
+                    );
+                    reopened += 1;
+                }
+                Err(e) => {
+                    warn!(tab_id = id, url = %old.current_url, error = %e, "failed to reopen tab");
+                    notes.push(format!(
+                        "tab {id} ({}) could not be reopened",
+                        old.current_url
+                    ));
+                }
+            }
         }
+        if !state.tabs.contains_key(&state.active_tab) {
+            state.active_tab = state.tabs.keys().min().copied().unwrap_or(0);
+        }
+        info!(tabs = reopened, "browser recovered");
 
-        info!(
-            launch = self.launch_browser,
-            port = self.port,
-            "initializing browser connection"
+        let mut notice = format!(
+            "NOTICE: The browser crashed or disconnected and was restarted. \
+             {reopened} tab(s) were reopened at their last URLs"
+        );
+        for note in notes {
+            notice.push_str("; ");
+            notice.push_str(&note);
+        }
+        notice.push_str(
+            ". Page state (form input, scroll position, in-page navigation) was lost and \
+             all @eN refs are stale: take a new snapshot before interacting.",
         );
+        Ok(Some(notice))
+    }
+
+    /// Obtain a browser (and context) for the session: leased from the pool,
+    /// launched, or connected to over CDP.
+    async fn connect_browser(&self, state: &mut BrowserState) -> anyhow::Result<()> {
+        if let Some(pool) = &self.pool {
+            let lease = pool.lease().await?;
+            state.browser = Some(lease.browser());
+            state.context = Some(lease.context().clone());
+            state.lease = Some(lease);
+            info!("browser context leased from pool");
+            return Ok(());
+        }
+
         let b = if self.launch_browser {
-            browser::launch().await?
+            browser::launch(&self.browser_options).await?
         } else {
             browser::connect(self.port).await?
         };
+        if self.isolate {
+            state.context = Some(browser::create_context(&b, &self.browser_options.proxy).await?);
+        }
+
+        state.browser = Some(Arc::new(b));
+        info!(isolated = self.isolate, "browser ready");
+        Ok(())
+    }
+
+    /// Check the browser before a tool runs, if one was started. Returns the
+    /// notice to show the agent when it had to be reconnected.
+    async fn recover_browser(&self) -> Option<String> {
+        {
+            // Checked under the read lock without a CDP round trip, so tools
+            // do not queue up behind each other here.
+            let state = self.state.read().await;
+            let connected = match &state.browser {
+                Some(b) => b.is_connected(),
+                None => state.tabs.is_empty(),
+            };
+            if connected {
+                return None;
+            }
+        }
+        match self.ensure_browser().await {
+            Ok(notice) => notice,
+            Err(e) => {
+                warn!(error = %e, "browser recovery failed");
+                Some(format!(
+                    "NOTICE: The browser crashed or disconnected and could not be restarted: {e}"
+                ))
+            }
+        }
+    }
+
+    /// Create a blank page, attach per-tab listeners before anything loads, then
+    /// navigate it to `url`.
+    async fn open_page(&self, state: &BrowserState, url: &str) -> anyhow::Result<OpenedPage> {
+        let browser = state.browser.as_ref().context("No browser")?;
+        let mut params = CreateTargetParams::new("about:blank");
+        params.browser_context_id = state.context.clone();
+        let page = browser
+            .new_page(params)
+            .await
+            .context("Failed to create page")?;
+
+        let network_log = network::NetworkLog::shared();
+        if let Err(e) = network::attach(&page, network_log.clone()).await {
+            warn!(error = %e, "failed to attach network listeners");
+        }
+        if let Err(e) = har::attach(&page, self.har.clone()).await {
+            warn!(error = %e, "failed to attach HAR listeners");
+        }
+
+        let stats = Arc::new(intercept::InterceptStats::default());
+        intercept::attach(&page, self.intercept_rules.clone(), stats.clone()).await?;
+        let rules = self.current_rules();
+        if rules.is_active() {
+            intercept::sync(&page, &rules)
+                .await
+                .context("Failed to enable request interception")?;
+        }
+
+        let emulation = self.current_emulation();
+        if emulation.is_active() {
+            emulation::apply(&page, &emulation, self.browser_options.base_viewport())
+                .await
+                .context("Failed to apply emulation")?;
+        }
+
+        let headers = self.current_headers();
+        if !headers.is_empty() {
+            proxy::apply_headers(&page, &headers)
+                .await
+                .context("Failed to set extra headers")?;
+        }
+
+        let mut auth_scripts = Vec::new();
+        for script in state.auth_scripts.values() {
+            auth_scripts.push(
+                storage::install(&page, script)
+                    .await
+                    .context("Failed to register auth storage script")?,
+            );
+        }
+
+        if let Err(e) = page.goto(url).await {
+            policy_violation(&stats)?;
+            return Err(e.into());
+        }
+        Ok(OpenedPage {
+            page,
+            network: network_log,
+            intercept: stats,
+            auth_scripts,
+        })
+    }
+
+    fn current_emulation(&self) -> emulation::Emulation {
+        self.emulation
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn current_headers(&self) -> proxy::ExtraHeaders {
+        self.extra_headers
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn current_rules(&self) -> intercept::InterceptRules {
+        self.intercept_rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
 
-        state.browser = Some(b);
-        info!("browser ready");
+    /// Refuse a URL the domain policy does not allow, before loading it.
+    fn check_policy(&self, url: &str) -> anyhow::Result<()> {
+        let rules = self
+            .intercept_rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Err(reason) = rules.policy.check(url) {
+            warn!(url = %url, reason = %reason, "navigation denied by domain policy");
+            anyhow::bail!("Navigation to {url} blocked by domain policy: {reason}");
+        }
         Ok(())
     }
 
     async fn do_navigate(&self, url: &str) -> anyhow::Result<String> {
         info!(url = %url, "navigate");
+        self.check_policy(url)?;
         self.ensure_browser().await?;
 
         let mut state = self.state.write().await;
+        self.auto_restore_auth(&mut state, url).await?;
 
         // Reuse existing page if we have an active tab, otherwise create a new one.
-        let page = if state.tabs.is_empty() {
-            let browser = state.browser.as_ref().context("No browser")?;
-            browser
-                .new_page(url)
+        let (page, opened) = if state.tabs.is_empty() {
+            let opened = self
+                .open_page(&state, url)
                 .await
-                .with_context(|| format!("Failed to navigate to {url}"))?
+                .with_context(|| format!("Failed to navigate to {url}"))?;
+            (opened.page.clone(), Some(opened))
         } else {
             let tab = state.active_tab()?;
-            tab.page
-                .goto(url)
-                .await
-                .with_context(|| format!("Failed to navigate to {url}"))?;
+            tab.intercept.take_violation();
+            if let Err(e) = tab.page.goto(url).await {
+                policy_violation(&tab.intercept)?;
+                return Err(e).with_context(|| format!("Failed to navigate to {url}"));
+            }
             // Return a reference isn't possible here, so we re-fetch below.
             // The page is already in the tab state - we'll update it in place.
-            state.active_tab()?.page.clone()
+            (state.active_tab()?.page.clone(), None)
         };
 
         page.wait_for_navigation().await.ok();
+        match &opened {
+            Some(opened) => policy_violation(&opened.intercept)?,
+            None => policy_violation(&state.active_tab()?.intercept)?,
+        }
 
         let html = page.content().await.context("Failed to get page content")?;
         let final_url = page
@@ -820,6 +1888,8 @@ impl CortexBrowserServer {
 
         let mut result = pipeline::process_with_refs(&html, &final_url);
         result.snapshot.viewport = viewport;
+        result.snapshot.emulation = self.current_emulation().describe();
+        result.snapshot.auth = state.auth_label(&final_url);
 
         let ref_exprs: Vec<(u32, String)> = result
             .ref_index
@@ -847,7 +1917,7 @@ impl CortexBrowserServer {
             url: url.to_string(),
         });
 
-        if state.tabs.is_empty() {
+        if let Some(opened) = opened {
             let tab_id = state.next_tab_id;
             state.next_tab_id += 1;
             let text = apply_task_context(&None, &result.snapshot);
@@ -862,6 +1932,9 @@ impl CortexBrowserServer {
                     observer_installed: true,
                     task_context: None,
                     previous_snapshot: None,
+                    network: opened.network,
+                    intercept: opened.intercept,
+                    auth_scripts: opened.auth_scripts,
                 },
             );
             state.active_tab = tab_id;
@@ -938,6 +2011,8 @@ impl CortexBrowserServer {
 
         let mut result = pipeline::process_with_refs(&html, &url);
         result.snapshot.viewport = viewport;
+        result.snapshot.emulation = self.current_emulation().describe();
+        result.snapshot.auth = state.auth_label(&url);
 
         let tab = state.active_tab()?;
         let ref_exprs: Vec<(u32, String)> = result
@@ -985,6 +2060,12 @@ impl CortexBrowserServer {
                 .with_context(|| format!("Unknown ref @e{ref_id}"))?;
             (locator.click_js(), locator.clone())
         };
+        // Relative links are checked by interception once they resolve.
+        if let Some(href) = captured_locator.href.as_deref() {
+            if policy::url_scheme(href).is_some() {
+                self.check_policy(href)?;
+            }
+        }
         let result = self.execute_and_snapshot(&js, ref_id, return_diff).await?;
         {
             let mut state = self.state.write().await;
@@ -1000,9 +2081,11 @@ impl CortexBrowserServer {
         &self,
         ref_id: u32,
         text: &str,
+        variable: Option<&str>,
         return_diff: bool,
     ) -> anyhow::Result<String> {
-        info!(ref_id = ref_id, text = %text, return_diff = return_diff, "type_text");
+        let placeholder = variable.map(recording::variable_placeholder).transpose()?;
+        let code = self.expand_totp(text)?;
         let (js, captured_locator) = {
             let state = self.state.read().await;
             let tab = state.active_tab()?;
@@ -1010,27 +2093,59 @@ impl CortexBrowserServer {
                 .ref_index
                 .get(&ref_id)
                 .with_context(|| format!("Unknown ref @e{ref_id}"))?;
-            (locator.type_js(text), locator.clone())
+            (
+                locator.type_js(code.as_deref().unwrap_or(text)),
+                locator.clone(),
+            )
+        };
+        // A TOTP placeholder is logged and recorded as is, so replays type a
+        // fresh code.
+        let secret = captured_locator.secret_kind().filter(|_| code.is_none());
+        let logged = if secret.is_some() {
+            secrets::REDACTED
+        } else {
+            text
         };
+        info!(ref_id = ref_id, text = %logged, return_diff = return_diff, "type_text");
         let result = self.execute_and_snapshot(&js, ref_id, return_diff).await?;
         {
             let mut state = self.state.write().await;
+            let text = match (placeholder, secret) {
+                (Some(placeholder), _) => placeholder,
+                (None, Some(kind)) => secrets::placeholder(&captured_locator, kind),
+                (None, None) if code.is_some() => text.to_string(),
+                (None, None) => recording::escape_braces(text),
+            };
             state.record(recording::RecordedAction::TypeText {
                 locator: captured_locator,
-                text: text.to_string(),
+                text,
                 ref_id,
             });
         }
         Ok(result)
     }
 
+    /// The current code if `text` is a `{{totp:PROFILE}}` placeholder.
+    fn expand_totp(&self, text: &str) -> anyhow::Result<Option<String>> {
+        let Some(profile) = totp::placeholder_profile(text) else {
+            return Ok(None);
+        };
+        let (code, _) = self
+            .auth_store
+            .totp_code(profile, None)
+            .with_context(|| format!("Cannot expand {text}"))?;
+        Ok(Some(code))
+    }
+
     async fn do_select(
         &self,
         ref_id: u32,
         value: &str,
+        variable: Option<&str>,
         return_diff: bool,
     ) -> anyhow::Result<String> {
         info!(ref_id = ref_id, value = %value, return_diff = return_diff, "select_option");
+        let placeholder = variable.map(recording::variable_placeholder).transpose()?;
         let (js, captured_locator) = {
             let state = self.state.read().await;
             let tab = state.active_tab()?;
@@ -1045,7 +2160,7 @@ impl CortexBrowserServer {
             let mut state = self.state.write().await;
             state.record(recording::RecordedAction::SelectOption {
                 locator: captured_locator,
-                value: value.to_string(),
+                value: placeholder.unwrap_or_else(|| recording::escape_braces(value)),
                 ref_id,
             });
         }
@@ -1071,6 +2186,7 @@ impl CortexBrowserServer {
         let result_value = {
             let state = self.state.read().await;
             let tab = state.active_tab()?;
+            tab.intercept.take_violation();
             let eval = tab
                 .page
                 .evaluate(js)
@@ -1085,6 +2201,7 @@ impl CortexBrowserServer {
         }
 
         tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        policy_violation(&self.state.read().await.active_tab()?.intercept)?;
         let full_snapshot = self.do_snapshot().await?;
 
         if return_diff {
@@ -1104,6 +2221,17 @@ impl CortexBrowserServer {
     }
 
     async fn do_wait_for_changes(&self, timeout_ms: u64) -> anyhow::Result<String> {
+        let result = self.wait_for_dom_change(timeout_ms).await;
+        if result.is_ok() {
+            self.state
+                .write()
+                .await
+                .record(recording::RecordedAction::WaitForChanges { timeout_ms });
+        }
+        result
+    }
+
+    async fn wait_for_dom_change(&self, timeout_ms: u64) -> anyhow::Result<String> {
         debug!(timeout_ms = timeout_ms, "waiting for DOM changes");
         let deadline = tokio::time::Instant::now() + std::time::Duration::from_millis(timeout_ms);
         let poll_interval = std::time::Duration::from_millis(100);
@@ -1194,7 +2322,9 @@ impl CortexBrowserServer {
             .flatten()
             .unwrap_or_else(|| tab.current_url.clone());
 
-        let result = pipeline::process_with_refs(&html, &url);
+        let mut result = pipeline::process_with_refs(&html, &url);
+        result.snapshot.emulation = self.current_emulation().describe();
+        result.snapshot.auth = state.auth_label(&url);
         let filtered = ctx.filter_snapshot(&result.snapshot);
         let text = serialize::to_compact_text(&filtered);
 
@@ -1207,17 +2337,24 @@ impl CortexBrowserServer {
 
     async fn do_open_tab(&self, url: &str) -> anyhow::Result<String> {
         info!(url = %url, "open_tab");
+        self.check_policy(url)?;
         self.ensure_browser().await?;
 
         let mut state = self.state.write().await;
-        let browser = state.browser.as_ref().context("No browser")?;
-
-        let page = browser
-            .new_page(url)
+        self.auto_restore_auth(&mut state, url).await?;
+
+        let OpenedPage {
+            page,
+            network: network_log,
+            intercept: intercept_stats,
+            auth_scripts,
+        } = self
+            .open_page(&state, url)
             .await
             .with_context(|| format!("Failed to open tab for {url}"))?;
 
         page.wait_for_navigation().await.ok();
+        policy_violation(&intercept_stats)?;
 
         let html = page.content().await.context("Failed to get page content")?;
         let final_url = page
@@ -1229,7 +2366,9 @@ impl CortexBrowserServer {
 
         page.evaluate(mutation::INSTALL_OBSERVER_JS).await.ok();
 
-        let result = pipeline::process_with_refs(&html, &final_url);
+        let mut result = pipeline::process_with_refs(&html, &final_url);
+        result.snapshot.emulation = self.current_emulation().describe();
+        result.snapshot.auth = state.auth_label(&final_url);
         let text = serialize::to_compact_text(&result.snapshot);
 
         let tab_id = state.next_tab_id;
@@ -1245,9 +2384,16 @@ impl CortexBrowserServer {
                 observer_installed: true,
                 task_context: None,
                 previous_snapshot: None,
+                network: network_log,
+                intercept: intercept_stats,
+                auth_scripts,
             },
         );
         state.active_tab = tab_id;
+        state.record(recording::RecordedAction::OpenTab {
+            url: url.to_string(),
+            tab_id,
+        });
 
         info!(tab_id = tab_id, "tab opened");
         Ok(format!("Tab {tab_id} opened.\n{text}"))
@@ -1270,7 +2416,11 @@ impl CortexBrowserServer {
             } else {
                 ""
             };
-            lines.push(format!("Tab {id}{active}: [{}]", tab.current_url));
+            let blocked = match tab.intercept.blocked() {
+                0 => String::new(),
+                n => format!(" ({n} blocked)"),
+            };
+            lines.push(format!("Tab {id}{active}: [{}]{blocked}", tab.current_url));
         }
 
         Ok(lines.join("\n"))
@@ -1284,6 +2434,7 @@ impl CortexBrowserServer {
             anyhow::bail!("No tab with ID {tab_id}");
         }
         state.active_tab = tab_id;
+        state.record(recording::RecordedAction::SwitchTab { tab_id });
         drop(state);
 
         self.do_snapshot().await
@@ -1298,6 +2449,7 @@ impl CortexBrowserServer {
             .with_context(|| format!("No tab with ID {tab_id}"))?;
 
         tab.page.close().await.ok();
+        state.record(recording::RecordedAction::CloseTab { tab_id });
 
         if state.active_tab == tab_id {
             state.active_tab = state.tabs.keys().copied().min().unwrap_or(0);
@@ -1313,7 +2465,11 @@ impl CortexBrowserServer {
         }
     }
 
-    async fn do_scroll(&self, scroll_js: &str) -> anyhow::Result<String> {
+    async fn do_scroll(
+        &self,
+        scroll_js: &str,
+        action: recording::RecordedAction,
+    ) -> anyhow::Result<String> {
         {
             let state = self.state.read().await;
             let tab = state.active_tab()?;
@@ -1324,6 +2480,7 @@ impl CortexBrowserServer {
             let mut state = self.state.write().await;
             let tab = state.active_tab_mut()?;
             tab.cached_snapshot = None;
+            state.record(action);
         }
         self.do_snapshot().await
     }
@@ -1392,11 +2549,15 @@ impl CortexBrowserServer {
             ("unknown".into(), String::new())
         };
 
+        let start_tab = state.active_tab().ok().map(|_| state.active_tab);
+        state.last_recorded_at = params.timing.unwrap_or(false).then(std::time::Instant::now);
         state.active_recording = Some(recording::Recording {
             name: params.name.clone(),
             domain: domain.clone(),
             start_url,
             created_at: recording::now_timestamp(),
+            version: 1,
+            start_tab,
             description: params.description.clone(),
             actions: Vec::new(),
         });
@@ -1411,6 +2572,7 @@ impl CortexBrowserServer {
     async fn do_stop_recording(&self) -> anyhow::Result<String> {
         let rec = {
             let mut state = self.state.write().await;
+            state.last_recorded_at = None;
             state
                 .active_recording
                 .take()
@@ -1421,24 +2583,60 @@ impl CortexBrowserServer {
         let path = self.store.save(&rec)?;
 
         info!(name = %rec.name, actions = action_count, path = %path.display(), "recording saved");
+        let variables = rec.variables();
+        let vars = if variables.is_empty() {
+            String::new()
+        } else {
+            format!("\nVariables: {}", variables.join(", "))
+        };
         Ok(format!(
-            "Recording '{}' saved with {} action(s) to {}",
+            "Recording '{}' saved with {} action(s) to {}{}",
             rec.name,
             action_count,
-            path.display()
+            path.display(),
+            vars
         ))
     }
 
-    async fn do_replay_recording(&self, params: ReplayRecordingParams) -> anyhow::Result<String> {
+    async fn do_replay_recording(
+        &self,
+        params: ReplayRecordingParams,
+        peer: &Peer<RoleServer>,
+    ) -> anyhow::Result<String> {
         let rec = self.store.load(&params.name, params.domain.as_deref())?;
         info!(name = %rec.name, actions = rec.actions.len(), "replaying recording");
+        let variables = params.variables.unwrap_or_default();
+        let missing: Vec<String> = rec
+            .variables()
+            .into_iter()
+            .filter(|name| !variables.contains_key(name))
+            .collect();
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "Recording '{}' needs values for variable(s): {}. Pass them in `variables`.",
+                rec.name,
+                missing.join(", ")
+            );
+        }
 
         {
             let mut state = self.state.write().await;
             state.replaying = true;
         }
 
-        let replay_result = self.do_replay_actions(&rec).await;
+        let delay_scale = params.delay_scale.unwrap_or(1.0);
+        if !(delay_scale.is_finite() && delay_scale >= 0.0) {
+            anyhow::bail!("delay_scale must be 0 or more, not {delay_scale}");
+        }
+
+        let provider = secrets::SecretProvider::new(params.secrets.unwrap_or_default());
+        let heal = match params.heal {
+            Some(false) => None,
+            _ => Some(params.heal_threshold.unwrap_or(heal::DEFAULT_THRESHOLD)),
+        };
+        let replay_result = self
+            .do_replay_actions(&rec, &provider, &variables, heal, delay_scale, peer)
+            .await;
 
         {
             let mut state = self.state.write().await;
@@ -1448,38 +2646,246 @@ impl CortexBrowserServer {
         replay_result
     }
 
-    async fn do_replay_actions(&self, rec: &recording::Recording) -> anyhow::Result<String> {
+    async fn do_replay_actions(
+        &self,
+        rec: &recording::Recording,
+        secrets: &secrets::SecretProvider,
+        variables: &BTreeMap<String, String>,
+        heal: Option<f64>,
+        delay_scale: f64,
+        peer: &Peer<RoleServer>,
+    ) -> anyhow::Result<String> {
         let mut step_results = Vec::new();
+        let mut failed = None;
+        let mut healed_rec = rec.clone();
+        let mut healed_steps = Vec::new();
+        let mut tabs = recording::TabMap::new(rec.start_tab, self.state.read().await.active_tab);
 
         for (i, action) in rec.actions.iter().enumerate() {
             let step_num = i + 1;
+            let substitute = |text: &str| {
+                recording::substitute(text, variables)
+                    .with_context(|| format!("Replay step {step_num}"))
+            };
             match action {
                 recording::RecordedAction::Navigate { url } => {
-                    self.do_navigate(url).await?;
+                    let url = substitute(url)?;
+                    self.do_navigate(&url).await?;
                     step_results.push(format!("Step {}: navigate → {}", step_num, url));
                 }
-                recording::RecordedAction::Click { locator, .. } => {
-                    let js = locator.click_js();
-                    self.execute_replay_step(&js, step_num).await?;
+                recording::RecordedAction::Click { locator, ref_id } => {
+                    let healed = self
+                        .execute_replay_step(
+                            locator,
+                            *ref_id,
+                            step_num,
+                            heal,
+                            Some((confirm::ActionKind::Click, peer)),
+                            |l| l.click_js(),
+                        )
+                        .await?;
                     step_results.push(format!("Step {}: click", step_num));
+                    if let Some(target) = healed {
+                        note_healed(&mut step_results, &mut healed_rec.actions[i], target);
+                        healed_steps.push(step_num);
+                    }
                 }
-                recording::RecordedAction::TypeText { locator, text, .. } => {
-                    let js = locator.type_js(text);
-                    self.execute_replay_step(&js, step_num).await?;
+                recording::RecordedAction::TypeText {
+                    locator,
+                    text,
+                    ref_id,
+                } => {
+                    // Placeholders are expanded before variables, so a
+                    // variable value cannot name a secret or TOTP profile.
+                    let text = match self
+                        .expand_totp(text)
+                        .with_context(|| format!("Replay step {step_num}"))?
+                    {
+                        Some(code) => code,
+                        None if secrets::placeholder_name(text).is_some() => secrets
+                            .resolve(text)
+                            .with_context(|| format!("Replay step {step_num}"))?,
+                        None => substitute(text)?,
+                    };
+                    let healed = self
+                        .execute_replay_step(
+                            locator,
+                            *ref_id,
+                            step_num,
+                            heal,
+                            Some((confirm::ActionKind::TypeText, peer)),
+                            |l| l.type_js(&text),
+                        )
+                        .await?;
                     step_results.push(format!("Step {}: type_text", step_num));
+                    if let Some(target) = healed {
+                        note_healed(&mut step_results, &mut healed_rec.actions[i], target);
+                        healed_steps.push(step_num);
+                    }
                 }
-                recording::RecordedAction::SelectOption { locator, value, .. } => {
-                    let js = locator.select_js(value);
-                    self.execute_replay_step(&js, step_num).await?;
+                recording::RecordedAction::SelectOption {
+                    locator,
+                    value,
+                    ref_id,
+                } => {
+                    let value = substitute(value)?;
+                    let healed = self
+                        .execute_replay_step(locator, *ref_id, step_num, heal, None, |l| {
+                            l.select_js(&value)
+                        })
+                        .await?;
                     step_results.push(format!("Step {}: select_option", step_num));
+                    if let Some(target) = healed {
+                        note_healed(&mut step_results, &mut healed_rec.actions[i], target);
+                        healed_steps.push(step_num);
+                    }
+                }
+                recording::RecordedAction::ScrollDown => {
+                    self.do_scroll(mutation::SCROLL_DOWN_JS, action.clone())
+                        .await?;
+                    step_results.push(format!("Step {}: scroll_down", step_num));
+                }
+                recording::RecordedAction::ScrollUp => {
+                    self.do_scroll(mutation::SCROLL_UP_JS, action.clone())
+                        .await?;
+                    step_results.push(format!("Step {}: scroll_up", step_num));
+                }
+                recording::RecordedAction::ScrollToRef { locator, ref_id } => {
+                    let healed = self
+                        .execute_replay_step(locator, *ref_id, step_num, heal, None, |l| {
+                            l.scroll_into_view_js()
+                        })
+                        .await?;
+                    step_results.push(format!("Step {}: scroll_to_ref", step_num));
+                    if let Some(target) = healed {
+                        note_healed(&mut step_results, &mut healed_rec.actions[i], target);
+                        healed_steps.push(step_num);
+                    }
+                }
+                recording::RecordedAction::WaitForChanges { timeout_ms } => {
+                    self.do_wait_for_changes(*timeout_ms).await?;
+                    step_results.push(format!("Step {}: wait_for_changes", step_num));
+                }
+                recording::RecordedAction::PressKey {
+                    key,
+                    locator,
+                    ref_id,
+                } => {
+                    let approve =
+                        confirm::submits_on(key).then_some((confirm::ActionKind::PressKey, peer));
+                    if let Some(locator) = locator {
+                        let healed = self
+                            .execute_replay_step(
+                                locator,
+                                ref_id.unwrap_or(0),
+                                step_num,
+                                heal,
+                                approve,
+                                |l| l.focus_js(),
+                            )
+                            .await?;
+                        if let Some(target) = healed {
+                            note_healed(&mut step_results, &mut healed_rec.actions[i], target);
+                            healed_steps.push(step_num);
+                        }
+                    } else if let Some((kind, peer)) = approve {
+                        self.approve_replay_step(peer, kind, None, step_num).await?;
+                    }
+                    self.dispatch_key(key)
+                        .await
+                        .with_context(|| format!("Replay step {step_num}"))?;
+                    step_results.push(format!("Step {}: press_key {}", step_num, key));
+                }
+                recording::RecordedAction::GoBack => {
+                    self.do_history(true).await?;
+                    step_results.push(format!("Step {}: go_back", step_num));
+                }
+                recording::RecordedAction::GoForward => {
+                    self.do_history(false).await?;
+                    step_results.push(format!("Step {}: go_forward", step_num));
+                }
+                recording::RecordedAction::OpenTab { url, tab_id } => {
+                    let url = substitute(url)?;
+                    self.do_open_tab(&url).await?;
+                    let live = self.state.read().await.active_tab;
+                    tabs.opened(*tab_id, live);
+                    step_results.push(format!(
+                        "Step {}: open_tab → {} (tab {})",
+                        step_num, url, live
+                    ));
+                }
+                recording::RecordedAction::SwitchTab { tab_id } => {
+                    let live = tabs
+                        .resolve(*tab_id)
+                        .with_context(|| format!("Replay step {step_num}"))?;
+                    self.do_switch_tab(live).await?;
+                    step_results.push(format!("Step {}: switch_tab {}", step_num, live));
+                }
+                recording::RecordedAction::CloseTab { tab_id } => {
+                    let live = tabs
+                        .resolve(*tab_id)
+                        .with_context(|| format!("Replay step {step_num}"))?;
+                    self.do_close_tab(live).await?;
+                    tabs.closed(*tab_id);
+                    step_results.push(format!("Step {}: close_tab {}", step_num, live));
+                }
+                recording::RecordedAction::Delay { ms } => {
+                    let ms = recording::replay_delay_ms(*ms, delay_scale);
+                    tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
+                    step_results.push(format!("Step {}: delay {}ms", step_num, ms));
+                }
+                recording::RecordedAction::AssertText { .. }
+                | recording::RecordedAction::AssertUrl { .. }
+                | recording::RecordedAction::AssertRef { .. }
+                | recording::RecordedAction::AssertSnapshotContains { .. } => {
+                    let outcome = self
+                        .check_assertion(action, variables)
+                        .await
+                        .with_context(|| format!("Replay step {step_num}"))?;
+                    match outcome {
+                        Ok(()) => {
+                            step_results.push(format!("Step {}: {} PASS", step_num, action.name()))
+                        }
+                        Err(reason) => {
+                            step_results.push(format!(
+                                "Step {}: {} FAIL: {}",
+                                step_num,
+                                action.name(),
+                                reason
+                            ));
+                            failed = Some(step_num);
+                            break;
+                        }
+                    }
                 }
             }
         }
 
+        if heal::keep_healed(&healed_steps, failed) {
+            let path = self.store.save_version(&mut healed_rec)?;
+            info!(name = %rec.name, version = healed_rec.version, steps = ?healed_steps, "healed recording saved");
+            step_results.push(format!(
+                "Healed step(s) {}; saved as version {} to {}",
+                heal::step_list(&healed_steps),
+                healed_rec.version,
+                path.display()
+            ));
+        } else if !healed_steps.is_empty() {
+            step_results.push(format!(
+                "Healed step(s) {}; not saved because the replay failed",
+                heal::step_list(&healed_steps)
+            ));
+        }
+
         let final_snapshot = self.do_snapshot().await?;
+        let status = match failed {
+            Some(step) => format!("FAILED at step {step}"),
+            None => "completed".to_string(),
+        };
         let summary = format!(
-            "Replay '{}' completed ({} steps):\n{}\n\n{}",
+            "Replay '{}' {} ({} steps):\n{}\n\n{}",
             rec.name,
+            status,
             rec.actions.len(),
             step_results.join("\n"),
             final_snapshot,
@@ -1487,28 +2893,284 @@ impl CortexBrowserServer {
         Ok(summary)
     }
 
-    /// Execute a JS step during replay, checking for NOT_FOUND.
-    async fn execute_replay_step(&self, js: &str, step_num: usize) -> anyhow::Result<()> {
-        let result_value = {
+    /// Evaluate a recorded assertion on the active tab. The outer error is a
+    /// failure to check; the inner one says why the assertion does not hold.
+    async fn check_assertion(
+        &self,
+        action: &recording::RecordedAction,
+        variables: &BTreeMap<String, String>,
+    ) -> anyhow::Result<Result<(), String>> {
+        let eval = |js: String| async move {
             let state = self.state.read().await;
-            let tab = state.active_tab()?;
-            let eval = tab
+            let value = state
+                .active_tab()?
                 .page
                 .evaluate(js)
                 .await
-                .context("Failed to execute replay action")?;
-            eval.into_value::<String>().unwrap_or_default()
+                .context("Failed to evaluate assertion")?;
+            anyhow::Ok(value.into_value::<String>().unwrap_or_default())
+        };
+        Ok(match action {
+            recording::RecordedAction::AssertText { text } => {
+                let page = eval(PAGE_TEXT_JS.to_string()).await?;
+                recording::verify_contains(&page, &recording::substitute(text, variables)?)
+            }
+            recording::RecordedAction::AssertUrl { url } => {
+                let current = eval("location.href".to_string()).await?;
+                recording::verify_url(&current, &recording::substitute(url, variables)?)
+            }
+            recording::RecordedAction::AssertRef { locator, check, .. } => {
+                let check = match check {
+                    recording::RefCheck::Value(value) => {
+                        recording::RefCheck::Value(recording::substitute(value, variables)?)
+                    }
+                    other => other.clone(),
+                };
+                let json = eval(locator.inspect_js()).await?;
+                let state = serde_json::from_str::<crate::dom::ElementState>(&json).ok();
+                check.verify(state.as_ref())
+            }
+            recording::RecordedAction::AssertSnapshotContains { text } => {
+                let snapshot = self.do_snapshot().await?;
+                recording::verify_contains(&snapshot, &recording::substitute(text, variables)?)
+            }
+            other => anyhow::bail!("{} is not an assertion", other.name()),
+        })
+    }
+
+    /// Run a replay step's JS on its recorded element. If the locator no
+    /// longer finds it and healing is on (`heal` holds the threshold), re-find
+    /// the element in a fresh snapshot and run the step on that instead.
+    /// Clicks and typing pass `approve` so confirm rules apply to the element
+    /// the step acts on.
+    async fn execute_replay_step(
+        &self,
+        locator: &crate::dom::ElementLocator,
+        ref_id: u32,
+        step_num: usize,
+        heal: Option<f64>,
+        approve: Option<(confirm::ActionKind, &Peer<RoleServer>)>,
+        js: impl Fn(&crate::dom::ElementLocator) -> String,
+    ) -> anyhow::Result<Option<heal::HealedTarget>> {
+        let healed = if self.replay_element_exists(locator).await? {
+            None
+        } else {
+            Some(
+                self.heal_replay_step(locator, ref_id, step_num, heal)
+                    .await?,
+            )
         };
+        let element = healed.as_ref().map_or(locator, |target| &target.locator);
+        if let Some((kind, peer)) = approve {
+            self.approve_replay_step(peer, kind, Some(element), step_num)
+                .await?;
+        }
+        if !self.run_replay_js(&js(element)).await? {
+            match &healed {
+                Some(target) => anyhow::bail!(
+                    "Replay step {}: healed element @e{} not found in the live DOM",
+                    step_num,
+                    target.ref_id
+                ),
+                None => anyhow::bail!(
+                    "Replay step {}: element not found in the live DOM",
+                    step_num
+                ),
+            }
+        }
+        if let Some(target) = &healed {
+            warn!(
+                step = step_num,
+                ref_id = target.ref_id,
+                confidence = target.confidence,
+                "replay step healed"
+            );
+        }
+        self.settle_replay_step().await?;
+        Ok(healed)
+    }
 
-        if result_value == "NOT_FOUND" {
+    /// Whether a replay step's recorded element is in the live DOM.
+    async fn replay_element_exists(
+        &self,
+        locator: &crate::dom::ElementLocator,
+    ) -> anyhow::Result<bool> {
+        let js = format!("String(!!({}))", locator.to_js_expression());
+        let state = self.state.read().await;
+        let value = state
+            .active_tab()?
+            .page
+            .evaluate(js)
+            .await
+            .context("Failed to execute replay action")?;
+        Ok(value.into_value::<String>().unwrap_or_default() == "true")
+    }
+
+    /// Re-find a replay step's element that its locator no longer matches.
+    async fn heal_replay_step(
+        &self,
+        locator: &crate::dom::ElementLocator,
+        ref_id: u32,
+        step_num: usize,
+        heal: Option<f64>,
+    ) -> anyhow::Result<heal::HealedTarget> {
+        let Some(threshold) = heal else {
             anyhow::bail!(
                 "Replay step {}: element not found in the live DOM",
                 step_num
             );
+        };
+
+        self.do_snapshot().await?;
+        let matched = {
+            let state = self.state.read().await;
+            heal::best_match(locator, ref_id, &state.active_tab()?.ref_index, threshold)
+        };
+        matched.map_err(|reason| {
+            anyhow::anyhow!(
+                "Replay step {step_num}: element not found in the live DOM and could not be healed: {reason}"
+            )
+        })
+    }
+
+    /// Apply the confirm rules to a replayed click, typing or Enter step; a
+    /// key pressed in the focused element has no `locator`. The human is
+    /// asked through elicitation; without it the replay stops, since the rest
+    /// of the recording cannot wait in the pending queue.
+    async fn approve_replay_step(
+        &self,
+        peer: &Peer<RoleServer>,
+        kind: confirm::ActionKind,
+        locator: Option<&crate::dom::ElementLocator>,
+        step_num: usize,
+    ) -> anyhow::Result<()> {
+        let Some((_, host, reason)) = self.approval_reason(kind, locator).await? else {
+            return Ok(());
+        };
+        let summary = format!(
+            "{} on {host}",
+            confirm::summarize_replay(kind, locator, step_num)
+        );
+        warn!(action = %summary, reason = %reason, "replay step needs approval");
+        if !peer
+            .supported_elicitation_modes()
+            .contains(&ElicitationMode::Form)
+        {
+            anyhow::bail!(
+                "Replay step {step_num}: {summary} needs human approval ({reason}). \
+                 The replay stopped before it; run the remaining steps with the regular tools \
+                 so the action can be approved."
+            );
         }
+        self.elicit_approval(peer, &summary, &reason)
+            .await
+            .with_context(|| format!("Replay step {step_num}"))
+    }
+
+    /// Evaluate replay JS on the active tab; false if the element was not found.
+    async fn run_replay_js(&self, js: &str) -> anyhow::Result<bool> {
+        let result_value = {
+            let state = self.state.read().await;
+            let tab = state.active_tab()?;
+            tab.intercept.take_violation();
+            let eval = tab
+                .page
+                .evaluate(js)
+                .await
+                .context("Failed to execute replay action")?;
+            eval.into_value::<String>().unwrap_or_default()
+        };
+
+        Ok(result_value != "NOT_FOUND")
+    }
 
+    /// Let the page react to a replay step, then surface policy violations.
+    async fn settle_replay_step(&self) -> anyhow::Result<()> {
         tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-        Ok(())
+        policy_violation(&self.state.read().await.active_tab()?.intercept)
+    }
+
+    async fn do_add_assertion(&self, params: AddAssertionParams) -> anyhow::Result<String> {
+        info!(kind = %params.kind, "add_assertion");
+        if self.state.read().await.active_recording.is_none() {
+            anyhow::bail!("No active recording. Start one with start_recording.");
+        }
+        let required_text = || {
+            params
+                .text
+                .clone()
+                .filter(|t| !t.trim().is_empty())
+                .ok_or_else(|| anyhow::anyhow!("A {} assertion needs `text`", params.kind))
+        };
+        let action = match params.kind.to_ascii_lowercase().as_str() {
+            "text" => recording::RecordedAction::AssertText {
+                text: required_text()?,
+            },
+            "snapshot" => recording::RecordedAction::AssertSnapshotContains {
+                text: required_text()?,
+            },
+            "url" => {
+                let url = match params.text.clone() {
+                    Some(url) => url,
+                    None => self.state.read().await.active_tab()?.current_url.clone(),
+                };
+                recording::RecordedAction::AssertUrl { url }
+            }
+            "ref" => {
+                let ref_id = params
+                    .r#ref
+                    .ok_or_else(|| anyhow::anyhow!("A ref assertion needs `ref`"))?;
+                let locator = {
+                    let state = self.state.read().await;
+                    state
+                        .active_tab()?
+                        .ref_index
+                        .get(&ref_id)
+                        .with_context(|| format!("Unknown ref @e{ref_id}"))?
+                        .clone()
+                };
+                let check = match (params.value, params.checked) {
+                    (Some(_), Some(_)) => {
+                        anyhow::bail!("Pass either `value` or `checked`, not both")
+                    }
+                    // The value would be stored in the recording as plaintext.
+                    (Some(_), None) if locator.secret_kind().is_some() => anyhow::bail!(
+                        "@e{ref_id} is a sensitive field, so its value cannot be asserted. Assert that it exists instead."
+                    ),
+                    (Some(value), None) => recording::RefCheck::Value(value),
+                    (None, Some(checked)) => recording::RefCheck::Checked(checked),
+                    (None, None) => recording::RefCheck::Exists,
+                };
+                recording::RecordedAction::AssertRef {
+                    locator,
+                    check,
+                    ref_id,
+                }
+            }
+            other => {
+                anyhow::bail!("Unknown assertion kind '{other}'. Use text, url, ref or snapshot.")
+            }
+        };
+
+        // Assertions on variables can only be checked at replay time.
+        if action.variables().is_empty() {
+            if let Err(reason) = self.check_assertion(&action, &BTreeMap::new()).await? {
+                anyhow::bail!("The assertion does not hold on the current page: {reason}");
+            }
+        }
+
+        let mut state = self.state.write().await;
+        let rec = state
+            .active_recording
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No active recording"))?;
+        rec.actions.push(action.clone());
+        Ok(format!(
+            "Added {} as step {} of recording '{}'.",
+            action.name(),
+            rec.actions.len(),
+            rec.name
+        ))
     }
 
     async fn do_list_recordings(&self, params: ListRecordingsParams) -> anyhow::Result<String> {
@@ -1525,9 +3187,19 @@ impl CortexBrowserServer {
                     .as_deref()
                     .map(|d| format!(" - {d}"))
                     .unwrap_or_default();
+                let version = if s.version > 1 {
+                    format!(", v{}", s.version)
+                } else {
+                    String::new()
+                };
+                let vars = if s.variables.is_empty() {
+                    String::new()
+                } else {
+                    format!(", variables: {}", s.variables.join(", "))
+                };
                 format!(
-                    "  {} [{}] ({} actions){}",
-                    s.name, s.domain, s.action_count, desc
+                    "  {} [{}] ({} actions{}{}){}",
+                    s.name, s.domain, s.action_count, version, vars, desc
                 )
             })
             .collect();
@@ -1540,20 +3212,44 @@ impl CortexBrowserServer {
         Ok(format!("Recording '{}' deleted.", params.name))
     }
 
+    fn do_export_recording(&self, params: ExportRecordingParams) -> anyhow::Result<String> {
+        info!(name = %params.name, "export_recording");
+        let language = match params.language.as_deref() {
+            Some(language) => language.parse().map_err(|e: String| anyhow::anyhow!(e))?,
+            None => playwright::Language::TypeScript,
+        };
+        let Some(path) = params.path else {
+            let rec = self.store.load(&params.name, params.domain.as_deref())?;
+            return Ok(playwright::export(&rec, language));
+        };
+        let path = playwright::export_path(&self.store, &path)?;
+        let rec = playwright::export_file(
+            &self.store,
+            &params.name,
+            params.domain.as_deref(),
+            &path,
+            language,
+            params.overwrite.unwrap_or(false),
+        )?;
+        Ok(format!(
+            "Exported recording '{}' ({} action(s)) to {} as a {language} Playwright test.",
+            rec.name,
+            rec.actions.len(),
+            path.display()
+        ))
+    }
+
     async fn do_scroll_to_ref(&self, ref_id: u32) -> anyhow::Result<String> {
         debug!(ref_id = ref_id, "scroll_to_ref");
-        let js = {
+        let locator = {
             let state = self.state.read().await;
             let tab = state.active_tab()?;
-            let locator = tab
-                .ref_index
+            tab.ref_index
                 .get(&ref_id)
-                .with_context(|| format!("Unknown ref @e{ref_id}"))?;
-            format!(
-                "(function() {{ var el = {}; if (!el) return 'NOT_FOUND'; el.scrollIntoView({{behavior: 'instant', block: 'center'}}); return 'OK'; }})()",
-                locator.to_js_expression()
-            )
+                .with_context(|| format!("Unknown ref @e{ref_id}"))?
+                .clone()
         };
+        let js = locator.scroll_into_view_js();
 
         let result_value = {
             let state = self.state.read().await;
@@ -1575,7 +3271,88 @@ impl CortexBrowserServer {
             let mut state = self.state.write().await;
             let tab = state.active_tab_mut()?;
             tab.cached_snapshot = None;
+            state.record(recording::RecordedAction::ScrollToRef { locator, ref_id });
+        }
+        self.do_snapshot().await
+    }
+
+    async fn do_press_key(&self, key: &str, ref_id: Option<u32>) -> anyhow::Result<String> {
+        info!(key = %key, ref_id = ?ref_id, "press_key");
+        let locator = match ref_id {
+            Some(ref_id) => {
+                let state = self.state.read().await;
+                let locator = state
+                    .active_tab()?
+                    .ref_index
+                    .get(&ref_id)
+                    .with_context(|| format!("Unknown ref @e{ref_id}"))?
+                    .clone();
+                Some(locator)
+            }
+            None => None,
+        };
+        if let Some(locator) = &locator {
+            if !self.run_replay_js(&locator.focus_js()).await? {
+                anyhow::bail!(
+                    "Element @e{} not found in the live DOM",
+                    ref_id.unwrap_or(0)
+                );
+            }
+        }
+        self.dispatch_key(key).await?;
+        {
+            let mut state = self.state.write().await;
+            state.active_tab_mut()?.cached_snapshot = None;
+            state.record(recording::RecordedAction::PressKey {
+                key: key.to_string(),
+                locator,
+                ref_id,
+            });
+        }
+        self.do_snapshot().await
+    }
+
+    /// Dispatch a key press to the focused element of the active tab.
+    async fn dispatch_key(&self, key: &str) -> anyhow::Result<()> {
+        {
+            let state = self.state.read().await;
+            let tab = state.active_tab()?;
+            tab.intercept.take_violation();
+            tab.page
+                .find_element("html")
+                .await
+                .context("Failed to find the page")?
+                .press_key(key)
+                .await
+                .with_context(|| format!("Failed to press {key}"))?;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        policy_violation(&self.state.read().await.active_tab()?.intercept)
+    }
+
+    /// Go back or forward in the active tab's history.
+    async fn do_history(&self, back: bool) -> anyhow::Result<String> {
+        let (js, action) = if back {
+            ("history.back()", recording::RecordedAction::GoBack)
+        } else {
+            ("history.forward()", recording::RecordedAction::GoForward)
+        };
+        info!(action = action.name(), "history");
+        {
+            let mut state = self.state.write().await;
+            let tab = state.active_tab_mut()?;
+            tab.intercept.take_violation();
+            tab.page
+                .evaluate(js)
+                .await
+                .context("Failed to navigate history")?;
+            // A cross-document history step replaces the mutation observer.
+            tab.cached_snapshot = None;
+            tab.observer_installed = false;
         }
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        policy_violation(&self.state.read().await.active_tab()?.intercept)?;
+        self.state.write().await.record(action);
         self.do_snapshot().await
     }
 
@@ -1703,71 +3480,145 @@ impl CortexBrowserServer {
             })
             .collect();
 
+        let origins = if params.storage.unwrap_or(true) {
+            storage::capture(&tab.page, params.indexed_db.unwrap_or(false))
+                .await
+                .context("Failed to read web storage")?
+                .into_iter()
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let storage_note = match origins.first() {
+            Some(o) if !o.is_empty() => format!(
+                ", web storage for {} ({} localStorage, {} sessionStorage item(s), {} IndexedDB database(s))",
+                o.origin,
+                o.local_storage.len(),
+                o.session_storage.len(),
+                o.indexed_db.len()
+            ),
+            _ => String::new(),
+        };
+
         let url = &tab.current_url;
         let cookie_count = stored.len();
         let http_only_count = stored.iter().filter(|c| c.http_only).count();
-        let path = self.auth_store.save(url, &params.profile, stored)?;
+        let path = self
+            .auth_store
+            .save_with_storage(url, &params.profile, stored, origins)?;
 
         Ok(format!(
-            "Auth profile '{}' saved with {} cookie(s) ({} httpOnly) to {}",
+            "Auth profile '{}' saved with {} cookie(s) ({} httpOnly){} to {}{}",
             params.profile,
             cookie_count,
             http_only_count,
-            path.display()
+            storage_note,
+            path.display(),
+            if self.auth_store.is_encrypted() {
+                " (encrypted)"
+            } else {
+                ""
+            }
         ))
     }
 
     async fn do_restore_auth(&self, params: RestoreAuthParams) -> anyhow::Result<String> {
         info!(profile = %params.profile, "restore_auth");
+        let mut state = self.state.write().await;
+        state.active_tab()?;
+
         let profile = self
-            .auth_store
-            .load(&params.profile, params.domain.as_deref())?;
+            .restore_cookies(&state, &params.profile, params.domain.as_deref())
+            .await?;
+        state.last_auth = Some((params.profile.clone(), params.domain));
 
-        let state = self.state.read().await;
-        let tab = state.active_tab()?;
+        self.set_auth_script(&mut state, &profile).await?;
 
-        let cookie_params: Vec<chromiumoxide::cdp::browser_protocol::network::CookieParam> =
-            profile
-                .cookies
-                .iter()
-                .map(|c| {
-                    let mut param =
-                        chromiumoxide::cdp::browser_protocol::network::CookieParam::new(
-                            &c.name, &c.value,
-                        );
-                    param.domain = Some(c.domain.clone());
-                    param.path = Some(c.path.clone());
-                    param.secure = Some(c.secure);
-                    param.http_only = Some(c.http_only);
-                    if let Some(ref ss) = c.same_site {
-                        param.same_site = match ss.as_str() {
-                            "Strict" => Some(chromiumoxide::cdp::browser_protocol::network::CookieSameSite::Strict),
-                            "Lax" => Some(chromiumoxide::cdp::browser_protocol::network::CookieSameSite::Lax),
-                            "None" => Some(chromiumoxide::cdp::browser_protocol::network::CookieSameSite::None),
-                            _ => Option::None,
-                        };
-                    }
-                    if let Some(exp) = c.expires {
-                        param.expires = Some(chromiumoxide::cdp::browser_protocol::network::TimeSinceEpoch::new(exp));
-                    }
-                    param
-                })
-                .collect();
+        let storage_note = if profile.origins.is_empty() {
+            String::new()
+        } else {
+            let origins: Vec<&str> = profile.origins.iter().map(|o| o.origin.as_str()).collect();
+            format!(" and web storage for {}", origins.join(", "))
+        };
+        Ok(format!(
+            "Auth profile '{}' restored: {} cookie(s) injected for domain '{}'{}. Reload the page to apply.",
+            params.profile,
+            profile.cookies.len(),
+            profile.domain,
+            storage_note
+        ))
+    }
 
-        let count = cookie_params.len();
-        tab.page
-            .set_cookies(cookie_params)
+    /// Restore the profile bound to `url`'s host (see `bind_auth`) before the
+    /// page loads. Each binding is applied once per session.
+    async fn auto_restore_auth(&self, state: &mut BrowserState, url: &str) -> anyhow::Result<()> {
+        let host = network::url_host(url).to_ascii_lowercase();
+        let Some((pattern, binding)) = self.auth_store.binding_for(&host)? else {
+            return Ok(());
+        };
+        if state.auto_auth.get(&pattern) == Some(&binding) {
+            return Ok(());
+        }
+        let profile = self
+            .restore_cookies(state, &binding.profile, Some(&binding.domain))
             .await
-            .context("Failed to set cookies via CDP")?;
+            .with_context(|| {
+                format!(
+                    "Failed to restore auth profile '{}' bound to {pattern} (use unbind_auth to remove the binding)",
+                    binding.profile
+                )
+            })?;
+        self.set_auth_script(state, &profile).await?;
+        info!(profile = %binding.profile, pattern = %pattern, "auth profile restored automatically");
+        state.auto_auth.insert(pattern, binding);
+        Ok(())
+    }
 
-        Ok(format!(
-            "Auth profile '{}' restored: {} cookie(s) injected for domain '{}'. Reload the page to apply.",
-            params.profile, count, profile.domain
-        ))
+    /// Register `profile`'s web storage script on every tab, replacing the
+    /// script of an earlier restore of the same profile.
+    async fn set_auth_script(
+        &self,
+        state: &mut BrowserState,
+        profile: &auth::AuthProfile,
+    ) -> anyhow::Result<()> {
+        let key = format!("{}/{}", profile.domain, profile.profile);
+        match storage::restore_script(profile) {
+            Some(script) => state.auth_scripts.insert(key, script),
+            None => state.auth_scripts.remove(&key),
+        };
+        let scripts: Vec<String> = state.auth_scripts.values().cloned().collect();
+        for tab in state.tabs.values_mut() {
+            for id in tab.auth_scripts.drain(..) {
+                storage::uninstall(&tab.page, id).await.ok();
+            }
+            for script in &scripts {
+                tab.auth_scripts.push(
+                    storage::install(&tab.page, script)
+                        .await
+                        .context("Failed to register auth storage script")?,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Load a saved auth profile and inject its cookies into the session's
+    /// browser context.
+    async fn restore_cookies(
+        &self,
+        state: &BrowserState,
+        profile_name: &str,
+        domain: Option<&str>,
+    ) -> anyhow::Result<auth::AuthProfile> {
+        let profile = self.auth_store.load(profile_name, domain)?;
+        let browser = state.browser.as_ref().context("No browser")?;
+        set_cookies(browser, state.context.clone(), &profile.cookies).await?;
+        Ok(profile)
     }
 
     async fn do_list_auth(&self, params: ListAuthParams) -> anyhow::Result<String> {
         let summaries = self.auth_store.list(params.domain.as_deref())?;
+        let now = auth::now_secs();
         if summaries.is_empty() {
             return Ok("No auth profiles found.".into());
         }
@@ -1775,79 +3626,1169 @@ impl CortexBrowserServer {
         let lines: Vec<String> = summaries
             .iter()
             .map(|s| {
-                format!(
-                    "  {} [{}] ({} cookies)",
-                    s.profile, s.domain, s.cookie_count
-                )
+                if s.locked {
+                    format!("  {} [{}] (encrypted, key not set)", s.profile, s.domain)
+                } else {
+                    let mut storage = match s.storage_origins {
+                        0 => String::new(),
+                        n => format!(", web storage for {n} origin(s)"),
+                    };
+                    if s.has_totp {
+                        storage.push_str(", TOTP");
+                    }
+                    format!(
+                        "  {} [{}] ({} cookies{}{})",
+                        s.profile,
+                        s.domain,
+                        s.cookie_count,
+                        storage,
+                        expiry_note(s, now)
+                    )
+                }
             })
             .collect();
 
-        Ok(format!("Auth profiles:\n{}", lines.join("\n")))
+        let mut text = format!("Auth profiles:\n{}", lines.join("\n"));
+        let bindings = self.auth_store.bindings()?;
+        if !bindings.is_empty() {
+            text.push_str("\nRestored automatically (bind_auth):");
+            for (host, binding) in &bindings {
+                text.push_str(&format!(
+                    "\n  {host} -> {} [{}]",
+                    binding.profile, binding.domain
+                ));
+            }
+        }
+        Ok(text)
     }
 
-    async fn do_delete_auth(&self, params: DeleteAuthParams) -> anyhow::Result<String> {
-        self.auth_store
-            .delete(&params.profile, params.domain.as_deref())?;
-        Ok(format!("Auth profile '{}' deleted.", params.profile))
-    }
-}
+    async fn do_verify_auth(&self, params: VerifyAuthParams) -> anyhow::Result<String> {
+        info!(profile = %params.profile, "verify_auth");
+        let profile = self
+            .auth_store
+            .load(&params.profile, params.domain.as_deref())?;
+        let configured = self
+            .probes
+            .get(&profile.profile)
+            .or_else(|| self.probes.get(&profile.domain))
+            .cloned()
+            .unwrap_or_default();
+        let probe = verify::AuthProbe {
+            url: params.url,
+            logged_in: params.logged_in,
+            logged_out: params.logged_out,
+        }
+        .or(&configured);
+        probe.validate()?;
+        let url = probe.url.clone().unwrap_or_else(|| profile.url.clone());
+        self.check_policy(&url)?;
 
-pub async fn run_mcp_server(launch: bool, port: u16) -> anyhow::Result<()> {
-    info!(
-        launch = launch,
-        port = port,
-        "starting MCP server over stdio"
-    );
-    let server = CortexBrowserServer::new(launch, port);
+        self.ensure_browser().await?;
+        let browser = {
+            let state = self.state.read().await;
+            state.browser.clone().context("No browser")?
+        };
+        let context = browser::create_context(&browser, &self.browser_options.proxy).await?;
+        let snapshot = self
+            .probe_snapshot(&browser, &context, &profile, &url)
+            .await;
+        if let Err(e) = browser.dispose_browser_context(context).await {
+            warn!(error = %e, "failed to dispose verify_auth context");
+        }
+        let snapshot = snapshot?;
 
-    let service = server
-        .serve(rmcp::transport::stdio())
-        .await
-        .context("Failed to start MCP server")?;
+        let now = auth::now_secs();
+        let summary = auth::AuthSummary::at(&profile, now);
+        let (state, reason) = match verify::judge(&probe, &snapshot)? {
+            verify::Verdict::LoggedIn(reason) => ("LOGGED IN", reason),
+            verify::Verdict::LoggedOut(reason) => ("LOGGED OUT", reason),
+        };
+        info!(profile = %profile.profile, verdict = state, url = %snapshot.url, "verify_auth done");
+        Ok(format!(
+            "Auth profile '{}': {} at {} ({}).\nCookies: {}{}",
+            profile.profile,
+            state,
+            snapshot.url,
+            reason,
+            summary.cookie_count,
+            expiry_note(&summary, now)
+        ))
+    }
 
-    info!("MCP server running, waiting for requests");
-    service.waiting().await?;
-    info!("MCP server shut down");
-    Ok(())
+    /// Load `url` in a fresh page of `context` with `profile` applied and
+    /// return its snapshot.
+    async fn probe_snapshot(
+        &self,
+        browser: &Browser,
+        context: &BrowserContextId,
+        profile: &auth::AuthProfile,
+        url: &str,
+    ) -> anyhow::Result<crate::dom::PageSnapshot> {
+        set_cookies(browser, Some(context.clone()), &profile.cookies).await?;
+        let mut params = CreateTargetParams::new("about:blank");
+        params.browser_context_id = Some(context.clone());
+        let page = browser
+            .new_page(params)
+            .await
+            .context("Failed to create page")?;
+
+        let stats = Arc::new(intercept::InterceptStats::default());
+        intercept::attach(&page, self.intercept_rules.clone(), stats.clone()).await?;
+        let rules = self.current_rules();
+        if rules.is_active() {
+            intercept::sync(&page, &rules)
+                .await
+                .context("Failed to enable request interception")?;
+        }
+        let headers = self.current_headers();
+        if !headers.is_empty() {
+            proxy::apply_headers(&page, &headers)
+                .await
+                .context("Failed to set extra headers")?;
+        }
+        if let Some(script) = storage::restore_script(profile) {
+            storage::install(&page, &script)
+                .await
+                .context("Failed to register auth storage script")?;
+        }
+
+        if let Err(e) = page.goto(url).await {
+            policy_violation(&stats)?;
+            return Err(e).with_context(|| format!("Failed to load {url}"));
+        }
+        page.wait_for_navigation().await.ok();
+        policy_violation(&stats)?;
+
+        let html = page.content().await.context("Failed to get page content")?;
+        let final_url = page
+            .url()
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| url.to_string());
+        Ok(pipeline::process_with_refs(&html, &final_url).snapshot)
+    }
+
+    fn do_bind_auth(&self, params: BindAuthParams) -> anyhow::Result<String> {
+        info!(host = %params.host, profile = %params.profile, "bind_auth");
+        let binding =
+            self.auth_store
+                .bind(&params.host, &params.profile, params.domain.as_deref())?;
+        Ok(format!(
+            "Auth profile '{}' [{}] will be restored automatically when navigating to {}.",
+            binding.profile,
+            binding.domain,
+            params.host.trim().to_ascii_lowercase()
+        ))
+    }
+
+    fn do_unbind_auth(&self, params: UnbindAuthParams) -> anyhow::Result<String> {
+        info!(host = %params.host, "unbind_auth");
+        if !self.auth_store.unbind(&params.host)? {
+            anyhow::bail!("No auth profile is bound to {}", params.host);
+        }
+        Ok(format!(
+            "Removed the auth binding for {}. Profiles already restored this session stay applied.",
+            params.host
+        ))
+    }
+
+    fn do_totp_code(&self, params: TotpCodeParams) -> anyhow::Result<String> {
+        info!(profile = %params.profile, "totp_code");
+        let (code, remaining) = self
+            .auth_store
+            .totp_code(&params.profile, params.domain.as_deref())?;
+        Ok(format!("{code} (valid for {remaining}s)"))
+    }
+
+    fn do_import_auth(&self, params: ImportAuthParams) -> anyhow::Result<String> {
+        info!(path = %params.path, profile = %params.profile, "import_auth");
+        let format = params
+            .format
+            .as_deref()
+            .map(str::parse::<interchange::Format>)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let profile = interchange::import_file(
+            &self.auth_store,
+            &access::client_path(&interchange::exports_dir(), &params.path)?,
+            &params.profile,
+            format,
+            params.url.as_deref(),
+        )?;
+        let mut text = format!(
+            "Imported {} cookie(s) as auth profile '{}' [{}]",
+            profile.cookies.len(),
+            profile.profile,
+            profile.domain
+        );
+        if !profile.origins.is_empty() {
+            text.push_str(&format!(
+                " with web storage for {} origin(s)",
+                profile.origins.len()
+            ));
+        }
+        text.push_str(". Use restore_auth to apply it.");
+        Ok(text)
+    }
+
+    fn do_export_auth(&self, params: ExportAuthParams) -> anyhow::Result<String> {
+        info!(path = %params.path, profile = %params.profile, "export_auth");
+        let format = match params.format.as_deref() {
+            Some(format) => format.parse().map_err(|e: String| anyhow::anyhow!(e))?,
+            None => interchange::Format::StorageState,
+        };
+        let path = access::client_path(&interchange::exports_dir(), &params.path)?;
+        let profile = interchange::export_file(
+            &self.auth_store,
+            &params.profile,
+            params.domain.as_deref(),
+            &path,
+            format,
+            params.overwrite.unwrap_or(false),
+        )?;
+        Ok(format!(
+            "Exported auth profile '{}' ({} cookie(s)) to {} as {format}.",
+            profile.profile,
+            profile.cookies.len(),
+            path.display()
+        ))
+    }
+
+    async fn do_delete_auth(&self, params: DeleteAuthParams) -> anyhow::Result<String> {
+        self.auth_store
+            .delete(&params.profile, params.domain.as_deref())?;
+        Ok(format!("Auth profile '{}' deleted.", params.profile))
+    }
+
+    async fn do_network_log(&self, params: NetworkLogParams) -> anyhow::Result<String> {
+        debug!(url_pattern = ?params.url_pattern, resource_type = ?params.resource_type, "network_log");
+        let state = self.state.read().await;
+        let tab = state.active_tab()?;
+        let mut log = tab.network.lock().unwrap_or_else(|e| e.into_inner());
+
+        let filter = network::NetworkFilter {
+            url_pattern: params.url_pattern,
+            resource_type: params.resource_type,
+        };
+        let matched = log.filter(&filter);
+        let total = matched.len();
+        let limit = params.limit.unwrap_or(50);
+        let shown = &matched[total.saturating_sub(limit)..];
+
+        let text = if shown.is_empty() {
+            "No matching network requests.".to_string()
+        } else if shown.len() < total {
+            format!(
+                "Network requests (last {} of {}):\n{}",
+                shown.len(),
+                total,
+                network::format_entries(shown)
+            )
+        } else {
+            format!(
+                "Network requests ({}):\n{}",
+                total,
+                network::format_entries(shown)
+            )
+        };
+
+        if params.clear.unwrap_or(false) {
+            log.clear();
+        }
+        Ok(text)
+    }
+
+    async fn do_get_response_body(&self, params: ResponseBodyParams) -> anyhow::Result<String> {
+        debug!(request_id = %params.request_id, "get_response_body");
+        let request_id = params.request_id.trim_start_matches('#');
+        let state = self.state.read().await;
+        let tab = state.active_tab()?;
+
+        let entry = tab
+            .network
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(request_id)
+            .cloned()
+            .with_context(|| {
+                format!("Unknown request ID {request_id}. Use network_log to list requests.")
+            })?;
+
+        let resp = tab
+            .page
+            .execute(
+                chromiumoxide::cdp::browser_protocol::network::GetResponseBodyParams::new(
+                    request_id.to_string(),
+                ),
+            )
+            .await
+            .context("Response body not available (request pending, failed, or evicted)")?;
+
+        let body = if resp.base64_encoded {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(&resp.body)
+                .context("Failed to decode response body")?;
+            match String::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(e) => format!("(binary body, {} bytes)", e.as_bytes().len()),
+            }
+        } else {
+            resp.result.body
+        };
+
+        let max_chars = params.max_chars.unwrap_or(20_000);
+        let char_count = body.chars().count();
+        let body = if char_count > max_chars {
+            let truncated: String = body.chars().take(max_chars).collect();
+            format!("{truncated}\n... (truncated, {char_count} chars total)")
+        } else {
+            body
+        };
+
+        Ok(format!(
+            "{}\n---\n{}",
+            network::format_entries(&[&entry]),
+            body
+        ))
+    }
+
+    async fn do_set_blocking(&self, params: SetBlockingParams) -> anyhow::Result<String> {
+        let rules = {
+            let mut rules = self
+                .intercept_rules
+                .write()
+                .unwrap_or_else(|e| e.into_inner());
+            let blocklist = &mut rules.blocklist;
+            if params.clear {
+                *blocklist = blocking::BlockList::default();
+            }
+            if let Some(patterns) = params.patterns {
+                blocklist.url_patterns = patterns;
+            }
+            if let Some(hosts) = params.hosts {
+                blocklist.hosts = hosts.into_iter().map(|h| h.to_lowercase()).collect();
+            }
+            if let Some(types) = params.resource_types {
+                blocklist.resource_types = types;
+            }
+            if let Some(trackers) = params.trackers {
+                blocklist.trackers = trackers;
+            }
+            rules.clone()
+        };
+        info!(rules = %rules.blocklist.describe(), "set_blocking");
+
+        let stats = self.sync_interception(&rules).await?;
+        let mut lines = vec![format!("Request {}.", rules.blocklist.describe())];
+        for (id, tab_stats) in stats {
+            lines.push(format!(
+                "  Tab {id}: {} request(s) blocked",
+                tab_stats.blocked()
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    async fn do_route(&self, params: RouteParams) -> anyhow::Result<String> {
+        info!(url_pattern = %params.url_pattern, remove = params.remove, "route");
+        let rules = {
+            let mut rules = self
+                .intercept_rules
+                .write()
+                .unwrap_or_else(|e| e.into_inner());
+            if params.remove {
+                if !rules.routes.remove(&params.url_pattern) {
+                    anyhow::bail!("No route for '{}'", params.url_pattern);
+                }
+            } else {
+                let route = routes::Route {
+                    url_pattern: params.url_pattern.clone(),
+                    file: params.file.map(std::path::PathBuf::from),
+                    body: params.body,
+                    status: params.status.unwrap_or(200),
+                    headers: params.headers,
+                    content_type: params.content_type,
+                };
+                // Fail now rather than on the first matching request
+                rules.routes.resolve(&route)?;
+                rules.routes.add(route);
+            }
+            rules.clone()
+        };
+
+        let stats = self.sync_interception(&rules).await?;
+        let action = if params.remove { "removed" } else { "added" };
+        let mut lines = vec![format!(
+            "Route '{}' {action}. Active: {}.",
+            params.url_pattern,
+            rules.routes.describe()
+        )];
+        for (id, tab_stats) in stats {
+            lines.push(format!(
+                "  Tab {id}: {} request(s) mocked",
+                tab_stats.fulfilled()
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    async fn do_emulate(&self, params: EmulateParams) -> anyhow::Result<String> {
+        let viewport = match (params.width, params.height) {
+            (Some(width), Some(height)) => Some(browser::Size { width, height }),
+            (None, None) => None,
+            _ => anyhow::bail!("width and height must be given together"),
+        };
+        let geolocation = match (params.latitude, params.longitude) {
+            (Some(latitude), Some(longitude)) => Some(emulation::Geolocation {
+                latitude,
+                longitude,
+                accuracy: params.accuracy,
+            }),
+            (None, None) => None,
+            _ => anyhow::bail!("latitude and longitude must be given together"),
+        };
+        let update = emulation::Emulation {
+            device: params.device,
+            viewport,
+            device_scale_factor: params.device_scale_factor,
+            mobile: params.mobile,
+            touch: params.touch,
+            user_agent: params.user_agent,
+            locale: params.locale,
+            timezone: params.timezone,
+            geolocation,
+            color_scheme: params.color_scheme,
+            reduced_motion: false,
+        };
+
+        let emulation = {
+            let mut current = self.emulation.write().unwrap_or_else(|e| e.into_inner());
+            let mut next = if params.reset {
+                emulation::Emulation::default()
+            } else {
+                current.clone()
+            };
+            next.merge(update);
+            if let Some(reduced_motion) = params.reduced_motion {
+                next.reduced_motion = reduced_motion;
+            }
+            next.validate()?;
+            *current = next.clone();
+            next
+        };
+        let summary = emulation.describe().unwrap_or_else(|| "off".into());
+        info!(emulation = %summary, "emulate");
+
+        let base_viewport = self.browser_options.base_viewport();
+        let mut state = self.state.write().await;
+        let mut tab_ids: Vec<u32> = state.tabs.keys().copied().collect();
+        tab_ids.sort();
+        for id in &tab_ids {
+            let tab = state.tabs.get_mut(id).context("Tab disappeared")?;
+            emulation::apply(&tab.page, &emulation, base_viewport)
+                .await
+                .with_context(|| format!("Failed to apply emulation to tab {id}"))?;
+            tab.cached_snapshot = None;
+        }
+
+        Ok(format!(
+            "Emulation: {summary}. Applied to {} tab(s); new tabs inherit it. \
+             Navigate again for user agent and locale changes to reach server-rendered content.",
+            tab_ids.len()
+        ))
+    }
+
+    async fn do_set_headers(&self, params: SetHeadersParams) -> anyhow::Result<String> {
+        for (name, value) in &params.headers {
+            proxy::validate_header(name, value).map_err(anyhow::Error::msg)?;
+        }
+        let headers = {
+            let mut current = self
+                .extra_headers
+                .write()
+                .unwrap_or_else(|e| e.into_inner());
+            if params.clear {
+                current.clear();
+            }
+            proxy::merge_headers(&mut current, params.headers);
+            current.clone()
+        };
+        let summary = proxy::describe_headers(&headers);
+        info!(headers = %summary, "set_headers");
+
+        let state = self.state.read().await;
+        let mut tab_ids: Vec<u32> = state.tabs.keys().copied().collect();
+        tab_ids.sort();
+        for id in &tab_ids {
+            proxy::apply_headers(&state.tabs[id].page, &headers)
+                .await
+                .with_context(|| format!("Failed to set headers on tab {id}"))?;
+        }
+
+        Ok(format!(
+            "Extra headers: {summary}. Applied to {} tab(s); new tabs inherit them.",
+            tab_ids.len()
+        ))
+    }
+
+    async fn do_set_proxy_auth(&self, params: SetProxyAuthParams) -> anyhow::Result<String> {
+        let credentials = if params.clear {
+            None
+        } else {
+            let username = params
+                .username
+                .filter(|u| !u.is_empty())
+                .context("username is required (or pass clear:true)")?;
+            Some(proxy::Credentials {
+                username,
+                password: proxy::Secret::new(params.password.unwrap_or_default()),
+            })
+        };
+        let text = match &credentials {
+            Some(c) => format!(
+                "Proxy auth challenges will be answered as '{}'.",
+                c.username
+            ),
+            None => "Proxy credentials cleared.".into(),
+        };
+        info!(username = ?credentials.as_ref().map(|c| &c.username), "set_proxy_auth");
+
+        let rules = {
+            let mut rules = self
+                .intercept_rules
+                .write()
+                .unwrap_or_else(|e| e.into_inner());
+            rules.proxy_auth = credentials;
+            rules.clone()
+        };
+        self.sync_interception(&rules).await?;
+        Ok(text)
+    }
+
+    /// Run a click or type_text, first asking a human if a confirm rule
+    /// matches. Without elicitation support the action is queued and a
+    /// PENDING message is returned instead.
+    async fn run_with_approval(
+        &self,
+        peer: &Peer<RoleServer>,
+        action: confirm::PendingKind,
+    ) -> anyhow::Result<String> {
+        if let Some((tab_id, locator, summary, reason)) = self.approval_needed(&action).await? {
+            warn!(action = %summary, reason = %reason, "action needs approval");
+            if peer
+                .supported_elicitation_modes()
+                .contains(&ElicitationMode::Form)
+            {
+                self.elicit_approval(peer, &summary, &reason).await?;
+            } else {
+                let id = self.pending.lock().unwrap_or_else(|e| e.into_inner()).push(
+                    tab_id,
+                    action,
+                    locator,
+                    summary.clone(),
+                    reason.clone(),
+                );
+                return Ok(format!(
+                    "PENDING #{id}: {summary} needs human approval ({reason}). \
+                     Ask the user, then call approve with id {id} to run it or reject to drop it."
+                ));
+            }
+        }
+        self.run_action(action).await
+    }
+
+    /// The active tab, the element, a summary and the reason if `action`
+    /// needs approval.
+    async fn approval_needed(
+        &self,
+        action: &confirm::PendingKind,
+    ) -> anyhow::Result<Option<(u32, Option<crate::dom::ElementLocator>, String, String)>> {
+        if !self.confirm.is_active() {
+            return Ok(None);
+        }
+        if let confirm::PendingKind::PressKey { key, .. } = action {
+            if !confirm::submits_on(key) {
+                return Ok(None);
+            }
+        }
+        let locator = match action.ref_id() {
+            Some(ref_id) => {
+                let state = self.state.read().await;
+                let locator = state
+                    .active_tab()?
+                    .ref_index
+                    .get(&ref_id)
+                    .with_context(|| format!("Unknown ref @e{ref_id}"))?
+                    .clone();
+                Some(locator)
+            }
+            None => None,
+        };
+        Ok(self
+            .approval_reason(action.kind(), locator.as_ref())
+            .await?
+            .map(|(tab_id, host, reason)| {
+                let summary = format!("{} on {host}", confirm::summarize(action, locator.as_ref()));
+                (tab_id, locator, summary, reason)
+            }))
+    }
+
+    /// The active tab, its host and the reason if `kind` on `locator` needs
+    /// approval. `locator` is `None` for a key pressed in the focused element.
+    async fn approval_reason(
+        &self,
+        kind: confirm::ActionKind,
+        locator: Option<&crate::dom::ElementLocator>,
+    ) -> anyhow::Result<Option<(u32, String, String)>> {
+        if !self.confirm.is_active() {
+            return Ok(None);
+        }
+        let state = self.state.read().await;
+        let tab = state.active_tab()?;
+        let host = network::url_host(&tab.current_url).to_ascii_lowercase();
+        let submits_js = match (kind, locator) {
+            (confirm::ActionKind::Click, Some(locator)) => Some(locator.submits_form_js()),
+            (confirm::ActionKind::PressKey, locator) => Some(crate::dom::enter_submits_js(locator)),
+            _ => None,
+        };
+        let submits_form = match submits_js {
+            Some(js) if self.confirm.watches_submits(&host) => tab
+                .page
+                .evaluate(js)
+                .await
+                .ok()
+                .and_then(|v| v.into_value::<String>().ok())
+                .is_some_and(|v| v == "true"),
+            _ => false,
+        };
+        let reason = match locator {
+            Some(locator) => self.confirm.reason(kind, locator, &host, submits_form),
+            None => self.confirm.submit_reason(&host, submits_form),
+        };
+        Ok(reason.map(|reason| (state.active_tab, host, reason)))
+    }
+
+    /// Ask the user through MCP elicitation; fail unless they approve.
+    async fn elicit_approval(
+        &self,
+        peer: &Peer<RoleServer>,
+        summary: &str,
+        reason: &str,
+    ) -> anyhow::Result<()> {
+        let message = format!("The agent wants to {summary} ({reason}). Allow it?");
+        match peer
+            .elicit_with_timeout::<Approval>(message, Some(APPROVAL_TIMEOUT))
+            .await
+        {
+            Ok(Some(Approval { approve: true })) => {
+                info!(action = %summary, "action approved");
+                Ok(())
+            }
+            Ok(_) | Err(ElicitationError::UserDeclined) | Err(ElicitationError::UserCancelled) => {
+                warn!(action = %summary, "action rejected");
+                anyhow::bail!("The user did not approve: {summary}")
+            }
+            Err(e) => anyhow::bail!("Could not get approval for {summary}: {e}"),
+        }
+    }
+
+    async fn run_action(&self, action: confirm::PendingKind) -> anyhow::Result<String> {
+        match action {
+            confirm::PendingKind::Click {
+                ref_id,
+                return_diff,
+            } => self.do_click(ref_id, return_diff).await,
+            confirm::PendingKind::TypeText {
+                ref_id,
+                text,
+                variable,
+                return_diff,
+            } => {
+                self.do_type_text(ref_id, text.expose(), variable.as_deref(), return_diff)
+                    .await
+            }
+            confirm::PendingKind::PressKey { key, ref_id } => self.do_press_key(&key, ref_id).await,
+        }
+    }
+
+    async fn do_approve(&self, id: u32) -> anyhow::Result<String> {
+        let pending = self.pending_action(id)?;
+        let current = {
+            let state = self.state.read().await;
+            if state.active_tab != pending.tab_id {
+                anyhow::bail!(
+                    "Action #{id} was requested on tab {}. Switch back to it before approving.",
+                    pending.tab_id
+                );
+            }
+            pending
+                .action
+                .ref_id()
+                .and_then(|ref_id| state.active_tab().ok()?.ref_index.get(&ref_id).cloned())
+        };
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take(id);
+        if let (Some(ref_id), Some(locator)) = (pending.action.ref_id(), &pending.locator) {
+            if current.as_ref() != Some(locator) {
+                warn!(id = id, action = %pending.summary, "pending action target changed");
+                anyhow::bail!(
+                    "Action #{id} was dropped: @e{ref_id} no longer refers to the element it was requested on \
+                     (the page changed). Take a fresh snapshot and retry the action."
+                );
+            }
+        }
+        info!(id = id, action = %pending.summary, "pending action approved");
+        let result = self.run_action(pending.action).await?;
+        Ok(format!("Approved #{id}: {}.\n\n{result}", pending.summary))
+    }
+
+    fn do_reject(&self, id: u32) -> anyhow::Result<String> {
+        let pending = self.pending_action(id)?;
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take(id);
+        info!(id = id, action = %pending.summary, "pending action rejected");
+        Ok(format!(
+            "Rejected #{id}: {}. It will not run.",
+            pending.summary
+        ))
+    }
+
+    /// Look up a queued action, listing the others when `id` is unknown.
+    fn pending_action(&self, id: u32) -> anyhow::Result<confirm::PendingAction> {
+        let queue = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(pending) = queue.get(id) {
+            return Ok(pending.clone());
+        }
+        let waiting: Vec<String> = queue
+            .list()
+            .iter()
+            .map(|p| format!("#{} {}", p.id, p.summary))
+            .collect();
+        if waiting.is_empty() {
+            anyhow::bail!("No pending action #{id}. Nothing is waiting for approval.");
+        }
+        anyhow::bail!("No pending action #{id}. Waiting: {}", waiting.join(", "))
+    }
+
+    fn do_start_har(&self, params: StartHarParams) -> anyhow::Result<String> {
+        let mut har = self.har.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(active) = har.as_ref() {
+            anyhow::bail!(
+                "A HAR capture is already running ({}). Stop it first.",
+                active.path.display()
+            );
+        }
+        let path = access::client_path(
+            &har::har_dir(),
+            &params
+                .path
+                .unwrap_or_else(|| format!("{}.har", recording::now_timestamp())),
+        )?;
+        let overwrite = params.overwrite.unwrap_or(false);
+        if path.exists() && !overwrite {
+            anyhow::bail!(
+                "{} already exists. Pass overwrite to replace it.",
+                path.display()
+            );
+        }
+        info!(path = %path.display(), "HAR capture started");
+        let text = format!(
+            "HAR capture started. Requests from all tabs will be written to {} when stop_har is called.",
+            path.display()
+        );
+        *har = Some(har::HarRecorder::new(path, overwrite));
+        Ok(text)
+    }
+
+    fn do_stop_har(&self) -> anyhow::Result<String> {
+        let recorder = self
+            .har
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("No active HAR capture to stop"))?;
+        let path = recorder.path.clone();
+        let count = recorder.save()?;
+        info!(path = %path.display(), entries = count, "HAR capture saved");
+        Ok(format!(
+            "HAR saved: {count} request(s) → {}",
+            path.display()
+        ))
+    }
+
+    async fn do_replay_har(&self, params: ReplayHarParams) -> anyhow::Result<String> {
+        let replay = params
+            .path
+            .map(|path| har::HarReplay::load_file(&access::client_path(&har::har_dir(), &path)?))
+            .transpose()?
+            .map(Arc::new);
+        let text = match &replay {
+            Some(r) => format!(
+                "Replaying {}. Requests missing from the HAR will fail.",
+                r.describe()
+            ),
+            None => "HAR replay stopped.".into(),
+        };
+        info!(replay = %text, "replay_har");
+
+        let rules = {
+            let mut rules = self
+                .intercept_rules
+                .write()
+                .unwrap_or_else(|e| e.into_inner());
+            rules.har = replay;
+            rules.clone()
+        };
+        self.sync_interception(&rules).await?;
+        Ok(text)
+    }
+
+    /// Re-apply interception rules to every open tab. Returns each tab's
+    /// counters, ordered by tab ID.
+    async fn sync_interception(
+        &self,
+        rules: &intercept::InterceptRules,
+    ) -> anyhow::Result<Vec<(u32, Arc<intercept::InterceptStats>)>> {
+        let state = self.state.read().await;
+        let mut tab_ids: Vec<u32> = state.tabs.keys().copied().collect();
+        tab_ids.sort();
+
+        let mut stats = Vec::new();
+        for id in tab_ids {
+            let tab = &state.tabs[&id];
+            intercept::sync(&tab.page, rules)
+                .await
+                .with_context(|| format!("Failed to update interception on tab {id}"))?;
+            stats.push((id, tab.intercept.clone()));
+        }
+        Ok(stats)
+    }
+}
+
+/// Fail with the domain policy violation `stats` recorded since it was last
+/// checked, if any.
+/// Cookie expiry for auth listings, e.g. ", 1 expired, earliest expiry 2h ago,
+/// latest in 30d" or ", EXPIRED".
+fn expiry_note(s: &auth::AuthSummary, now: f64) -> String {
+    if s.fully_expired {
+        return ", EXPIRED".into();
+    }
+    let mut note = String::new();
+    if s.expired_count > 0 {
+        note.push_str(&format!(", {} expired", s.expired_count));
+    }
+    if let (Some(earliest), Some(latest)) = (s.earliest_expiry, s.latest_expiry) {
+        note.push_str(&format!(
+            ", earliest expiry {}, latest {}",
+            auth::describe_expiry(earliest, now),
+            auth::describe_expiry(latest, now)
+        ));
+    }
+    note
+}
+
+/// Set cookies in the browser context `context` (the default context if `None`).
+async fn set_cookies(
+    browser: &Browser,
+    context: Option<BrowserContextId>,
+    cookies: &[auth::StoredCookie],
+) -> anyhow::Result<()> {
+    let cookie_params: Vec<chromiumoxide::cdp::browser_protocol::network::CookieParam> = cookies
+        .iter()
+        .map(|c| {
+            let mut param =
+                chromiumoxide::cdp::browser_protocol::network::CookieParam::new(&c.name, &c.value);
+            param.domain = Some(c.domain.clone());
+            param.path = Some(c.path.clone());
+            param.secure = Some(c.secure);
+            param.http_only = Some(c.http_only);
+            if let Some(ref ss) = c.same_site {
+                param.same_site = match ss.as_str() {
+                    "Strict" => {
+                        Some(chromiumoxide::cdp::browser_protocol::network::CookieSameSite::Strict)
+                    }
+                    "Lax" => {
+                        Some(chromiumoxide::cdp::browser_protocol::network::CookieSameSite::Lax)
+                    }
+                    "None" => {
+                        Some(chromiumoxide::cdp::browser_protocol::network::CookieSameSite::None)
+                    }
+                    _ => Option::None,
+                };
+            }
+            if let Some(exp) = c.expires {
+                param.expires =
+                    Some(chromiumoxide::cdp::browser_protocol::network::TimeSinceEpoch::new(exp));
+            }
+            param
+        })
+        .collect();
+
+    let mut params =
+        chromiumoxide::cdp::browser_protocol::storage::SetCookiesParams::new(cookie_params);
+    params.browser_context_id = context;
+    browser
+        .execute(params)
+        .await
+        .context("Failed to set cookies via CDP")?;
+    Ok(())
+}
+
+fn policy_violation(stats: &intercept::InterceptStats) -> anyhow::Result<()> {
+    match stats.take_violation() {
+        Some(violation) => anyhow::bail!("Navigation blocked by domain policy: {violation}"),
+        None => Ok(()),
+    }
+}
+
+pub async fn run_mcp_server(launch: bool, port: u16, options: ServerOptions) -> anyhow::Result<()> {
+    info!(
+        launch = launch,
+        port = port,
+        blocking = %options.blocklist.describe(),
+        routes = %options.routes.describe(),
+        har_replay = options.har_replay.is_some(),
+        har_capture = options.har_capture.is_some(),
+        proxy = ?options.browser.proxy.describe(),
+        headers = %proxy::describe_headers(&options.extra_headers),
+        policy = %options.policy.describe(),
+        confirm = %options.confirm.describe(),
+        isolate = options.isolate,
+        "starting MCP server over stdio"
+    );
+    let har_capture = options.har_capture.clone();
+    let server = CortexBrowserServer::with_options(launch, port, options);
+
+    let service = server
+        .serve(rmcp::transport::stdio())
+        .await
+        .context("Failed to start MCP server")?;
+
+    info!("MCP server running, waiting for requests");
+    service.waiting().await?;
+    info!("MCP server shut down");
+    save_har_capture(har_capture)
+}
+
+/// Settings of the HTTP transport itself.
+#[derive(Debug, Clone)]
+pub struct HttpServerOptions {
+    pub host: String,
+    pub port: u16,
+    /// Refuse new sessions with 503 while this many are active.
+    pub max_sessions: Option<usize>,
+    /// Close sessions that have been inactive this long, releasing their
+    /// browser context.
+    pub idle_timeout: Option<std::time::Duration>,
+    /// Browsers in the pool sessions lease from, when launching.
+    pub pool_size: usize,
+    /// Sessions sharing one pooled browser, each in its own context.
+    pub sessions_per_browser: usize,
+    /// Bearer token and Origin checks applied to every request.
+    pub access: access::AccessPolicy,
+    /// PEM certificate and private key to serve HTTPS with.
+    pub tls: Option<(std::path::PathBuf, std::path::PathBuf)>,
+}
+
+impl Default for HttpServerOptions {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".into(),
+            port: 8080,
+            max_sessions: None,
+            idle_timeout: Some(std::time::Duration::from_secs(30 * 60)),
+            pool_size: 1,
+            sessions_per_browser: 8,
+            access: access::AccessPolicy::default(),
+            tls: None,
+        }
+    }
 }
 
+/// Serve MCP over HTTP. Every session gets its own server and browser context.
+/// With `launch`, contexts are leased from a shared pool of browsers rather
+/// than starting one Chrome per session.
 pub async fn run_mcp_http_server(
     launch: bool,
     port: u16,
-    host: &str,
-    http_port: u16,
+    http: HttpServerOptions,
+    options: ServerOptions,
 ) -> anyhow::Result<()> {
+    use axum::response::IntoResponse;
     use rmcp::transport::streamable_http_server::{
-        session::local::LocalSessionManager, StreamableHttpServerConfig, StreamableHttpService,
+        session::local::{LocalSessionManager, SessionConfig},
+        StreamableHttpServerConfig, StreamableHttpService,
     };
     use tokio_util::sync::CancellationToken;
 
     let ct = CancellationToken::new();
+    let har_capture = options.har_capture.clone();
+    let pool = launch.then(|| {
+        Arc::new(pool::BrowserPool::new(
+            options.browser.clone(),
+            http.pool_size,
+            http.sessions_per_browser,
+        ))
+    });
+    let options = ServerOptions {
+        isolate: true,
+        pool: pool.clone(),
+        ..options
+    };
+    let sessions = Arc::new(LocalSessionManager {
+        sessions: Default::default(),
+        session_config: SessionConfig {
+            keep_alive: http.idle_timeout,
+            ..Default::default()
+        },
+    });
 
     let service: StreamableHttpService<CortexBrowserServer, LocalSessionManager> =
         StreamableHttpService::new(
-            move || Ok(CortexBrowserServer::new(launch, port)),
-            Arc::new(LocalSessionManager::default()),
+            move || {
+                Ok(CortexBrowserServer::with_options(
+                    launch,
+                    port,
+                    options.clone(),
+                ))
+            },
+            sessions.clone(),
             StreamableHttpServerConfig {
                 cancellation_token: ct.child_token(),
                 ..Default::default()
             },
         );
 
-    let router = axum::Router::new().nest_service("/mcp", service);
+    let mut router = axum::Router::new().nest_service("/mcp", service);
+    if let Some(max) = http.max_sessions {
+        router = router.layer(axum::middleware::from_fn(
+            move |request: axum::extract::Request, next: axum::middleware::Next| {
+                let sessions = sessions.clone();
+                async move {
+                    let is_new_session = request.method() == axum::http::Method::POST
+                        && !request.headers().contains_key("mcp-session-id");
+                    if is_new_session && sessions.sessions.read().await.len() >= max {
+                        warn!(max_sessions = max, "session limit reached, refusing new session");
+                        return (
+                            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                            format!(
+                                "Session limit reached ({max} active). Close an existing session or raise --max-sessions."
+                            ),
+                        )
+                            .into_response();
+                    }
+                    next.run(request).await
+                }
+            },
+        ));
+    }
+
+    // Added last so it runs first: refused requests never reach the session limit.
+    let policy = Arc::new(http.access.clone());
+    router = router.layer(axum::middleware::from_fn(
+        move |request: axum::extract::Request, next: axum::middleware::Next| {
+            let policy = policy.clone();
+            async move {
+                let checked = {
+                    let headers = request.headers();
+                    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+                    policy.check(
+                        header(axum::http::header::AUTHORIZATION),
+                        header(axum::http::header::ORIGIN),
+                    )
+                };
+                match checked {
+                    Ok(()) => next.run(request).await,
+                    Err(denied) => {
+                        warn!(reason = %denied.message(), "refused MCP HTTP request");
+                        let status = axum::http::StatusCode::from_u16(denied.status())
+                            .unwrap_or(axum::http::StatusCode::FORBIDDEN);
+                        let mut response = (status, denied.message()).into_response();
+                        if status == axum::http::StatusCode::UNAUTHORIZED {
+                            response.headers_mut().insert(
+                                axum::http::header::WWW_AUTHENTICATE,
+                                axum::http::HeaderValue::from_static("Bearer"),
+                            );
+                        }
+                        response
+                    }
+                }
+            }
+        },
+    ));
 
-    let bind_addr = format!("{host}:{http_port}");
+    let bind_addr = format!("{}:{}", http.host, http.port);
     let listener = tokio::net::TcpListener::bind(&bind_addr)
         .await
         .with_context(|| format!("Failed to bind to {bind_addr}"))?;
+    let exposed = !listener.local_addr()?.ip().is_loopback();
+    if exposed && http.access.token.is_none() {
+        warn!(addr = %bind_addr, "MCP HTTP server is reachable from the network without --token");
+    }
+    let scheme = if http.tls.is_some() { "https" } else { "http" };
 
-    info!(addr = %bind_addr, "MCP HTTP server listening on http://{bind_addr}/mcp");
+    info!(
+        addr = %bind_addr,
+        max_sessions = ?http.max_sessions,
+        idle_timeout = ?http.idle_timeout,
+        pool = ?pool,
+        token = http.access.token.is_some(),
+        "MCP HTTP server listening on {scheme}://{bind_addr}/mcp"
+    );
 
-    axum::serve(listener, router)
-        .with_graceful_shutdown(async move { ct.cancelled().await })
-        .await
-        .context("HTTP server error")?;
+    match &http.tls {
+        Some((cert, key)) => {
+            let config = axum_server::tls_rustls::RustlsConfig::from_pem_file(cert, key)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to load TLS certificate {} and key {}",
+                        cert.display(),
+                        key.display()
+                    )
+                })?;
+            let handle = axum_server::Handle::new();
+            tokio::spawn({
+                let handle = handle.clone();
+                async move {
+                    ct.cancelled().await;
+                    handle.graceful_shutdown(None);
+                }
+            });
+            axum_server::from_tcp_rustls(listener.into_std()?, config)
+                .handle(handle)
+                .serve(router.into_make_service())
+                .await
+                .context("HTTPS server error")?;
+        }
+        None => {
+            axum::serve(listener, router)
+                .with_graceful_shutdown(async move { ct.cancelled().await })
+                .await
+                .context("HTTP server error")?;
+        }
+    }
 
     info!("MCP HTTP server shut down");
+    if let Some(pool) = pool {
+        pool.shutdown().await;
+    }
+    save_har_capture(har_capture)
+}
+
+/// Write the `--har` capture on shutdown, unless `stop_har` already did.
+fn save_har_capture(capture: Option<har::SharedHarRecorder>) -> anyhow::Result<()> {
+    let Some(recorder) = capture.and_then(|c| c.lock().unwrap_or_else(|e| e.into_inner()).take())
+    else {
+        return Ok(());
+    };
+    let path = recorder.path.clone();
+    let count = recorder.save()?;
+    info!(path = %path.display(), entries = count, "HAR capture saved");
     Ok(())
 }
@@ -0,0 +1,307 @@
+//! Per-tab network request log.
+//!
+//! Every tab subscribes to the CDP Network domain events and keeps a bounded
+//! log of the requests its page made (method, URL, status, resource type,
+//! timing). Agents can filter the log and fetch response bodies directly,
+//! which is usually cheaper than scraping the rendered result of an API call.
+
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::network::{
+    EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, EventResponseReceived,
+};
+use chromiumoxide::Page;
+use futures::StreamExt;
+
+/// Maximum number of entries kept per tab. Oldest entries are dropped first.
+const MAX_ENTRIES: usize = 500;
+
+/// A single network request observed on a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkEntry {
+    pub request_id: String,
+    pub method: String,
+    pub url: String,
+    pub resource_type: String,
+    pub status: Option<i64>,
+    pub mime_type: Option<String>,
+    /// Monotonic timestamp (seconds) at which the request was sent.
+    pub started_at: f64,
+    pub duration_ms: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// Criteria for narrowing down the network log.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkFilter {
+    /// URL glob (`*` matches any run of characters). Without `*`, a plain
+    /// substring match is used.
+    pub url_pattern: Option<String>,
+    /// CDP resource type, case-insensitive (e.g. "xhr", "fetch", "document").
+    pub resource_type: Option<String>,
+}
+
+impl NetworkFilter {
+    pub fn matches(&self, entry: &NetworkEntry) -> bool {
+        if let Some(pattern) = &self.url_pattern {
+            if !url_matches(pattern, &entry.url) {
+                return false;
+            }
+        }
+        if let Some(rt) = &self.resource_type {
+            if !entry.resource_type.eq_ignore_ascii_case(rt) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Bounded, insertion-ordered log of network requests for one tab.
+#[derive(Debug, Default)]
+pub struct NetworkLog {
+    entries: Vec<NetworkEntry>,
+}
+
+pub type SharedNetworkLog = Arc<Mutex<NetworkLog>>;
+
+impl NetworkLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn shared() -> SharedNetworkLog {
+        Arc::new(Mutex::new(Self::new()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Record a request being sent. A request ID that is already present
+    /// (a redirect hop) starts a fresh entry; the previous hop keeps its
+    /// redirect status.
+    pub fn on_request(
+        &mut self,
+        request_id: &str,
+        method: &str,
+        url: &str,
+        resource_type: &str,
+        timestamp: f64,
+    ) {
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.entries.push(NetworkEntry {
+            request_id: request_id.to_string(),
+            method: method.to_string(),
+            url: url.to_string(),
+            resource_type: resource_type.to_string(),
+            status: None,
+            mime_type: None,
+            started_at: timestamp,
+            duration_ms: None,
+            error: None,
+        });
+    }
+
+    /// Record the status of a redirect hop before the follow-up request.
+    pub fn on_redirect(&mut self, request_id: &str, status: i64, timestamp: f64) {
+        if let Some(entry) = self.latest_mut(request_id) {
+            entry.status = Some(status);
+            entry.duration_ms = Some(elapsed_ms(entry.started_at, timestamp));
+        }
+    }
+
+    pub fn on_response(&mut self, request_id: &str, status: i64, mime_type: &str) {
+        if let Some(entry) = self.latest_mut(request_id) {
+            entry.status = Some(status);
+            entry.mime_type = Some(mime_type.to_string());
+        }
+    }
+
+    pub fn on_finished(&mut self, request_id: &str, timestamp: f64) {
+        if let Some(entry) = self.latest_mut(request_id) {
+            entry.duration_ms = Some(elapsed_ms(entry.started_at, timestamp));
+        }
+    }
+
+    pub fn on_failed(&mut self, request_id: &str, error: &str, timestamp: f64) {
+        if let Some(entry) = self.latest_mut(request_id) {
+            entry.error = Some(error.to_string());
+            entry.duration_ms = Some(elapsed_ms(entry.started_at, timestamp));
+        }
+    }
+
+    /// Most recent entry for a request ID.
+    pub fn get(&self, request_id: &str) -> Option<&NetworkEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|e| e.request_id == request_id)
+    }
+
+    pub fn filter(&self, filter: &NetworkFilter) -> Vec<&NetworkEntry> {
+        self.entries.iter().filter(|e| filter.matches(e)).collect()
+    }
+
+    fn latest_mut(&mut self, request_id: &str) -> Option<&mut NetworkEntry> {
+        self.entries
+            .iter_mut()
+            .rev()
+            .find(|e| e.request_id == request_id)
+    }
+}
+
+fn elapsed_ms(start: f64, end: f64) -> f64 {
+    ((end - start) * 1000.0).max(0.0)
+}
+
+/// Match a URL against a pattern: glob when it contains `*`, substring otherwise.
+pub fn url_matches(pattern: &str, url: &str) -> bool {
+    if pattern.contains('*') {
+        glob_match(pattern, url)
+    } else {
+        url.contains(pattern)
+    }
+}
+
+/// Host part of a URL, without scheme, userinfo, port, path or query.
+/// e.g. "https://user@api.example.com:8443/v1?q=1" → "api.example.com"
+pub fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map(|(_, r)| r).unwrap_or(url);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let host_port = authority.rsplit('@').next().unwrap_or(authority);
+    if let Some(v6) = host_port.strip_prefix('[') {
+        return v6.split(']').next().unwrap_or(v6);
+    }
+    host_port.split(':').next().unwrap_or(host_port)
+}
+
+/// Glob match where `*` matches any run of characters (including none).
+/// All other characters match literally.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if pi < p.len() && p[pi] == t[ti] {
+            pi += 1;
+            ti += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Format entries as one compact line each for LLM consumption.
+///
+/// Example: `#1234.5 GET 200 XHR https://api.example.com/search?q=x (84ms, application/json)`
+pub fn format_entries(entries: &[&NetworkEntry]) -> String {
+    entries
+        .iter()
+        .map(|e| {
+            let status = match (&e.error, e.status) {
+                (Some(err), _) => format!("FAILED({err})"),
+                (None, Some(s)) => s.to_string(),
+                (None, None) => "pending".into(),
+            };
+            let mut details = Vec::new();
+            if let Some(ms) = e.duration_ms {
+                details.push(format!("{}ms", ms.round() as u64));
+            }
+            if let Some(mime) = e.mime_type.as_deref().filter(|m| !m.is_empty()) {
+                details.push(mime.to_string());
+            }
+            let details = if details.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", details.join(", "))
+            };
+            format!(
+                "#{} {} {} {} {}{}",
+                e.request_id, e.method, status, e.resource_type, e.url, details
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Subscribe to the page's Network events and feed them into `log`.
+/// The listener tasks end when the page's event streams close.
+pub async fn attach(page: &Page, log: SharedNetworkLog) -> anyhow::Result<()> {
+    let mut sent = page.event_listener::<EventRequestWillBeSent>().await?;
+    let mut received = page.event_listener::<EventResponseReceived>().await?;
+    let mut finished = page.event_listener::<EventLoadingFinished>().await?;
+    let mut failed = page.event_listener::<EventLoadingFailed>().await?;
+
+    let l = log.clone();
+    tokio::spawn(async move {
+        while let Some(ev) = sent.next().await {
+            let Ok(mut log) = l.lock() else { break };
+            let ts = *ev.timestamp.inner();
+            if let Some(redirect) = &ev.redirect_response {
+                log.on_redirect(ev.request_id.as_ref(), redirect.status, ts);
+            }
+            let resource_type = ev.r#type.as_ref().map(|t| t.as_ref()).unwrap_or("Other");
+            log.on_request(
+                ev.request_id.as_ref(),
+                &ev.request.method,
+                &ev.request.url,
+                resource_type,
+                ts,
+            );
+        }
+    });
+
+    let l = log.clone();
+    tokio::spawn(async move {
+        while let Some(ev) = received.next().await {
+            let Ok(mut log) = l.lock() else { break };
+            log.on_response(
+                ev.request_id.as_ref(),
+                ev.response.status,
+                &ev.response.mime_type,
+            );
+        }
+    });
+
+    let l = log.clone();
+    tokio::spawn(async move {
+        while let Some(ev) = finished.next().await {
+            let Ok(mut log) = l.lock() else { break };
+            log.on_finished(ev.request_id.as_ref(), *ev.timestamp.inner());
+        }
+    });
+
+    tokio::spawn(async move {
+        while let Some(ev) = failed.next().await {
+            let Ok(mut log) = log.lock() else { break };
+            log.on_failed(
+                ev.request_id.as_ref(),
+                &ev.error_text,
+                *ev.timestamp.inner(),
+            );
+        }
+    });
+
+    Ok(())
+}
@@ -32,6 +32,12 @@ impl FnvHasher {
 }
 
 use crate::dom::{AriaRole, ElementLocator, PageSnapshot, ProcessResult, SemanticNode};
+use crate::secrets;
+
+/// Longest accessible name kept; longer ones are cut and end in
+/// [`TRUNCATION_MARK`].
+pub const MAX_NAME_LEN: usize = 200;
+pub const TRUNCATION_MARK: &str = "...";
 
 /// Tags that carry zero agent-relevant information and should be removed entirely.
 const PRUNED_TAGS: &[&str] = &[
@@ -163,6 +169,12 @@ pub fn process_with_refs(html: &str, url: &str) -> ProcessResult {
     let body = find_element(&root, "body").unwrap_or(root);
     let nodes = process_children(body, &mut ref_ctx, &labels);
 
+    let mut landmarks = HashMap::new();
+    collect_landmarks(&nodes, None, &mut landmarks);
+    for (id, locator) in &mut ref_ctx.ref_entries {
+        locator.landmark = landmarks.remove(id);
+    }
+
     let ref_count = ref_ctx.ref_entries.len();
     fn count_nodes(nodes: &[SemanticNode]) -> usize {
         nodes.iter().map(|n| 1 + count_nodes(&n.children)).sum()
@@ -181,11 +193,36 @@ pub fn process_with_refs(html: &str, url: &str) -> ProcessResult {
             url: url.to_string(),
             nodes,
             viewport: None,
+            emulation: None,
+            auth: None,
         },
         ref_index: ref_ctx.ref_entries.into_iter().collect(),
     }
 }
 
+/// Map each ref to the nearest landmark around it, e.g. `navigation "Main"`.
+fn collect_landmarks(
+    nodes: &[SemanticNode],
+    landmark: Option<&str>,
+    out: &mut HashMap<u32, String>,
+) {
+    for node in nodes {
+        if node.ref_id != 0 {
+            if let Some(landmark) = landmark {
+                out.insert(node.ref_id, landmark.to_string());
+            }
+        }
+        let own = node.role.is_landmark().then(|| {
+            if node.name.is_empty() {
+                node.role.to_string()
+            } else {
+                format!("{} \"{}\"", node.role, node.name)
+            }
+        });
+        collect_landmarks(&node.children, own.as_deref().or(landmark), out);
+    }
+}
+
 fn process_children(
     parent: ElementRef,
     ref_ctx: &mut RefContext,
@@ -244,8 +281,11 @@ fn process_element(
                     id: el.attr("id").map(String::from),
                     name: el.attr("name").map(String::from),
                     input_type: el.attr("type").map(String::from),
+                    autocomplete: el.attr("autocomplete").map(String::from),
                     href: el.attr("href").map(String::from),
                     text: name.clone(),
+                    role: Some(role.to_string()),
+                    landmark: None,
                 },
             ));
             id
@@ -440,18 +480,25 @@ fn compute_accessible_name(
     let text: String = element.text().collect::<Vec<_>>().join(" ");
     let trimmed = text.trim().to_string();
 
-    // Truncate very long text (find a char boundary near 197 bytes)
-    if trimmed.len() > 200 {
-        let mut end = 197;
+    // Truncate very long text (find a char boundary that leaves room for the mark)
+    if trimmed.len() > MAX_NAME_LEN {
+        let mut end = MAX_NAME_LEN - TRUNCATION_MARK.len();
         while !trimmed.is_char_boundary(end) {
             end -= 1;
         }
-        format!("{}...", &trimmed[..end])
+        format!("{}{TRUNCATION_MARK}", &trimmed[..end])
     } else {
         trimmed
     }
 }
 
+/// Whether an accessible name was cut short by the pipeline. The cut backs
+/// off to a char boundary, up to 3 bytes for a 4-byte char, so a truncated
+/// name of multi-byte text can be shorter than [`MAX_NAME_LEN`].
+pub fn is_truncated_name(name: &str) -> bool {
+    name.ends_with(TRUNCATION_MARK) && (MAX_NAME_LEN - 3..=MAX_NAME_LEN).contains(&name.len())
+}
+
 fn is_meaningful(
     role: &AriaRole,
     name: &str,
@@ -528,9 +575,17 @@ fn extract_relevant_attrs(tag: &str, el: &scraper::node::Element) -> Vec<(String
     attrs
 }
 
+/// Field value, masked for password, one-time code and credit card fields.
 fn extract_value(tag: &str, el: &scraper::node::Element) -> Option<String> {
     match tag {
-        "input" | "textarea" => el.attr("value").map(String::from),
+        "input" | "textarea" => {
+            let value = el.attr("value")?;
+            if secrets::classify(el.attr("type"), el.attr("autocomplete")).is_some() {
+                Some(secrets::REDACTED.into())
+            } else {
+                Some(value.into())
+            }
+        }
         _ => None,
     }
 }
@@ -787,6 +842,34 @@ mod tests {
         assert!(has_role(&s.nodes, &AriaRole::Button));
     }
 
+    #[test]
+    fn sensitive_values_masked() {
+        let result = process_with_refs(
+            r#"<body>
+            <input type="text" name="user" value="alice">
+            <input type="password" name="pass" value="hunter2">
+            <input name="code" autocomplete="one-time-code" value="123456">
+            <input name="card" autocomplete="billing cc-number" value="4111111111111111">
+        </body>"#,
+            "https://example.com/login",
+        );
+        let text = crate::serialize::to_compact_text(&result.snapshot);
+        assert!(text.contains("= \"alice\""));
+        for secret in ["hunter2", "123456", "4111111111111111"] {
+            assert!(!text.contains(secret), "{secret} leaked: {text}");
+        }
+        assert_eq!(text.matches(crate::secrets::REDACTED).count(), 3);
+        let otp = result
+            .ref_index
+            .values()
+            .find(|l| l.name.as_deref() == Some("code"))
+            .unwrap();
+        assert_eq!(
+            otp.secret_kind(),
+            Some(crate::secrets::SecretKind::OneTimeCode)
+        );
+    }
+
     #[test]
     fn select_maps_to_combobox() {
         let s = snap("<body><select><option>A</option></select></body>");
@@ -879,6 +962,24 @@ mod tests {
         assert_eq!(result.ref_index.len(), 2);
     }
 
+    #[test]
+    fn locator_records_role_and_landmark() {
+        let result = process_with_refs(
+            r#"<body><nav aria-label="Main"><a href="/docs">Docs</a></nav><button>Go</button></body>"#,
+            "",
+        );
+        let link = result.ref_index.values().find(|l| l.tag == "a").unwrap();
+        assert_eq!(link.role.as_deref(), Some("link"));
+        assert_eq!(link.landmark.as_deref(), Some("navigation \"Main\""));
+        let button = result
+            .ref_index
+            .values()
+            .find(|l| l.tag == "button")
+            .unwrap();
+        assert_eq!(button.role.as_deref(), Some("button"));
+        assert!(button.landmark.is_none());
+    }
+
     #[test]
     fn locator_uses_id_when_available() {
         let result = process_with_refs(r#"<body><button id="submit-btn">Go</button></body>"#, "");
@@ -1021,7 +1122,7 @@ mod tests {
         sorted.dedup();
         assert_eq!(refs.len(), sorted.len(), "no duplicate ref IDs: {:?}", refs);
         assert!(
-            refs.iter().all(|&r| r >= 10000 && r <= 99999),
+            refs.iter().all(|&r| (10000..=99999).contains(&r)),
             "all refs in 5-digit range: {:?}",
             refs
         );
@@ -0,0 +1,720 @@
+//! Recording export as Playwright tests.
+//!
+//! A recording becomes a TypeScript (`@playwright/test`) or Python
+//! (`pytest-playwright`) test. Elements are found the way Playwright
+//! recommends: by role and accessible name, by label for text fields, then by
+//! `#id`, `[name=...]` or `href`. Assertions become `expect` calls.
+//!
+//! Variables and secrets are read from environment variables, so the
+//! exported test holds no values: `{{query}}` from `QUERY` and
+//! `{{secret:password}}` from `CORTEX_SECRET_PASSWORD`. A code only lives for
+//! one period, so `{{totp:github}}` is generated at run time from the seed in
+//! `CORTEX_TOTP_SEED_GITHUB`, with `otplib` or `pyotp`.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::dom::ElementLocator;
+use crate::recording::{
+    self, unescape_braces, variable_captures, RecordedAction, Recording, RecordingStore, RefCheck,
+};
+use crate::{access, pipeline, secrets, totp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    TypeScript,
+    Python,
+}
+
+impl Language {
+    /// Playwright API name in this language: `getByRole` stays as is in
+    /// TypeScript and becomes `get_by_role` in Python.
+    fn method(self, name: &str) -> String {
+        if self == Language::TypeScript {
+            return name.to_string();
+        }
+        if name == "or" {
+            return "or_".into();
+        }
+        let mut out = String::new();
+        let mut prev_lower = false;
+        for c in name.chars() {
+            if c == '.' || (c.is_ascii_uppercase() && prev_lower) {
+                out.push('_');
+            }
+            if c != '.' {
+                out.push(c.to_ascii_lowercase());
+            }
+            prev_lower = c.is_ascii_lowercase();
+        }
+        out
+    }
+
+    /// String literal for `s`.
+    fn quote(self, s: &str) -> String {
+        let delim = match self {
+            Language::TypeScript => '\'',
+            Language::Python => '"',
+        };
+        let mut out = String::from(delim);
+        for c in s.chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if c == delim => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                c => out.push(c),
+            }
+        }
+        out.push(delim);
+        out
+    }
+
+    fn boolean(self, b: bool) -> &'static str {
+        match (self, b) {
+            (Language::TypeScript, true) => "true",
+            (Language::TypeScript, false) => "false",
+            (Language::Python, true) => "True",
+            (Language::Python, false) => "False",
+        }
+    }
+
+    /// Named options: `{ name: 'Go', exact: true }` in TypeScript,
+    /// `name="Go", exact=True` in Python.
+    fn options(self, options: &[(&str, String)]) -> String {
+        match self {
+            Language::TypeScript => {
+                let fields: Vec<String> =
+                    options.iter().map(|(k, v)| format!("{k}: {v}")).collect();
+                format!("{{ {} }}", fields.join(", "))
+            }
+            Language::Python => {
+                let fields: Vec<String> = options
+                    .iter()
+                    .map(|(k, v)| format!("{}={v}", self.method(k)))
+                    .collect();
+                fields.join(", ")
+            }
+        }
+    }
+
+    /// Expression reading environment variable `var`.
+    fn env(self, var: &str) -> String {
+        match self {
+            Language::TypeScript => format!("process.env.{var}!"),
+            Language::Python => format!("os.environ[\"{var}\"]"),
+        }
+    }
+
+    /// Expression for the current TOTP code of a base32 seed.
+    fn totp(self, seed: &str) -> String {
+        match self {
+            Language::TypeScript => format!("authenticator.generate({seed})"),
+            Language::Python => format!("pyotp.TOTP({seed}).now()"),
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "typescript" | "ts" => Ok(Language::TypeScript),
+            "python" | "py" => Ok(Language::Python),
+            _ => Err(format!(
+                "unknown language '{s}', expected typescript or python"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Language::TypeScript => f.write_str("typescript"),
+            Language::Python => f.write_str("python"),
+        }
+    }
+}
+
+/// Roles Playwright's `getByRole` is given for named elements.
+const ROLES: &[&str] = &[
+    "button", "link", "checkbox", "radio", "option", "tab", "menuitem", "img",
+];
+
+/// How an exported test finds a recorded element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Locator {
+    /// `getByRole(role, { name })`. `exact` is off for names the snapshot
+    /// truncated.
+    Role {
+        role: String,
+        name: String,
+        exact: bool,
+    },
+    /// `getByLabel(name)`, falling back to `getByPlaceholder(name)` for
+    /// inputs, whose accessible name may come from their placeholder.
+    Label {
+        name: String,
+        exact: bool,
+        placeholder: bool,
+    },
+    /// A CSS selector such as `#email` or `input[name="q"]`.
+    Css(String),
+    /// First `tag` element containing `text`.
+    Text { tag: String, text: String },
+}
+
+impl Locator {
+    /// The best locator for a recorded element.
+    pub fn for_element(el: &ElementLocator) -> Locator {
+        let role = el.role.clone().unwrap_or_else(|| implicit_role(el).into());
+        let text = el.text.trim();
+        let (name, exact) = match text.strip_suffix(pipeline::TRUNCATION_MARK) {
+            Some(truncated) if pipeline::is_truncated_name(text) => (truncated.to_string(), false),
+            _ => (text.to_string(), true),
+        };
+        if !name.is_empty() {
+            if role == "textbox" || role == "combobox" {
+                return Locator::Label {
+                    name,
+                    exact,
+                    placeholder: matches!(el.tag.as_str(), "input" | "textarea"),
+                };
+            }
+            if ROLES.contains(&role.as_str()) {
+                return Locator::Role { role, name, exact };
+            }
+        }
+        if let Some(id) = &el.id {
+            return Locator::Css(id_selector(id));
+        }
+        if let Some(attr) = &el.name {
+            return Locator::Css(format!("{}[name=\"{}\"]", el.tag, css_escape(attr)));
+        }
+        if let Some(href) = &el.href {
+            return Locator::Css(format!("{}[href=\"{}\"]", el.tag, css_escape(href)));
+        }
+        if !name.is_empty() {
+            return Locator::Text {
+                tag: el.tag.clone(),
+                text: name,
+            };
+        }
+        Locator::Css(el.tag.clone())
+    }
+
+    /// Expression locating the element on `page`.
+    pub fn render(&self, page: &str, lang: Language) -> String {
+        match self {
+            Locator::Role { role, name, exact } => format!(
+                "{page}.{}({}, {})",
+                lang.method("getByRole"),
+                lang.quote(role),
+                lang.options(&[
+                    ("name", lang.quote(name)),
+                    ("exact", lang.boolean(*exact).into())
+                ])
+            ),
+            Locator::Label {
+                name,
+                exact,
+                placeholder,
+            } => {
+                let by = |method: &str| {
+                    format!(
+                        "{page}.{}({}, {})",
+                        lang.method(method),
+                        lang.quote(name),
+                        lang.options(&[("exact", lang.boolean(*exact).into())])
+                    )
+                };
+                let label = by("getByLabel");
+                if *placeholder {
+                    format!("{label}.{}({})", lang.method("or"), by("getByPlaceholder"))
+                } else {
+                    label
+                }
+            }
+            Locator::Css(selector) => format!("{page}.locator({})", lang.quote(selector)),
+            Locator::Text { tag, text } => format!(
+                "{page}.locator({}, {})",
+                lang.quote(tag),
+                lang.options(&[("hasText", lang.quote(text))])
+            ),
+        }
+    }
+}
+
+/// Role of an element recorded before roles were stored, from its tag.
+fn implicit_role(el: &ElementLocator) -> &'static str {
+    match el.tag.as_str() {
+        "a" => "link",
+        "button" => "button",
+        "select" => "combobox",
+        "textarea" => "textbox",
+        "input" => match el.input_type.as_deref() {
+            Some("checkbox") => "checkbox",
+            Some("radio") => "radio",
+            Some("submit" | "button" | "reset") => "button",
+            _ => "textbox",
+        },
+        _ => "",
+    }
+}
+
+fn id_selector(id: &str) -> String {
+    let plain = id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if plain {
+        format!("#{id}")
+    } else {
+        format!("[id=\"{}\"]", css_escape(id))
+    }
+}
+
+fn css_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Matches a compact snapshot line such as `button "Log out"` or
+/// `heading[2] "Results"`.
+fn snapshot_item_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"^([a-z]+)(?:\[([1-6])\])? "(.+)"$"#).unwrap())
+}
+
+/// Environment variable the exported test reads the base32 seed of
+/// `{{totp:PROFILE}}` from, e.g. `CORTEX_TOTP_SEED_GITHUB`.
+pub fn totp_seed_env_var(profile: &str) -> String {
+    let name: String = profile
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("CORTEX_TOTP_SEED_{name}")
+}
+
+/// Environment variable the exported test reads `{{NAME}}` from, e.g. `QUERY`.
+pub fn variable_env_var(name: &str) -> String {
+    name.to_ascii_uppercase().replace('-', "_")
+}
+
+/// Convert a recording into a test file. A test starts at the recording's
+/// start URL unless its first action navigates.
+pub fn export(rec: &Recording, lang: Language) -> String {
+    let mut script = Script::new(lang);
+    let navigates_first = matches!(rec.actions.first(), Some(RecordedAction::Navigate { .. }));
+    if !navigates_first && !rec.start_url.is_empty() {
+        let url = lang.quote(&rec.start_url);
+        script.page_call("goto", &url);
+    }
+    for action in &rec.actions {
+        script.action(action);
+    }
+    script.finish(rec)
+}
+
+/// Load recording `name` and write it to `path` as a test. An existing file
+/// is only replaced when `overwrite` is set.
+pub fn export_file(
+    store: &RecordingStore,
+    name: &str,
+    domain: Option<&str>,
+    path: &Path,
+    lang: Language,
+    overwrite: bool,
+) -> anyhow::Result<Recording> {
+    let rec = store.load(name, domain)?;
+    access::create_file(path, overwrite)?.write_all(export(&rec, lang).as_bytes())?;
+    Ok(rec)
+}
+
+/// Resolve a path given by an MCP client inside the store's exports
+/// directory.
+pub fn export_path(store: &RecordingStore, path: &str) -> anyhow::Result<PathBuf> {
+    access::client_path(&store.exports_dir(), path)
+}
+
+struct Script {
+    lang: Language,
+    body: Vec<String>,
+    /// Variable holding the active tab's page.
+    page: String,
+    /// Recorded IDs of the tabs the test opened.
+    tabs: Vec<u32>,
+    env: BTreeSet<String>,
+    uses_context: bool,
+    uses_re: bool,
+    uses_totp: bool,
+}
+
+impl Script {
+    fn new(lang: Language) -> Self {
+        Self {
+            lang,
+            body: Vec::new(),
+            page: "page".into(),
+            tabs: Vec::new(),
+            env: BTreeSet::new(),
+            uses_context: false,
+            uses_re: false,
+            uses_totp: false,
+        }
+    }
+
+    fn run(&mut self, call: String) {
+        self.body.push(match self.lang {
+            Language::TypeScript => format!("await {call};"),
+            Language::Python => call,
+        });
+    }
+
+    fn page_call(&mut self, method: &str, args: &str) {
+        let call = format!("{}.{}({args})", self.page, self.lang.method(method));
+        self.run(call);
+    }
+
+    fn element_call(&mut self, el: &ElementLocator, method: &str, args: &str) {
+        let call = format!("{}.{}({args})", self.locator(el), self.lang.method(method));
+        self.run(call);
+    }
+
+    fn expect(&mut self, subject: &str, matcher: &str, args: &str) {
+        let call = format!("expect({subject}).{}({args})", self.lang.method(matcher));
+        self.run(call);
+    }
+
+    fn locator(&self, el: &ElementLocator) -> String {
+        Locator::for_element(el).render(&self.page, self.lang)
+    }
+
+    /// Page variable of recorded tab `tab_id`.
+    fn tab(&self, tab_id: u32) -> String {
+        if self.tabs.contains(&tab_id) {
+            format!("tab{tab_id}")
+        } else {
+            "page".into()
+        }
+    }
+
+    fn env_var(&mut self, var: String) -> String {
+        let expr = self.lang.env(&var);
+        self.env.insert(var);
+        expr
+    }
+
+    /// Expression for recorded text, reading placeholders from the
+    /// environment.
+    fn text(&mut self, text: &str) -> String {
+        if let Some(name) = secrets::placeholder_name(text) {
+            return self.env_var(secrets::env_var(name));
+        }
+        if let Some(profile) = totp::placeholder_profile(text) {
+            let seed = self.env_var(totp_seed_env_var(profile));
+            self.uses_totp = true;
+            return self.lang.totp(&seed);
+        }
+        let mut parts = Vec::new();
+        let mut last = 0;
+        for caps in variable_captures(text) {
+            let whole = caps.get(0).unwrap();
+            if whole.start() > last {
+                parts.push(
+                    self.lang
+                        .quote(&unescape_braces(&text[last..whole.start()])),
+                );
+            }
+            parts.push(self.env_var(variable_env_var(&caps[1])));
+            last = whole.end();
+        }
+        if last < text.len() || parts.is_empty() {
+            parts.push(self.lang.quote(&unescape_braces(&text[last..])));
+        }
+        parts.join(" + ")
+    }
+
+    /// Regex for an `AssertUrl` pattern: a glob must match the whole URL, a
+    /// substring anywhere. Variables match any text.
+    fn url_regex(&mut self, pattern: &str) -> String {
+        let glob = pattern.contains('*');
+        let mut source = String::new();
+        let mut last = 0;
+        let literal = |s: &str| {
+            unescape_braces(s)
+                .split('*')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(".*")
+        };
+        for caps in variable_captures(pattern) {
+            let whole = caps.get(0).unwrap();
+            source.push_str(&literal(&pattern[last..whole.start()]));
+            source.push_str(".*");
+            last = whole.end();
+        }
+        source.push_str(&literal(&pattern[last..]));
+        if glob {
+            source = format!("^{source}$");
+        }
+        match self.lang {
+            Language::TypeScript => format!("/{}/", source.replace('/', "\\/")),
+            Language::Python => {
+                self.uses_re = true;
+                format!("re.compile(r\"{}\")", source.replace('"', "\\\""))
+            }
+        }
+    }
+
+    fn action(&mut self, action: &RecordedAction) {
+        let lang = self.lang;
+        match action {
+            RecordedAction::Navigate { url } => {
+                let url = self.text(url);
+                self.page_call("goto", &url);
+            }
+            RecordedAction::Click { locator, .. } => self.element_call(locator, "click", ""),
+            RecordedAction::TypeText { locator, text, .. } => {
+                let text = self.text(text);
+                self.element_call(locator, "fill", &text);
+            }
+            RecordedAction::SelectOption { locator, value, .. } => {
+                let value = self.text(value);
+                self.element_call(locator, "selectOption", &value);
+            }
+            RecordedAction::ScrollDown => self.scroll(""),
+            RecordedAction::ScrollUp => self.scroll("-"),
+            RecordedAction::ScrollToRef { locator, .. } => {
+                self.element_call(locator, "scrollIntoViewIfNeeded", "")
+            }
+            RecordedAction::WaitForChanges { .. } => self.page_call("waitForLoadState", ""),
+            RecordedAction::PressKey { key, locator, .. } => {
+                let key = lang.quote(key);
+                match locator {
+                    Some(locator) => self.element_call(locator, "press", &key),
+                    None => {
+                        let call = format!("{}.keyboard.press({key})", self.page);
+                        self.run(call);
+                    }
+                }
+            }
+            RecordedAction::GoBack => self.page_call("goBack", ""),
+            RecordedAction::GoForward => self.page_call("goForward", ""),
+            RecordedAction::OpenTab { url, tab_id } => {
+                self.uses_context = true;
+                let var = format!("tab{tab_id}");
+                self.body.push(match lang {
+                    Language::TypeScript => format!("const {var} = await context.newPage();"),
+                    Language::Python => format!("{var} = context.new_page()"),
+                });
+                self.tabs.push(*tab_id);
+                self.page = var;
+                let url = self.text(url);
+                self.page_call("goto", &url);
+            }
+            RecordedAction::SwitchTab { tab_id } => {
+                self.page = self.tab(*tab_id);
+                self.page_call("bringToFront", "");
+            }
+            RecordedAction::CloseTab { tab_id } => {
+                let var = self.tab(*tab_id);
+                self.run(format!("{var}.close()"));
+                if self.page == var {
+                    self.page = "page".into();
+                }
+            }
+            RecordedAction::Delay { ms } => {
+                let ms = (*ms).min(recording::MAX_DELAY_MS);
+                self.page_call("waitForTimeout", &ms.to_string())
+            }
+            RecordedAction::AssertText { text } => {
+                let text = self.text(text);
+                let body = format!("{}.locator({})", self.page, lang.quote("body"));
+                self.expect(&body, "toContainText", &text);
+            }
+            RecordedAction::AssertUrl { url } => {
+                let regex = self.url_regex(url);
+                let page = self.page.clone();
+                self.expect(&page, "toHaveURL", &regex);
+            }
+            RecordedAction::AssertRef { locator, check, .. } => {
+                let subject = self.locator(locator);
+                match check {
+                    RefCheck::Exists => self.expect(&subject, "toBeAttached", ""),
+                    RefCheck::Value(value) => {
+                        let value = self.text(value);
+                        self.expect(&subject, "toHaveValue", &value);
+                    }
+                    RefCheck::Checked(true) => self.expect(&subject, "toBeChecked", ""),
+                    RefCheck::Checked(false) => self.expect(&subject, "not.toBeChecked", ""),
+                }
+            }
+            RecordedAction::AssertSnapshotContains { text } => {
+                // `role "name"` lines become a role check; other snapshot
+                // text is looked for in the page text.
+                let item = snapshot_item_regex().captures(text).filter(|caps| {
+                    (ROLES.contains(&&caps[1]) || caps.get(2).is_some()) && !text.contains("{{")
+                });
+                match item {
+                    Some(caps) => {
+                        let mut options = vec![("name", lang.quote(&caps[3]))];
+                        if let Some(level) = caps.get(2) {
+                            options.push(("level", level.as_str().to_string()));
+                        }
+                        let subject = format!(
+                            "{}.{}({}, {}).first()",
+                            self.page,
+                            lang.method("getByRole"),
+                            lang.quote(&caps[1]),
+                            lang.options(&options)
+                        );
+                        self.expect(&subject, "toBeVisible", "");
+                    }
+                    None => {
+                        let text = self.text(text);
+                        let body = format!("{}.locator({})", self.page, lang.quote("body"));
+                        self.expect(&body, "toContainText", &text);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Scroll by 85% of the viewport, like `scroll_down` and `scroll_up`.
+    fn scroll(&mut self, sign: &str) {
+        let js = format!("window.scrollBy(0, {sign}Math.round(window.innerHeight * 0.85))");
+        let arg = match self.lang {
+            Language::TypeScript => format!("() => {js}"),
+            Language::Python => self.lang.quote(&js),
+        };
+        self.page_call("evaluate", &arg);
+    }
+
+    fn finish(self, rec: &Recording) -> String {
+        let lang = self.lang;
+        let comment = match lang {
+            Language::TypeScript => "//",
+            Language::Python => "#",
+        };
+        let mut header = vec![format!(
+            "{comment} Exported from cortex-browser recording '{}' ({}, version {}).",
+            rec.name, rec.domain, rec.version
+        )];
+        if let Some(description) = &rec.description {
+            header.extend(description.lines().map(|line| format!("{comment} {line}")));
+        }
+        if !self.env.is_empty() {
+            let vars: Vec<&str> = self.env.iter().map(String::as_str).collect();
+            header.push(format!(
+                "{comment} Reads {} from the environment.",
+                vars.join(", ")
+            ));
+        }
+
+        let mut out = String::new();
+        match lang {
+            Language::TypeScript => {
+                out.push_str("import { test, expect } from '@playwright/test';\n");
+                if self.uses_totp {
+                    out.push_str("import { authenticator } from 'otplib';\n");
+                }
+                out.push('\n');
+                for line in &header {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+                out.push('\n');
+                let fixtures = if self.uses_context {
+                    "{ page, context }"
+                } else {
+                    "{ page }"
+                };
+                out.push_str(&format!(
+                    "test({}, async ({fixtures}) => {{\n",
+                    lang.quote(&rec.name)
+                ));
+                for line in &self.body {
+                    out.push_str(&format!("  {line}\n"));
+                }
+                out.push_str("});\n");
+            }
+            Language::Python => {
+                let mut imports = Vec::new();
+                if !self.env.is_empty() {
+                    imports.push("import os");
+                }
+                if self.uses_re {
+                    imports.push("import re");
+                }
+                for import in &imports {
+                    out.push_str(import);
+                    out.push('\n');
+                }
+                if !imports.is_empty() {
+                    out.push('\n');
+                }
+                let types = if self.uses_context {
+                    "BrowserContext, Page, expect"
+                } else {
+                    "Page, expect"
+                };
+                if self.uses_totp {
+                    out.push_str("import pyotp\n");
+                }
+                out.push_str(&format!("from playwright.sync_api import {types}\n\n"));
+                for line in &header {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+                let context = if self.uses_context {
+                    ", context: BrowserContext"
+                } else {
+                    ""
+                };
+                out.push_str(&format!(
+                    "\n\ndef test_{}(page: Page{context}) -> None:\n",
+                    python_name(&rec.name)
+                ));
+                if self.body.is_empty() {
+                    out.push_str("    pass\n");
+                }
+                for line in &self.body {
+                    out.push_str(&format!("    {line}\n"));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Python identifier for a recording name, e.g. "Log in" → "log_in".
+fn python_name(name: &str) -> String {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.is_empty() {
+        "recording".into()
+    } else {
+        words.join("_")
+    }
+}
@@ -0,0 +1,147 @@
+//! Domain policy: which hosts and URL schemes the agent may navigate to.
+//!
+//! The policy is checked before `navigate` and `open_tab`, and on every
+//! document request through Fetch interception, which covers link clicks,
+//! form submissions and redirects. Subresources (images, scripts, XHR) are
+//! not affected; use the blocklist for those.
+//!
+//! Host patterns are either a host, which also matches its subdomains
+//! ("example.com" matches "app.example.com"), or a glob with `*`
+//! ("*.staging.example.com"). Deny patterns win over allow patterns.
+
+use serde::{Deserialize, Serialize};
+
+use crate::blocking;
+use crate::network;
+
+/// Schemes allowed when the policy is active and none are listed.
+const DEFAULT_SCHEMES: &[&str] = &["http", "https"];
+
+/// Schemes always read as schemes, never as a host followed by a port, so
+/// `data:1` cannot pass as the host "data".
+const SCHEMES_NOT_HOSTS: &[&str] = &[
+    "about",
+    "blob",
+    "chrome",
+    "data",
+    "file",
+    "filesystem",
+    "javascript",
+    "mailto",
+    "vbscript",
+    "view-source",
+];
+
+/// Read from the `[policy]` table of the config file, then extended by CLI flags.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DomainPolicy {
+    /// When non-empty, only these hosts may be visited.
+    pub allow: Vec<String>,
+    /// Hosts that may never be visited.
+    pub deny: Vec<String>,
+    /// Allowed URL schemes. Defaults to http and https once any rule is set,
+    /// which keeps `file:`, `chrome:`, `javascript:` and `data:` out.
+    pub schemes: Vec<String>,
+}
+
+impl DomainPolicy {
+    pub fn is_active(&self) -> bool {
+        !self.allow.is_empty() || !self.deny.is_empty() || !self.schemes.is_empty()
+    }
+
+    /// Append another policy's rules to this one.
+    pub fn merge(&mut self, other: DomainPolicy) {
+        self.allow.extend(other.allow);
+        self.deny.extend(other.deny);
+        self.schemes.extend(other.schemes);
+    }
+
+    /// Check a URL, returning why it is not allowed. `about:blank` is always
+    /// allowed, since new tabs start there.
+    pub fn check(&self, url: &str) -> Result<(), String> {
+        if !self.is_active() || url == "about:blank" {
+            return Ok(());
+        }
+
+        let scheme = url_scheme(url);
+        if let Some(scheme) = &scheme {
+            let allowed = if self.schemes.is_empty() {
+                DEFAULT_SCHEMES.contains(&scheme.as_str())
+            } else {
+                self.schemes
+                    .iter()
+                    .any(|s| s.trim_end_matches(':').eq_ignore_ascii_case(scheme))
+            };
+            if !allowed {
+                return Err(format!("scheme '{scheme}:' is not allowed"));
+            }
+        }
+        if !url.contains("://") && scheme.is_some() {
+            // data:, javascript: and the like have no host to check.
+            return Ok(());
+        }
+
+        let host = network::url_host(url).to_ascii_lowercase();
+        if let Some(pattern) = self.deny.iter().find(|p| host_pattern_matches(p, &host)) {
+            return Err(format!("host '{host}' matches denied pattern '{pattern}'"));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|p| host_pattern_matches(p, &host)) {
+            return Err(format!("host '{host}' is not in the allowlist"));
+        }
+        Ok(())
+    }
+
+    /// Loggable summary, e.g. "allow example.com; deny prod.example.com".
+    pub fn describe(&self) -> String {
+        if !self.is_active() {
+            return "none".into();
+        }
+        let schemes = if self.schemes.is_empty() {
+            DEFAULT_SCHEMES.join(", ")
+        } else {
+            self.schemes.join(", ")
+        };
+        let mut parts = Vec::new();
+        if !self.allow.is_empty() {
+            parts.push(format!("allow {}", self.allow.join(", ")));
+        }
+        if !self.deny.is_empty() {
+            parts.push(format!("deny {}", self.deny.join(", ")));
+        }
+        parts.push(format!("schemes {schemes}"));
+        parts.join("; ")
+    }
+}
+
+/// Lowercased scheme of an absolute URL, or `None` for a bare host or path.
+pub fn url_scheme(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once(':')?;
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    if !valid {
+        return None;
+    }
+    let scheme = scheme.to_ascii_lowercase();
+    if rest.starts_with("//") || SCHEMES_NOT_HOSTS.contains(&scheme.as_str()) {
+        return Some(scheme);
+    }
+    // "localhost:8080/path" has a port, not a scheme: only digits up to the
+    // path. "javascript:1;alert(1)" does not.
+    let port = &rest[..rest.find(['/', '?', '#']).unwrap_or(rest.len())];
+    let is_port = !port.is_empty() && port.chars().all(|c| c.is_ascii_digit());
+    (!is_port).then_some(scheme)
+}
+
+/// Whether `host` matches a policy pattern: a glob if it contains `*`,
+/// otherwise the host itself or one of its subdomains.
+pub fn host_pattern_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    if pattern.contains('*') {
+        network::glob_match(&pattern, &host.to_ascii_lowercase())
+    } else {
+        blocking::host_matches(&pattern, host)
+    }
+}
@@ -0,0 +1,187 @@
+//! Shared pool of launched Chrome processes for `mcp-http --launch`.
+//!
+//! Rather than one Chrome per HTTP session, sessions lease an incognito
+//! browser context on one of up to `size` browsers, each hosting at most
+//! `sessions_per_browser` sessions. Browsers are launched on demand; one that
+//! crashed or disconnected is dropped from its slot and relaunched by a later
+//! lease. When every slot is full, leasing fails with a clear error instead of
+//! launching more processes.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use chromiumoxide::cdp::browser_protocol::browser::BrowserContextId;
+use tokio::sync::{Mutex, OnceCell};
+use tracing::{info, warn};
+
+use crate::browser::{self, BrowserHandle, BrowserOptions};
+
+struct Slot {
+    /// Set once the browser has launched.
+    browser: Arc<OnceCell<Arc<BrowserHandle>>>,
+    /// Sessions currently leasing a context on this browser.
+    leases: Arc<AtomicUsize>,
+}
+
+pub struct BrowserPool {
+    options: BrowserOptions,
+    size: usize,
+    sessions_per_browser: usize,
+    slots: Mutex<Vec<Option<Slot>>>,
+}
+
+impl fmt::Debug for BrowserPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BrowserPool")
+            .field("size", &self.size)
+            .field("sessions_per_browser", &self.sessions_per_browser)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A session's share of the pool: one browser context on a pooled browser.
+/// Dropping it frees the slot; the context itself is disposed by its owner.
+pub struct Lease {
+    browser: Arc<BrowserHandle>,
+    context: BrowserContextId,
+    leases: Arc<AtomicUsize>,
+}
+
+impl Lease {
+    pub fn browser(&self) -> Arc<BrowserHandle> {
+        self.browser.clone()
+    }
+
+    pub fn context(&self) -> &BrowserContextId {
+        &self.context
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.leases.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl BrowserPool {
+    pub fn new(options: BrowserOptions, size: usize, sessions_per_browser: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            options,
+            size,
+            sessions_per_browser: sessions_per_browser.max(1),
+            slots: Mutex::new((0..size).map(|_| None).collect()),
+        }
+    }
+
+    /// Lease a fresh context, launching a browser if every running one is full.
+    /// The pool lock is only held to pick and reserve a slot; launching Chrome
+    /// and creating the context happen outside it, so one slow start does not
+    /// hold up other sessions.
+    pub async fn lease(&self) -> anyhow::Result<Lease> {
+        let (index, cell, leases) = {
+            let mut slots = self.slots.lock().await;
+
+            for (index, slot) in slots.iter_mut().enumerate() {
+                let dead = slot
+                    .as_ref()
+                    .and_then(|running| running.browser.get())
+                    .is_some_and(|browser| !browser.is_connected());
+                if dead {
+                    warn!(
+                        slot = index,
+                        sessions = slot.as_ref().map(|s| s.leases.load(Ordering::Relaxed)),
+                        "pooled browser died, recycling"
+                    );
+                    *slot = None;
+                }
+            }
+
+            let loads: Vec<Option<usize>> = slots
+                .iter()
+                .map(|s| s.as_ref().map(|s| s.leases.load(Ordering::Relaxed)))
+                .collect();
+            let index = choose_slot(&loads, self.sessions_per_browser).with_context(|| {
+                format!(
+                    "Browser pool is saturated: {} browser(s) each hosting {} session(s). \
+                     Retry after another session ends, or raise --pool-size / --sessions-per-browser.",
+                    self.size, self.sessions_per_browser
+                )
+            })?;
+
+            let slot = slots[index].get_or_insert_with(|| Slot {
+                browser: Arc::new(OnceCell::new()),
+                leases: Arc::new(AtomicUsize::new(0)),
+            });
+            // Counted before the launch, so concurrent leases see the room
+            // this one takes.
+            slot.leases.fetch_add(1, Ordering::Relaxed);
+            (index, slot.browser.clone(), slot.leases.clone())
+        };
+
+        let leased = async {
+            // Leases reserved on a slot that is still launching wait for that
+            // launch; a failed launch leaves the slot to be retried.
+            let browser = cell
+                .get_or_try_init(|| async {
+                    info!(slot = index, "launching pooled browser");
+                    browser::launch(&self.options).await.map(Arc::new)
+                })
+                .await?
+                .clone();
+            let context = browser::create_context(&browser, &self.options.proxy).await?;
+            anyhow::Ok((browser, context))
+        }
+        .await;
+        let (browser, context) = match leased {
+            Ok(leased) => leased,
+            Err(e) => {
+                leases.fetch_sub(1, Ordering::Relaxed);
+                return Err(e);
+            }
+        };
+
+        info!(
+            slot = index,
+            sessions = leases.load(Ordering::Relaxed),
+            "browser context leased"
+        );
+        Ok(Lease {
+            browser,
+            context,
+            leases,
+        })
+    }
+
+    /// Close every pooled browser that no session still holds.
+    pub async fn shutdown(&self) {
+        let slots = std::mem::take(&mut *self.slots.lock().await);
+        for slot in slots.into_iter().flatten() {
+            let browser = Arc::try_unwrap(slot.browser)
+                .ok()
+                .and_then(OnceCell::into_inner)
+                .and_then(|browser| Arc::try_unwrap(browser).ok());
+            if let Some(mut browser) = browser {
+                browser.close().await.ok();
+                browser.wait().await.ok();
+            }
+        }
+    }
+}
+
+/// Pick a slot for a new session given each slot's session count (`None` for
+/// a slot without a running browser). Running browsers with room are filled
+/// first, least loaded first, so no new process starts while one can take the
+/// session. Returns `None` when the pool is saturated.
+pub fn choose_slot(loads: &[Option<usize>], sessions_per_browser: usize) -> Option<usize> {
+    let running = loads
+        .iter()
+        .enumerate()
+        .filter_map(|(i, load)| load.map(|n| (i, n)))
+        .filter(|&(_, n)| n < sessions_per_browser)
+        .min_by_key(|&(_, n)| n)
+        .map(|(i, _)| i);
+    running.or_else(|| loads.iter().position(Option::is_none))
+}
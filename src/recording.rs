@@ -1,10 +1,14 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
 
-use crate::dom::ElementLocator;
+use crate::dom::{ElementLocator, ElementState};
+use crate::network;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "action", rename_all = "snake_case")]
@@ -26,11 +30,230 @@ pub enum RecordedAction {
         value: String,
         ref_id: u32,
     },
+    ScrollDown,
+    ScrollUp,
+    ScrollToRef {
+        locator: ElementLocator,
+        ref_id: u32,
+    },
+    /// Wait until the DOM changes or `timeout_ms` passes.
+    WaitForChanges {
+        timeout_ms: u64,
+    },
+    /// Press a key, e.g. "Enter", in the element if one is given.
+    PressKey {
+        key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        locator: Option<ElementLocator>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ref_id: Option<u32>,
+    },
+    GoBack,
+    GoForward,
+    /// `tab_id` is the ID the tab had while recording; replay maps it to the
+    /// tab it opens.
+    OpenTab {
+        url: String,
+        tab_id: u32,
+    },
+    SwitchTab {
+        tab_id: u32,
+    },
+    CloseTab {
+        tab_id: u32,
+    },
+    /// Time between two actions, captured when recording with `timing`.
+    Delay {
+        ms: u64,
+    },
+    /// The page's visible text contains `text`.
+    AssertText {
+        text: String,
+    },
+    /// The current URL matches `url`: a glob when it contains `*`, a
+    /// substring otherwise.
+    AssertUrl {
+        url: String,
+    },
+    /// The element exists and passes `check`.
+    AssertRef {
+        locator: ElementLocator,
+        check: RefCheck,
+        ref_id: u32,
+    },
+    /// The compact snapshot contains `text`, e.g. `button "Log out"`.
+    AssertSnapshotContains {
+        text: String,
+    },
+}
+
+impl RecordedAction {
+    /// Tool-style name used in replay reports, e.g. "assert_url".
+    pub fn name(&self) -> &'static str {
+        match self {
+            RecordedAction::Navigate { .. } => "navigate",
+            RecordedAction::Click { .. } => "click",
+            RecordedAction::TypeText { .. } => "type_text",
+            RecordedAction::SelectOption { .. } => "select_option",
+            RecordedAction::ScrollDown => "scroll_down",
+            RecordedAction::ScrollUp => "scroll_up",
+            RecordedAction::ScrollToRef { .. } => "scroll_to_ref",
+            RecordedAction::WaitForChanges { .. } => "wait_for_changes",
+            RecordedAction::PressKey { .. } => "press_key",
+            RecordedAction::GoBack => "go_back",
+            RecordedAction::GoForward => "go_forward",
+            RecordedAction::OpenTab { .. } => "open_tab",
+            RecordedAction::SwitchTab { .. } => "switch_tab",
+            RecordedAction::CloseTab { .. } => "close_tab",
+            RecordedAction::Delay { .. } => "delay",
+            RecordedAction::AssertText { .. } => "assert_text",
+            RecordedAction::AssertUrl { .. } => "assert_url",
+            RecordedAction::AssertRef { .. } => "assert_ref",
+            RecordedAction::AssertSnapshotContains { .. } => "assert_snapshot_contains",
+        }
+    }
+
+    /// Names of the `{{NAME}}` variables in this action's text.
+    pub fn variables(&self) -> Vec<String> {
+        let text = match self {
+            RecordedAction::Navigate { url }
+            | RecordedAction::AssertUrl { url }
+            | RecordedAction::OpenTab { url, .. } => url,
+            RecordedAction::TypeText { text, .. }
+            | RecordedAction::AssertText { text }
+            | RecordedAction::AssertSnapshotContains { text } => text,
+            RecordedAction::SelectOption { value, .. }
+            | RecordedAction::AssertRef {
+                check: RefCheck::Value(value),
+                ..
+            } => value,
+            _ => return Vec::new(),
+        };
+        variable_captures(text)
+            .map(|caps| caps[1].to_string())
+            .collect()
+    }
+
+    /// Point an element action at a healed element. Returns false for
+    /// actions without a target element.
+    pub fn retarget(&mut self, new_locator: ElementLocator, new_ref: u32) -> bool {
+        match self {
+            RecordedAction::Click { locator, ref_id }
+            | RecordedAction::TypeText {
+                locator, ref_id, ..
+            }
+            | RecordedAction::SelectOption {
+                locator, ref_id, ..
+            }
+            | RecordedAction::AssertRef {
+                locator, ref_id, ..
+            }
+            | RecordedAction::ScrollToRef { locator, ref_id } => {
+                *locator = new_locator;
+                *ref_id = new_ref;
+                true
+            }
+            RecordedAction::PressKey {
+                locator: locator @ Some(_),
+                ref_id,
+                ..
+            } => {
+                *locator = Some(new_locator);
+                *ref_id = Some(new_ref);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_assertion(&self) -> bool {
+        matches!(
+            self,
+            RecordedAction::AssertText { .. }
+                | RecordedAction::AssertUrl { .. }
+                | RecordedAction::AssertRef { .. }
+                | RecordedAction::AssertSnapshotContains { .. }
+        )
+    }
+}
+
+/// What `AssertRef` checks about its element.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefCheck {
+    Exists,
+    Value(String),
+    Checked(bool),
+}
+
+impl RefCheck {
+    /// Check the element's observed state; `None` if it was not found.
+    pub fn verify(&self, state: Option<&ElementState>) -> Result<(), String> {
+        let Some(state) = state else {
+            return Err("element not found in the live DOM".into());
+        };
+        match self {
+            RefCheck::Exists => Ok(()),
+            RefCheck::Value(expected) => match state.value.as_deref() {
+                Some(value) if value == expected => Ok(()),
+                Some(value) => Err(format!("value is \"{value}\", expected \"{expected}\"")),
+                None => Err(format!("element has no value, expected \"{expected}\"")),
+            },
+            RefCheck::Checked(expected) if state.checked == *expected => Ok(()),
+            RefCheck::Checked(expected) => Err(format!(
+                "element is {}, expected {}",
+                checked_label(state.checked),
+                checked_label(*expected)
+            )),
+        }
+    }
+}
+
+fn checked_label(checked: bool) -> &'static str {
+    if checked {
+        "checked"
+    } else {
+        "unchecked"
+    }
+}
+
+/// Whether `haystack` contains `needle`, treating runs of whitespace alike.
+pub fn text_contains(haystack: &str, needle: &str) -> bool {
+    let collapse = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+    collapse(haystack).contains(&collapse(needle))
+}
+
+/// Check an `AssertText` or `AssertSnapshotContains` against the page text.
+pub fn verify_contains(page: &str, text: &str) -> Result<(), String> {
+    if text_contains(page, text) {
+        Ok(())
+    } else {
+        Err(format!("\"{text}\" not found on the page"))
+    }
+}
+
+/// Check an `AssertUrl` against the current URL.
+pub fn verify_url(url: &str, pattern: &str) -> Result<(), String> {
+    if network::url_matches(pattern, url) {
+        Ok(())
+    } else {
+        Err(format!("URL is {url}, expected {pattern}"))
+    }
+}
+
+fn first_version() -> u32 {
+    1
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Recording {
     pub name: String,
+    /// Bumped each time replay heals a locator and saves the recording again.
+    #[serde(default = "first_version")]
+    pub version: u32,
+    /// Tab that was active when recording started, for remapping tab IDs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_tab: Option<u32>,
     pub domain: String,
     pub start_url: String,
     pub created_at: String,
@@ -39,6 +262,25 @@ pub struct Recording {
     pub actions: Vec<RecordedAction>,
 }
 
+impl Recording {
+    /// Append an action run in `active_tab`. A recording started before any
+    /// tab was open takes the first tab an action runs in as its start tab,
+    /// so replay can map it; tabs opened by `open_tab` are mapped anyway.
+    pub fn push(&mut self, action: RecordedAction, active_tab: Option<u32>) {
+        if self.start_tab.is_none() && !matches!(action, RecordedAction::OpenTab { .. }) {
+            self.start_tab = active_tab;
+        }
+        self.actions.push(action);
+    }
+
+    /// Names of the `{{NAME}}` variables the actions use, sorted. Replay
+    /// needs a value for each.
+    pub fn variables(&self) -> Vec<String> {
+        let names: BTreeSet<String> = self.actions.iter().flat_map(|a| a.variables()).collect();
+        names.into_iter().collect()
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct RecordingSummary {
     pub name: String,
@@ -46,6 +288,9 @@ pub struct RecordingSummary {
     pub description: Option<String>,
     pub action_count: usize,
     pub created_at: String,
+    pub version: u32,
+    /// Variables `replay_recording` must be given values for.
+    pub variables: Vec<String>,
 }
 
 impl From<&Recording> for RecordingSummary {
@@ -56,8 +301,122 @@ impl From<&Recording> for RecordingSummary {
             description: rec.description.clone(),
             action_count: rec.actions.len(),
             created_at: rec.created_at.clone(),
+            version: rec.version,
+            variables: rec.variables(),
+        }
+    }
+}
+
+/// Gaps shorter than this are not recorded as delays.
+pub const MIN_DELAY_MS: u64 = 100;
+
+/// Longest delay recorded or waited out. A gap between tool calls also
+/// holds model think time and approval prompts, which a replay should not
+/// sit through.
+pub const MAX_DELAY_MS: u64 = 5_000;
+
+/// Milliseconds a replay waits for a recorded delay of `ms`, capped at
+/// [`MAX_DELAY_MS`] and multiplied by `scale` (0 skips delays).
+pub fn replay_delay_ms(ms: u64, scale: f64) -> u64 {
+    (ms.min(MAX_DELAY_MS) as f64 * scale).round() as u64
+}
+
+// ── Tabs ────────────────────────────────────────────────────────────────────
+
+/// Maps the tab IDs of a recording to the live tabs of a replay.
+#[derive(Debug, Default)]
+pub struct TabMap {
+    tabs: HashMap<u32, u32>,
+}
+
+impl TabMap {
+    /// Start with the recording's first tab mapped to the replay's active one.
+    pub fn new(recorded_start: Option<u32>, live_start: u32) -> Self {
+        let mut map = Self::default();
+        if let Some(recorded) = recorded_start {
+            map.tabs.insert(recorded, live_start);
         }
+        map
+    }
+
+    pub fn opened(&mut self, recorded: u32, live: u32) {
+        self.tabs.insert(recorded, live);
+    }
+
+    pub fn closed(&mut self, recorded: u32) {
+        self.tabs.remove(&recorded);
+    }
+
+    /// The live tab for a recorded tab ID.
+    pub fn resolve(&self, recorded: u32) -> anyhow::Result<u32> {
+        self.tabs.get(&recorded).copied().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Recorded tab {recorded} was not opened or was closed during the replay"
+            )
+        })
+    }
+}
+
+// ── Variables ───────────────────────────────────────────────────────────────
+
+/// Matches `{{NAME}}`. Names hold no colon, so `{{secret:NAME}}` and
+/// `{{totp:PROFILE}}` placeholders are left alone.
+pub fn variable_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_-]*)\s*\}\}").unwrap())
+}
+
+/// A literal `{{` in recorded text: `\{{query}}` types `{{query}}`.
+pub const LITERAL_BRACES: &str = "\\{{";
+
+/// The `{{NAME}}` variables in `text`, skipping escaped ones.
+pub fn variable_captures(text: &str) -> impl Iterator<Item = Captures<'_>> {
+    variable_regex()
+        .captures_iter(text)
+        .filter(|caps| !text[..caps.get(0).unwrap().start()].ends_with('\\'))
+}
+
+/// Escape text typed as is, so a replay types it unchanged instead of
+/// reading `{{...}}` in it as a placeholder.
+pub fn escape_braces(text: &str) -> String {
+    text.replace("{{", LITERAL_BRACES)
+}
+
+/// Undo [`escape_braces`] in text between variables.
+pub fn unescape_braces(text: &str) -> String {
+    text.replace(LITERAL_BRACES, "{{")
+}
+
+/// The placeholder recorded for a value marked as variable `name`, e.g. `{{query}}`.
+pub fn variable_placeholder(name: &str) -> anyhow::Result<String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        anyhow::bail!(
+            "Invalid variable name '{name}': use letters, digits, '_' and '-', starting with a letter or '_'"
+        );
+    }
+    Ok(format!("{{{{{name}}}}}"))
+}
+
+/// Replace every `{{NAME}}` in `text` with its value. Values are inserted as
+/// is and never read as placeholders themselves.
+pub fn substitute(text: &str, variables: &BTreeMap<String, String>) -> anyhow::Result<String> {
+    let mut result = String::new();
+    let mut last = 0;
+    for caps in variable_captures(text) {
+        let whole = caps.get(0).unwrap();
+        let Some(value) = variables.get(&caps[1]) else {
+            anyhow::bail!("No value for variable '{}'", &caps[1]);
+        };
+        result.push_str(&unescape_braces(&text[last..whole.start()]));
+        result.push_str(value);
+        last = whole.end();
     }
+    result.push_str(&unescape_braces(&text[last..]));
+    Ok(result)
 }
 
 // ── Helpers ─────────────────────────────────────────────────────────────────
@@ -109,6 +468,37 @@ pub fn recordings_dir() -> PathBuf {
         .join("recordings")
 }
 
+/// Subdirectory of a domain directory holding earlier recording versions.
+const VERSIONS_DIR: &str = ".versions";
+
+/// Subdirectory of the recordings directory that exported tests are written
+/// to on behalf of MCP clients.
+const EXPORTS_DIR: &str = "exports";
+
+/// Delete the earlier versions of recording `name` kept in `domain_dir`.
+fn remove_versions(domain_dir: &Path, name: &str) -> anyhow::Result<()> {
+    let history = domain_dir.join(VERSIONS_DIR);
+    let Ok(entries) = fs::read_dir(&history) else {
+        return Ok(());
+    };
+    let prefix = format!("{}.v", sanitize_filename(name));
+    for entry in entries {
+        let path = entry?.path();
+        let file = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .unwrap_or_default();
+        let is_version = file
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".json"))
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        if is_version {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
 // ── RecordingStore ──────────────────────────────────────────────────────────
 
 pub struct RecordingStore {
@@ -132,17 +522,47 @@ impl RecordingStore {
         Self { base }
     }
 
-    /// Save a recording to disk. Returns the path written.
+    /// Directory exported tests are written to, e.g.
+    /// `~/.cortex-browser/recordings/exports`.
+    pub fn exports_dir(&self) -> PathBuf {
+        self.base.join(EXPORTS_DIR)
+    }
+
+    /// Save a recording to disk. Returns the path written. The file is
+    /// written next to its final path and renamed into place, so a failed
+    /// write never leaves a truncated recording.
     pub fn save(&self, rec: &Recording) -> anyhow::Result<PathBuf> {
         let dir = self.base.join(&rec.domain);
         fs::create_dir_all(&dir)?;
         let filename = format!("{}.json", sanitize_filename(&rec.name));
         let path = dir.join(&filename);
+        let tmp = path.with_extension("json.tmp");
         let json = serde_json::to_string_pretty(rec)?;
-        fs::write(&path, json)?;
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &path)?;
         Ok(path)
     }
 
+    /// Save `rec` as its next version, copying the current file to
+    /// `<domain>/.versions/<name>.v<N>.json` first. Returns the path written.
+    pub fn save_version(&self, rec: &mut Recording) -> anyhow::Result<PathBuf> {
+        let dir = self.base.join(&rec.domain);
+        let stem = sanitize_filename(&rec.name);
+        let current = dir.join(format!("{stem}.json"));
+        if let Ok(json) = fs::read_to_string(&current) {
+            let previous: Recording = serde_json::from_str(&json)?;
+            let history = dir.join(VERSIONS_DIR);
+            fs::create_dir_all(&history)?;
+            fs::copy(
+                &current,
+                history.join(format!("{stem}.v{}.json", previous.version)),
+            )?;
+            rec.version = rec.version.max(previous.version);
+        }
+        rec.version += 1;
+        self.save(rec)
+    }
+
     /// Load a recording by name. If `domain` is None, search all domain dirs.
     pub fn load(&self, name: &str, domain: Option<&str>) -> anyhow::Result<Recording> {
         let filename = format!("{}.json", sanitize_filename(name));
@@ -222,6 +642,7 @@ impl RecordingStore {
             let path = self.base.join(d).join(&filename);
             if path.exists() {
                 fs::remove_file(&path)?;
+                remove_versions(&self.base.join(d), name)?;
                 return Ok(());
             }
             anyhow::bail!("Recording '{}' not found in domain '{}'", name, d);
@@ -235,6 +656,7 @@ impl RecordingStore {
                     let path = entry.path().join(&filename);
                     if path.exists() {
                         fs::remove_file(&path)?;
+                        remove_versions(&entry.path(), name)?;
                         return Ok(());
                     }
                 }
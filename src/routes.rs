@@ -0,0 +1,185 @@
+//! Request mocking: serve responses from fixture files or inline bodies.
+//!
+//! A `RouteTable` maps URL globs to canned responses. Matching requests are
+//! answered through `Fetch.fulfillRequest` (see `intercept`) and never reach
+//! the network. With `offline` set, unmatched requests fail instead of going
+//! out, which keeps CI sessions fully deterministic.
+//!
+//! Routes files are JSON or TOML (chosen by extension):
+//! ```json
+//! {
+//!   "offline": true,
+//!   "routes": [
+//!     { "url": "https://shop.test/*", "file": "fixtures/ecommerce.html" },
+//!     { "url": "*/api/search*", "body": "{\"items\":[]}", "content_type": "application/json" }
+//!   ]
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::network::glob_match;
+
+/// A single mocked URL pattern.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Route {
+    /// URL glob (`*` wildcard) the route applies to.
+    #[serde(alias = "url")]
+    pub url_pattern: String,
+    /// File to serve as the body. Relative paths resolve against the routes
+    /// file's directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Inline body, used when `file` is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Content-Type header. Guessed from the file extension when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+/// A resolved response ready to be fulfilled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouteTable {
+    /// Fail requests that match no route instead of letting them through.
+    #[serde(default)]
+    pub offline: bool,
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    /// Directory relative `file` paths resolve against.
+    #[serde(skip)]
+    pub base_dir: Option<PathBuf>,
+}
+
+impl RouteTable {
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty() && !self.offline
+    }
+
+    /// Load a routes file. `.toml` files are parsed as TOML, anything else as JSON.
+    pub fn load_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read routes file {}: {e}", path.display()))?;
+        let mut table: RouteTable = if path.extension().and_then(|e| e.to_str()) == Some("toml") {
+            toml::from_str(&contents)
+                .map_err(|e| anyhow::anyhow!("Invalid routes file {}: {e}", path.display()))?
+        } else {
+            serde_json::from_str(&contents)
+                .map_err(|e| anyhow::anyhow!("Invalid routes file {}: {e}", path.display()))?
+        };
+        table.base_dir = path.parent().map(Path::to_path_buf);
+        Ok(table)
+    }
+
+    /// Add a route, replacing any existing route with the same pattern.
+    pub fn add(&mut self, route: Route) {
+        self.routes.retain(|r| r.url_pattern != route.url_pattern);
+        self.routes.push(route);
+    }
+
+    /// Remove the route with this pattern. Returns whether one was removed.
+    pub fn remove(&mut self, url_pattern: &str) -> bool {
+        let before = self.routes.len();
+        self.routes.retain(|r| r.url_pattern != url_pattern);
+        self.routes.len() != before
+    }
+
+    /// Route matching `url`. Later routes take priority, so routes added
+    /// mid-session override the ones from the startup file.
+    pub fn find(&self, url: &str) -> Option<&Route> {
+        self.routes
+            .iter()
+            .rev()
+            .find(|r| glob_match(&r.url_pattern, url))
+    }
+
+    /// Build the response for a route, reading its fixture file if needed.
+    pub fn resolve(&self, route: &Route) -> anyhow::Result<MockResponse> {
+        let body = match (&route.file, &route.body) {
+            (Some(file), _) => {
+                let path = match &self.base_dir {
+                    Some(base) if file.is_relative() => base.join(file),
+                    _ => file.clone(),
+                };
+                std::fs::read(&path).map_err(|e| {
+                    anyhow::anyhow!("Failed to read fixture {}: {e}", path.display())
+                })?
+            }
+            (None, Some(body)) => body.clone().into_bytes(),
+            (None, None) => Vec::new(),
+        };
+
+        let content_type = route.content_type.clone().or_else(|| {
+            route
+                .file
+                .as_deref()
+                .and_then(guess_content_type)
+                .map(String::from)
+        });
+
+        let mut headers: Vec<(String, String)> = route
+            .headers
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let has_content_type = headers
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case("content-type"));
+        if let (Some(ct), false) = (content_type, has_content_type) {
+            headers.push(("Content-Type".into(), ct));
+        }
+
+        Ok(MockResponse {
+            status: route.status,
+            headers,
+            body,
+        })
+    }
+
+    /// One-line summary for tool output and logs.
+    pub fn describe(&self) -> String {
+        let mut text = format!("{} route(s)", self.routes.len());
+        if self.offline {
+            text.push_str(", offline");
+        }
+        text
+    }
+}
+
+/// Content type for common fixture extensions.
+pub fn guess_content_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "json" => "application/json",
+        "js" | "mjs" => "application/javascript",
+        "css" => "text/css",
+        "txt" => "text/plain; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "woff2" => "font/woff2",
+        _ => return None,
+    })
+}
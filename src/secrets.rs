@@ -0,0 +1,118 @@
+//! Sensitive form fields: passwords, one-time codes and credit cards.
+//!
+//! Fields are recognised by their `type` and `autocomplete` attributes. Their
+//! values are masked in snapshots, text typed into them is masked in logs,
+//! and recordings store a `{{secret:NAME}}` placeholder instead of the text.
+//! At replay time placeholders are resolved from a [`SecretProvider`]:
+//! values passed to `replay_recording`, then `CORTEX_SECRET_<NAME>`
+//! environment variables.
+
+use std::collections::BTreeMap;
+
+use crate::dom::ElementLocator;
+use crate::proxy::Secret;
+
+/// Shown in place of a sensitive value.
+pub const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretKind {
+    Password,
+    OneTimeCode,
+    CreditCard,
+}
+
+impl SecretKind {
+    /// Placeholder name used when the field has no name or id.
+    pub fn label(&self) -> &'static str {
+        match self {
+            SecretKind::Password => "password",
+            SecretKind::OneTimeCode => "otp",
+            SecretKind::CreditCard => "card",
+        }
+    }
+}
+
+/// Classify a field from its `type` and `autocomplete` attributes.
+/// `autocomplete` may hold several tokens, e.g. "section-billing cc-number".
+pub fn classify(input_type: Option<&str>, autocomplete: Option<&str>) -> Option<SecretKind> {
+    if input_type.is_some_and(|t| t.eq_ignore_ascii_case("password")) {
+        return Some(SecretKind::Password);
+    }
+    autocomplete?.split_ascii_whitespace().find_map(|token| {
+        match token.to_ascii_lowercase().as_str() {
+            "current-password" | "new-password" => Some(SecretKind::Password),
+            "one-time-code" => Some(SecretKind::OneTimeCode),
+            "cc-number" | "cc-csc" | "cc-exp" | "cc-exp-month" | "cc-exp-year" => {
+                Some(SecretKind::CreditCard)
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Placeholder recorded instead of text typed into a sensitive field, named
+/// after the field's `name` or `id`, e.g. `{{secret:password}}`.
+pub fn placeholder(locator: &ElementLocator, kind: SecretKind) -> String {
+    let raw = locator
+        .name
+        .as_deref()
+        .or(locator.id.as_deref())
+        .unwrap_or(kind.label());
+    let name: String = raw
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{{{{secret:{name}}}}}")
+}
+
+/// The secret name if `text` is a placeholder.
+pub fn placeholder_name(text: &str) -> Option<&str> {
+    text.strip_prefix("{{secret:")?
+        .strip_suffix("}}")
+        .filter(|name| !name.is_empty())
+}
+
+/// Environment variable a secret is read from, e.g. `CORTEX_SECRET_PASSWORD`.
+pub fn env_var(name: &str) -> String {
+    format!("CORTEX_SECRET_{}", name.to_ascii_uppercase())
+}
+
+/// Supplies secret values for placeholders during replay.
+#[derive(Debug, Default)]
+pub struct SecretProvider {
+    values: BTreeMap<String, Secret>,
+}
+
+impl SecretProvider {
+    pub fn new(values: BTreeMap<String, String>) -> Self {
+        Self {
+            values: values
+                .into_iter()
+                .map(|(name, value)| (name, Secret::new(value)))
+                .collect(),
+        }
+    }
+
+    /// Replace a placeholder with its secret; other text is returned as is.
+    pub fn resolve(&self, text: &str) -> anyhow::Result<String> {
+        let Some(name) = placeholder_name(text) else {
+            return Ok(text.to_string());
+        };
+        if let Some(value) = self.values.get(name) {
+            return Ok(value.expose().to_string());
+        }
+        let var = env_var(name);
+        std::env::var(&var).map_err(|_| {
+            anyhow::anyhow!(
+                "The recording needs secret '{name}': pass it in `secrets` or set {var}"
+            )
+        })
+    }
+}
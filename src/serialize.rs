@@ -32,6 +32,14 @@ pub fn to_compact_text(snapshot: &PageSnapshot) -> String {
             ));
         }
 
+        if let Some(emulation) = &snapshot.emulation {
+            output.push_str(&format!("emulation: {emulation}\n"));
+        }
+
+        if let Some(auth) = &snapshot.auth {
+            output.push_str(&format!("auth: {auth}\n"));
+        }
+
         output.push_str("---\n");
     }
 
@@ -205,6 +213,8 @@ mod tests {
             url: "https://example.com".into(),
             nodes: vec![],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.starts_with("page: \"My Page\" [https://example.com]"));
@@ -218,6 +228,8 @@ mod tests {
             url: String::new(),
             nodes: vec![],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("page: \"Title Only\""));
@@ -231,6 +243,8 @@ mod tests {
             url: String::new(),
             nodes: vec![node(AriaRole::StaticText, "Hello", 0)],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(!text.contains("page:"));
@@ -244,6 +258,8 @@ mod tests {
             url: String::new(),
             nodes: vec![node(AriaRole::StaticText, "Just text", 0)],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert_eq!(text.trim(), "Just text");
@@ -256,6 +272,8 @@ mod tests {
             url: String::new(),
             nodes: vec![node(AriaRole::Button, "Submit", 1)],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("button @e1 \"Submit\""));
@@ -273,6 +291,8 @@ mod tests {
                 vec![("href", "/home")],
             )],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("link @e1 \"Home\" -> /home"));
@@ -285,6 +305,8 @@ mod tests {
             url: String::new(),
             nodes: vec![node(AriaRole::Checkbox, "Remember me", 1)],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("[unchecked]"));
@@ -302,6 +324,8 @@ mod tests {
                 vec![("checked", "true")],
             )],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("[checked]"));
@@ -319,6 +343,8 @@ mod tests {
                 vec![("disabled", "true")],
             )],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("[disabled]"));
@@ -336,6 +362,8 @@ mod tests {
                 vec![("required", "true")],
             )],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("[required]"));
@@ -353,6 +381,8 @@ mod tests {
                 vec![("type", "password")],
             )],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("(password)"));
@@ -373,6 +403,8 @@ mod tests {
                 offscreen: None,
             }],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("= \"John\""));
@@ -393,6 +425,8 @@ mod tests {
                 ],
             )],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("navigation:\n"));
@@ -413,6 +447,8 @@ mod tests {
                 vec![node(AriaRole::StaticText, "Click here", 0)],
             )],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         // Should NOT have "Click here" appearing as a child line
@@ -438,6 +474,8 @@ mod tests {
                 ],
             )],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         let nav_line = text.lines().find(|l| l.contains("navigation")).unwrap();
@@ -461,6 +499,8 @@ mod tests {
                 vec![node(AriaRole::StaticText, "Hello world", 0)],
             )],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(
@@ -479,6 +519,8 @@ mod tests {
                 node(AriaRole::Heading { level: 3 }, "Subtitle", 0),
             ],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("heading[1] \"Title\""));
@@ -496,6 +538,8 @@ mod tests {
                 viewport_height: 900,
                 document_height: 4200,
             }),
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(
@@ -511,11 +555,47 @@ mod tests {
             url: "https://test.com".into(),
             nodes: vec![],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(!text.contains("viewport:"), "no viewport line when None");
     }
 
+    #[test]
+    fn emulation_header_rendered() {
+        let snap = PageSnapshot {
+            title: "Test".into(),
+            url: "https://test.com".into(),
+            nodes: vec![],
+            viewport: None,
+            emulation: Some("iPhone 15 393x852@3x mobile touch, de-DE".into()),
+            auth: None,
+        };
+        let text = to_compact_text(&snap);
+        assert!(
+            text.contains("emulation: iPhone 15 393x852@3x mobile touch, de-DE\n---"),
+            "emulation line: {text}"
+        );
+    }
+
+    #[test]
+    fn auth_header_rendered() {
+        let snap = PageSnapshot {
+            title: "Inbox".into(),
+            url: "https://mail.example.com".into(),
+            nodes: vec![],
+            viewport: None,
+            emulation: None,
+            auth: Some("work-mail (auto, bound to example.com)".into()),
+        };
+        let text = to_compact_text(&snap);
+        assert!(
+            text.contains("\nauth: work-mail (auto, bound to example.com)\n---"),
+            "auth line: {text}"
+        );
+    }
+
     #[test]
     fn offscreen_annotation_rendered() {
         let snap = PageSnapshot {
@@ -531,6 +611,8 @@ mod tests {
                 offscreen: Some(true),
             }],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(text.contains("[offscreen]"), "offscreen annotation: {text}");
@@ -555,6 +637,8 @@ mod tests {
                 offscreen: Some(false),
             }],
             viewport: None,
+            emulation: None,
+            auth: None,
         };
         let text = to_compact_text(&snap);
         assert!(
@@ -0,0 +1,214 @@
+//! Web storage in auth profiles.
+//!
+//! Many single-page apps keep their session token in `localStorage` rather
+//! than a cookie, so cookies alone do not keep them logged in. [`capture`]
+//! reads the current origin's `localStorage` and `sessionStorage` through CDP
+//! DOMStorage and, optionally, its IndexedDB databases through page script.
+//! [`restore_script`] builds a script that is registered with
+//! `Page.addScriptToEvaluateOnNewDocument`, so the values are in place before
+//! the app's own scripts run.
+//!
+//! The script writes an origin's values once per tab (tracked with a
+//! `sessionStorage` marker per profile), so an app that logs out or
+//! refreshes its token afterwards is not overridden on the next navigation.
+
+use std::collections::BTreeMap;
+
+use chromiumoxide::cdp::browser_protocol::dom_storage::{
+    EnableParams, GetDomStorageItemsParams, StorageId,
+};
+use chromiumoxide::cdp::browser_protocol::page::{
+    RemoveScriptToEvaluateOnNewDocumentParams, ScriptIdentifier,
+};
+use chromiumoxide::Page;
+
+use crate::auth::{AuthProfile, IndexedDbDatabase, OriginStorage};
+
+/// Prefix of the `sessionStorage` keys recording which profiles were applied
+/// to the tab.
+pub const RESTORED_MARKER: &str = "__cortex_auth_restored";
+
+/// JavaScript that reads every IndexedDB database of the current origin.
+/// Returns a JSON array of databases; values that do not survive a JSON
+/// round trip are skipped.
+pub const CAPTURE_INDEXED_DB_JS: &str = r#"(async function() {
+    if (!window.indexedDB || !indexedDB.databases) return '[]';
+    var request = function(r) {
+        return new Promise(function(resolve, reject) {
+            r.onsuccess = function() { resolve(r.result); };
+            r.onerror = function() { reject(r.error); };
+        });
+    };
+    var json = function(v) {
+        try {
+            var s = JSON.stringify(v);
+            return s === undefined ? undefined : JSON.parse(s);
+        } catch (e) {
+            return undefined;
+        }
+    };
+    var out = [];
+    for (var info of await indexedDB.databases()) {
+        if (!info.name) continue;
+        var db;
+        try { db = await request(indexedDB.open(info.name)); } catch (e) { continue; }
+        var stores = [];
+        for (var name of Array.from(db.objectStoreNames)) {
+            var store = db.transaction(name, 'readonly').objectStore(name);
+            var indexes = Array.from(store.indexNames).map(function(n) {
+                var index = store.index(n);
+                return {
+                    name: n,
+                    key_path: index.keyPath,
+                    unique: index.unique,
+                    multi_entry: index.multiEntry
+                };
+            });
+            var results = await Promise.all([
+                request(store.getAllKeys()),
+                request(store.getAll())
+            ]);
+            var records = [];
+            results[0].forEach(function(key, i) {
+                var k = json(key), v = json(results[1][i]);
+                if (k !== undefined && v !== undefined) records.push({key: k, value: v});
+            });
+            stores.push({
+                name: name,
+                key_path: store.keyPath,
+                auto_increment: store.autoIncrement,
+                indexes: indexes,
+                records: records
+            });
+        }
+        out.push({name: db.name, version: db.version, stores: stores});
+        db.close();
+    }
+    return JSON.stringify(out);
+})()"#;
+
+/// Restore function, called with the storage by origin, a token naming the
+/// saved profile and the profile's marker key.
+const RESTORE_JS: &str = r#"(function(data, token, marker) {
+    var entry = data[location.origin];
+    if (!entry) return;
+    try {
+        if (sessionStorage.getItem(marker) === token) return;
+        sessionStorage.setItem(marker, token);
+    } catch (e) {
+        return;
+    }
+    var fill = function(storage, items) {
+        try {
+            Object.keys(items || {}).forEach(function(k) { storage.setItem(k, items[k]); });
+        } catch (e) {}
+    };
+    fill(localStorage, entry.local_storage);
+    fill(sessionStorage, entry.session_storage);
+    (entry.indexed_db || []).forEach(function(db) {
+        var open = indexedDB.open(db.name, db.version);
+        open.onerror = function(e) { e.preventDefault(); };
+        open.onupgradeneeded = function() {
+            var conn = open.result;
+            db.stores.forEach(function(s) {
+                if (conn.objectStoreNames.contains(s.name)) return;
+                var options = {autoIncrement: s.auto_increment};
+                if (s.key_path !== null) options.keyPath = s.key_path;
+                var store = conn.createObjectStore(s.name, options);
+                s.indexes.forEach(function(i) {
+                    store.createIndex(i.name, i.key_path, {unique: i.unique, multiEntry: i.multi_entry});
+                });
+            });
+        };
+        open.onsuccess = function() {
+            var conn = open.result;
+            var names = db.stores.map(function(s) { return s.name; })
+                .filter(function(n) { return conn.objectStoreNames.contains(n); });
+            if (!names.length) { conn.close(); return; }
+            var tx = conn.transaction(names, 'readwrite');
+            db.stores.forEach(function(s) {
+                if (names.indexOf(s.name) < 0) return;
+                var store = tx.objectStore(s.name);
+                s.records.forEach(function(r) {
+                    if (store.keyPath === null) store.put(r.value, r.key); else store.put(r.value);
+                });
+            });
+            tx.oncomplete = function() { conn.close(); };
+        };
+    });
+})"#;
+
+/// Read the web storage of the page's origin. `None` for pages without a
+/// real origin (`about:blank`, `file:`).
+pub async fn capture(page: &Page, indexed_db: bool) -> anyhow::Result<Option<OriginStorage>> {
+    let origin: String = page.evaluate("location.origin").await?.into_value()?;
+    if origin.is_empty() || origin == "null" {
+        return Ok(None);
+    }
+
+    page.execute(EnableParams::default()).await?;
+    let mut storage = OriginStorage {
+        origin: origin.clone(),
+        ..Default::default()
+    };
+    for is_local in [true, false] {
+        let id = StorageId::builder()
+            .security_origin(origin.clone())
+            .is_local_storage(is_local)
+            .build()
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let items: BTreeMap<String, String> = page
+            .execute(GetDomStorageItemsParams::new(id))
+            .await?
+            .result
+            .entries
+            .iter()
+            .filter_map(|item| match item.inner().as_slice() {
+                [key, value] if !key.starts_with(RESTORED_MARKER) => {
+                    Some((key.clone(), value.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        if is_local {
+            storage.local_storage = items;
+        } else {
+            storage.session_storage = items;
+        }
+    }
+
+    if indexed_db {
+        let json: String = page.evaluate(CAPTURE_INDEXED_DB_JS).await?.into_value()?;
+        storage.indexed_db = serde_json::from_str::<Vec<IndexedDbDatabase>>(&json)?;
+    }
+    Ok(Some(storage))
+}
+
+/// Script that writes a profile's web storage into matching origins, or
+/// `None` if the profile has none.
+pub fn restore_script(profile: &AuthProfile) -> Option<String> {
+    if profile.origins.is_empty() {
+        return None;
+    }
+    let data: BTreeMap<&str, &OriginStorage> = profile
+        .origins
+        .iter()
+        .map(|o| (o.origin.trim_end_matches('/'), o))
+        .collect();
+    let token = serde_json::Value::String(format!("{}@{}", profile.profile, profile.saved_at));
+    let marker = serde_json::Value::String(format!("{RESTORED_MARKER}:{}", profile.profile));
+    let data = serde_json::to_string(&data).ok()?;
+    Some(format!("{RESTORE_JS}({data}, {token}, {marker});"))
+}
+
+/// Register a restore script to run before any page script on every
+/// document the page loads.
+pub async fn install(page: &Page, script: &str) -> anyhow::Result<ScriptIdentifier> {
+    Ok(page.evaluate_on_new_document(script.to_string()).await?)
+}
+
+pub async fn uninstall(page: &Page, id: ScriptIdentifier) -> anyhow::Result<()> {
+    page.execute(RemoveScriptToEvaluateOnNewDocumentParams::new(id))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,230 @@
+//! TOTP one-time codes (RFC 6238) for 2FA logins.
+//!
+//! An auth profile can hold the TOTP seed of its account, stored only in
+//! encrypted profiles. Agents never see the seed: `totp_code` returns the
+//! current code, and `type_text` expands a `{{totp:PROFILE}}` placeholder
+//! into the code when typing, so recordings keep the placeholder and replays
+//! type a fresh code.
+
+use hmac::digest::core_api::BlockSizeUser;
+use hmac::digest::Digest;
+use hmac::{Mac, SimpleHmac};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Algorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl std::str::FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().replace('-', "").as_str() {
+            "SHA1" => Ok(Algorithm::Sha1),
+            "SHA256" => Ok(Algorithm::Sha256),
+            "SHA512" => Ok(Algorithm::Sha512),
+            _ => Err(format!("unsupported TOTP algorithm '{s}'")),
+        }
+    }
+}
+
+fn default_digits() -> u32 {
+    6
+}
+
+fn default_period() -> u64 {
+    30
+}
+
+/// A TOTP seed with its parameters, as shown by an authenticator setup page.
+/// Seeds read from a profile are validated like parsed ones.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "StoredTotpSecret")]
+pub struct TotpSecret {
+    /// Base32 seed, normalized to upper case without spaces or padding.
+    pub secret: String,
+    #[serde(default = "default_digits")]
+    pub digits: u32,
+    /// Seconds each code is valid for.
+    #[serde(default = "default_period")]
+    pub period: u64,
+    #[serde(default)]
+    pub algorithm: Algorithm,
+}
+
+/// Prints the parameters only, so a profile in a log or error message does
+/// not reveal the seed.
+impl std::fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TotpSecret")
+            .field("secret", &format_args!("<redacted>"))
+            .field("digits", &self.digits)
+            .field("period", &self.period)
+            .field("algorithm", &self.algorithm)
+            .finish()
+    }
+}
+
+/// [`TotpSecret`] as stored, before validation.
+#[derive(Deserialize)]
+struct StoredTotpSecret {
+    secret: String,
+    #[serde(default = "default_digits")]
+    digits: u32,
+    #[serde(default = "default_period")]
+    period: u64,
+    #[serde(default)]
+    algorithm: Algorithm,
+}
+
+impl TryFrom<StoredTotpSecret> for TotpSecret {
+    type Error = anyhow::Error;
+
+    fn try_from(stored: StoredTotpSecret) -> anyhow::Result<Self> {
+        let totp = TotpSecret {
+            secret: stored.secret,
+            digits: stored.digits,
+            period: stored.period,
+            algorithm: stored.algorithm,
+        };
+        totp.validate()?;
+        Ok(totp)
+    }
+}
+
+impl TotpSecret {
+    /// Parse a base32 seed ("JBSW Y3DP EHPK 3PXP") or an `otpauth://totp/`
+    /// URI as encoded in setup QR codes.
+    pub fn parse(input: &str) -> anyhow::Result<TotpSecret> {
+        let input = input.trim();
+        let mut totp = TotpSecret {
+            secret: String::new(),
+            digits: default_digits(),
+            period: default_period(),
+            algorithm: Algorithm::Sha1,
+        };
+        if let Some(rest) = input.strip_prefix("otpauth://") {
+            let (kind, rest) = rest.split_once('/').unwrap_or((rest, ""));
+            if !kind.eq_ignore_ascii_case("totp") {
+                anyhow::bail!("Only otpauth://totp/ URIs are supported, not {kind}");
+            }
+            let query = rest.split_once('?').map(|(_, q)| q).unwrap_or("");
+            for pair in query.split('&') {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                match key.to_ascii_lowercase().as_str() {
+                    "secret" => totp.secret = value.replace("%20", ""),
+                    "digits" => {
+                        totp.digits = value
+                            .parse()
+                            .map_err(|_| anyhow::anyhow!("Invalid TOTP digits '{value}'"))?
+                    }
+                    "period" => {
+                        totp.period = value
+                            .parse()
+                            .map_err(|_| anyhow::anyhow!("Invalid TOTP period '{value}'"))?
+                    }
+                    "algorithm" => {
+                        totp.algorithm = value.parse().map_err(|e: String| anyhow::anyhow!(e))?
+                    }
+                    _ => {}
+                }
+            }
+        } else {
+            totp.secret = input.to_string();
+        }
+        totp.secret = totp
+            .secret
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-' && *c != '=')
+            .collect::<String>()
+            .to_ascii_uppercase();
+        totp.validate()?;
+        Ok(totp)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(6..=10).contains(&self.digits) {
+            anyhow::bail!("TOTP codes must have 6 to 10 digits, not {}", self.digits);
+        }
+        if self.period == 0 {
+            anyhow::bail!("TOTP period must be at least one second");
+        }
+        if base32_decode(&self.secret)?.is_empty() {
+            anyhow::bail!("TOTP secret is empty");
+        }
+        Ok(())
+    }
+
+    /// The code for Unix time `secs`.
+    pub fn code_at(&self, secs: u64) -> anyhow::Result<String> {
+        let key = base32_decode(&self.secret)?;
+        let counter = (secs / self.period).to_be_bytes();
+        let hash = match self.algorithm {
+            Algorithm::Sha1 => hmac::<sha1::Sha1>(&key, &counter),
+            Algorithm::Sha256 => hmac::<sha2::Sha256>(&key, &counter),
+            Algorithm::Sha512 => hmac::<sha2::Sha512>(&key, &counter),
+        };
+        // Dynamic truncation (RFC 4226 section 5.3).
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let value = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        let code = u64::from(value) % 10u64.pow(self.digits);
+        Ok(format!("{code:0width$}", width = self.digits as usize))
+    }
+
+    /// Seconds until the code for `secs` expires.
+    pub fn remaining(&self, secs: u64) -> u64 {
+        self.period - secs % self.period
+    }
+}
+
+fn hmac<D: Digest + BlockSizeUser>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac =
+        <SimpleHmac<D> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Decode RFC 4648 base32 without padding.
+fn base32_decode(input: &str) -> anyhow::Result<Vec<u8>> {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut out = Vec::with_capacity(input.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for (i, c) in input.bytes().enumerate() {
+        // The seed must not end up in logs, so only the position is reported.
+        let value = ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "TOTP secret is not base32 (invalid character at position {})",
+                    i + 1
+                )
+            })?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(out)
+}
+
+/// The profile name if `text` is a `{{totp:PROFILE}}` placeholder.
+pub fn placeholder_profile(text: &str) -> Option<&str> {
+    text.strip_prefix("{{totp:")?
+        .strip_suffix("}}")
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
@@ -0,0 +1,236 @@
+//! Encryption at rest for auth profiles.
+//!
+//! When a key is configured, profiles are written as a JSON envelope instead
+//! of plain JSON: a versioned header, then the profile encrypted with
+//! XChaCha20-Poly1305. The header (format, version, key derivation and the
+//! profile's name, domain and save time) is authenticated as associated
+//! data, so it can be read without the key but not altered.
+//!
+//! The key comes from one of two environment variables:
+//! - `CORTEX_AUTH_KEY_FILE`: path to a file holding a base64-encoded 32-byte
+//!   key, e.g. made with `head -c 32 /dev/urandom | base64 > auth.key`
+//! - `CORTEX_AUTH_PASSPHRASE`: a passphrase, stretched with Argon2id and a
+//!   random salt stored in each file
+
+use std::fmt;
+use std::path::Path;
+
+use base64::Engine as _;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+use crate::proxy::Secret;
+
+pub const KEY_FILE_ENV: &str = "CORTEX_AUTH_KEY_FILE";
+pub const PASSPHRASE_ENV: &str = "CORTEX_AUTH_PASSPHRASE";
+/// New passphrase for `rotate-key`.
+pub const NEW_PASSPHRASE_ENV: &str = "CORTEX_AUTH_NEW_PASSPHRASE";
+
+/// Identifies an encrypted profile file.
+const FORMAT: &str = "cortex-browser-auth";
+const VERSION: u32 = 1;
+const KDF_ARGON2ID: &str = "argon2id";
+const KDF_NONE: &str = "none";
+
+/// Key used to encrypt auth profiles.
+#[derive(Clone)]
+pub enum AuthKey {
+    /// Stretched with Argon2id and a per-file salt.
+    Passphrase(Secret),
+    /// Used as is.
+    Key([u8; 32]),
+}
+
+impl fmt::Debug for AuthKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthKey::Passphrase(_) => f.write_str("Passphrase(<redacted>)"),
+            AuthKey::Key(_) => f.write_str("Key(<redacted>)"),
+        }
+    }
+}
+
+impl AuthKey {
+    /// Read the key from `CORTEX_AUTH_KEY_FILE` or `CORTEX_AUTH_PASSPHRASE`.
+    /// `None` when neither is set, in which case profiles stay plaintext.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        if let Some(path) = std::env::var_os(KEY_FILE_ENV) {
+            return Self::from_key_file(Path::new(&path)).map(Some);
+        }
+        match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) => Self::passphrase(&passphrase).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn passphrase(passphrase: &str) -> anyhow::Result<Self> {
+        if passphrase.is_empty() {
+            anyhow::bail!("Auth passphrase is empty");
+        }
+        Ok(AuthKey::Passphrase(Secret::new(passphrase)))
+    }
+
+    /// Load a base64-encoded 32-byte key.
+    pub fn from_key_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read key file {}: {e}", path.display()))?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(contents.trim())
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Key file {} must hold a base64-encoded 32-byte key \
+                     (e.g. `head -c 32 /dev/urandom | base64`)",
+                    path.display()
+                )
+            })?;
+        Ok(AuthKey::Key(bytes))
+    }
+
+    fn kdf(&self) -> &'static str {
+        match self {
+            AuthKey::Passphrase(_) => KDF_ARGON2ID,
+            AuthKey::Key(_) => KDF_NONE,
+        }
+    }
+
+    fn derive(&self, salt: Option<&[u8]>) -> anyhow::Result<[u8; 32]> {
+        match self {
+            AuthKey::Key(key) => Ok(*key),
+            AuthKey::Passphrase(passphrase) => {
+                let salt = salt.ok_or_else(|| anyhow::anyhow!("Missing salt"))?;
+                let mut key = [0u8; 32];
+                argon2::Argon2::default()
+                    .hash_password_into(passphrase.expose().as_bytes(), salt, &mut key)
+                    .map_err(|e| anyhow::anyhow!("Key derivation failed: {e}"))?;
+                Ok(key)
+            }
+        }
+    }
+}
+
+/// Unencrypted profile details kept in the envelope header, so profiles can
+/// be listed without the key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub profile: String,
+    pub domain: String,
+    pub saved_at: String,
+}
+
+/// On-disk form of an encrypted profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    pub format: String,
+    pub version: u32,
+    pub kdf: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+    nonce: String,
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    ciphertext: String,
+}
+
+impl Envelope {
+    /// Parse `contents` if it is an encrypted profile; `None` for plain JSON.
+    pub fn parse(contents: &str) -> Option<Self> {
+        serde_json::from_str::<Envelope>(contents)
+            .ok()
+            .filter(|e| e.format == FORMAT)
+    }
+
+    fn associated_data(&self) -> Vec<u8> {
+        let m = &self.metadata;
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            self.format, self.version, self.kdf, m.profile, m.domain, m.saved_at
+        )
+        .into_bytes()
+    }
+}
+
+/// Encrypt `plaintext` into an envelope carrying `metadata`.
+pub fn encrypt(key: &AuthKey, metadata: Metadata, plaintext: &[u8]) -> anyhow::Result<Envelope> {
+    let b64 = &base64::engine::general_purpose::STANDARD;
+    let salt = match key {
+        AuthKey::Passphrase(_) => {
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            Some(salt.to_vec())
+        }
+        AuthKey::Key(_) => None,
+    };
+    let derived = key.derive(salt.as_deref())?;
+    let cipher = XChaCha20Poly1305::new(&derived.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let mut envelope = Envelope {
+        format: FORMAT.into(),
+        version: VERSION,
+        kdf: key.kdf().into(),
+        salt: salt.map(|s| b64.encode(s)),
+        nonce: b64.encode(nonce),
+        metadata,
+        ciphertext: String::new(),
+    };
+    let aad = envelope.associated_data();
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+    envelope.ciphertext = b64.encode(ciphertext);
+    Ok(envelope)
+}
+
+/// Decrypt an envelope, failing if the key is wrong or the file was altered.
+pub fn decrypt(key: &AuthKey, envelope: &Envelope) -> anyhow::Result<Vec<u8>> {
+    if envelope.version != VERSION {
+        anyhow::bail!(
+            "Unsupported encrypted profile version {} (this build reads version {VERSION})",
+            envelope.version
+        );
+    }
+    if envelope.kdf != key.kdf() {
+        let needed = match envelope.kdf.as_str() {
+            KDF_ARGON2ID => PASSPHRASE_ENV,
+            _ => KEY_FILE_ENV,
+        };
+        anyhow::bail!("Profile was encrypted with a different kind of key; set {needed}");
+    }
+    let b64 = &base64::engine::general_purpose::STANDARD;
+    let decode = |field: &str, value: &str| {
+        b64.decode(value)
+            .map_err(|_| anyhow::anyhow!("Corrupt encrypted profile: bad {field}"))
+    };
+    let salt = envelope
+        .salt
+        .as_deref()
+        .map(|s| decode("salt", s))
+        .transpose()?;
+    let nonce = decode("nonce", &envelope.nonce)?;
+    if nonce.len() != 24 {
+        anyhow::bail!("Corrupt encrypted profile: bad nonce");
+    }
+    let ciphertext = decode("ciphertext", &envelope.ciphertext)?;
+
+    let derived = key.derive(salt.as_deref())?;
+    let cipher = XChaCha20Poly1305::new(&derived.into());
+    cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &envelope.associated_data(),
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Wrong key, or the encrypted profile was modified"))
+}
@@ -0,0 +1,126 @@
+//! Auth profile health checks for `verify_auth`.
+//!
+//! A probe names a page to load with the profile applied and what to look
+//! for in its snapshot: text or an element role that only shows when logged
+//! in ("Sign out", a `navigation` landmark) or only when logged out ("Log
+//! in"). Probes come from the `[probes]` table of the config file, keyed by
+//! profile name or profile domain, and can be overridden per call.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::dom::{PageSnapshot, SemanticNode};
+
+/// Read from `[probes.<profile or domain>]` in the config file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthProbe {
+    /// Page to load; defaults to the URL the profile was saved from.
+    pub url: Option<String>,
+    /// Present only when logged in.
+    pub logged_in: Option<SnapshotMatch>,
+    /// Present only when logged out; checked first.
+    pub logged_out: Option<SnapshotMatch>,
+}
+
+impl AuthProbe {
+    /// Check that the probe has something to look for.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let empty = |m: &Option<SnapshotMatch>| m.as_ref().is_none_or(SnapshotMatch::is_empty);
+        if empty(&self.logged_in) && empty(&self.logged_out) {
+            anyhow::bail!(
+                "No login check configured: pass logged_in or logged_out, or add one under [probes] in the config file"
+            );
+        }
+        Ok(())
+    }
+
+    /// Fill unset fields from `fallback`.
+    pub fn or(self, fallback: &AuthProbe) -> AuthProbe {
+        AuthProbe {
+            url: self.url.or_else(|| fallback.url.clone()),
+            logged_in: self.logged_in.or_else(|| fallback.logged_in.clone()),
+            logged_out: self.logged_out.or_else(|| fallback.logged_out.clone()),
+        }
+    }
+}
+
+/// Matches a snapshot node by role, text or both. Text matches
+/// case-insensitively anywhere in the node's name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotMatch {
+    /// Text to look for, e.g. "Sign out"
+    pub text: Option<String>,
+    /// ARIA role the node must have, e.g. "button", "navigation", "heading"
+    pub role: Option<String>,
+}
+
+impl SnapshotMatch {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_none() && self.role.is_none()
+    }
+
+    fn matches_node(&self, node: &SemanticNode) -> bool {
+        let role_ok = self.role.as_deref().is_none_or(|role| {
+            let actual = node.role.to_string();
+            let role = role.trim().to_ascii_lowercase();
+            actual == role || actual.starts_with(&format!("{role}["))
+        });
+        let text_ok = self.text.as_deref().is_none_or(|text| {
+            node.name
+                .to_lowercase()
+                .contains(&text.trim().to_lowercase())
+        });
+        role_ok && text_ok
+    }
+
+    /// Whether any node of the snapshot matches.
+    pub fn matches(&self, snapshot: &PageSnapshot) -> bool {
+        fn walk(m: &SnapshotMatch, nodes: &[SemanticNode]) -> bool {
+            nodes
+                .iter()
+                .any(|n| m.matches_node(n) || walk(m, &n.children))
+        }
+        !self.is_empty() && walk(self, &snapshot.nodes)
+    }
+
+    /// e.g. `button "Sign out"`
+    pub fn describe(&self) -> String {
+        match (&self.role, &self.text) {
+            (Some(role), Some(text)) => format!("{role} \"{text}\""),
+            (Some(role), None) => role.clone(),
+            (None, Some(text)) => format!("\"{text}\""),
+            (None, None) => "nothing".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// Includes the reason, e.g. `found "Sign out"`.
+    LoggedIn(String),
+    LoggedOut(String),
+}
+
+/// Decide from a snapshot whether the probe sees a logged-in page. Errors if
+/// the probe has neither predicate.
+pub fn judge(probe: &AuthProbe, snapshot: &PageSnapshot) -> anyhow::Result<Verdict> {
+    probe.validate()?;
+    let logged_in = probe.logged_in.as_ref().filter(|m| !m.is_empty());
+    let logged_out = probe.logged_out.as_ref().filter(|m| !m.is_empty());
+
+    if let Some(m) = logged_out {
+        if m.matches(snapshot) {
+            return Ok(Verdict::LoggedOut(format!("found {}", m.describe())));
+        }
+    }
+    match logged_in {
+        Some(m) if m.matches(snapshot) => Ok(Verdict::LoggedIn(format!("found {}", m.describe()))),
+        Some(m) => Ok(Verdict::LoggedOut(format!("did not find {}", m.describe()))),
+        None => Ok(Verdict::LoggedIn(format!(
+            "did not find {}",
+            logged_out.map(|m| m.describe()).unwrap_or_default()
+        ))),
+    }
+}